@@ -0,0 +1,155 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::clock;
+use crate::window::SlidingWindow;
+
+/// Rules checked unless `--alerts` says otherwise.
+pub const DEFAULT_ALERTS: &str = "p99>5000ms,pending-full>10s";
+
+/// Condition the UI raises an alert on while it holds.
+#[derive(Debug, Clone, Copy)]
+pub enum AlertRule {
+    /// The 99th percentile response time over the rolling window above
+    /// this many ms.
+    P99Above(u64),
+    /// The pending requests at their limit for longer than this.
+    PendingFullFor(Duration),
+}
+
+impl fmt::Display for AlertRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::P99Above(ms) => write!(f, "p99>{ms}ms"),
+            Self::PendingFullFor(duration) => write!(f, "pending-full>{}s", duration.as_secs()),
+        }
+    }
+}
+
+impl FromStr for AlertRule {
+    type Err = String;
+
+    /// Parses `p99>Nms` or `pending-full>Ns`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid alert '{s}', expected p99>Nms or pending-full>Ns");
+        let (metric, threshold) = s.trim().split_once('>').ok_or_else(invalid)?;
+
+        match metric {
+            "p99" => {
+                let ms = threshold.strip_suffix("ms").unwrap_or(threshold);
+                Ok(Self::P99Above(ms.parse().map_err(|_| invalid())?))
+            }
+            "pending-full" => {
+                let seconds = threshold.strip_suffix('s').unwrap_or(threshold);
+                Ok(Self::PendingFullFor(Duration::from_secs(
+                    seconds.parse().map_err(|_| invalid())?,
+                )))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Rules given with `--alerts`.
+#[derive(Debug, Clone)]
+pub struct AlertRules(Vec<AlertRule>);
+
+impl Default for AlertRules {
+    fn default() -> Self {
+        DEFAULT_ALERTS.parse().unwrap()
+    }
+}
+
+impl FromStr for AlertRules {
+    type Err = String;
+
+    /// Parses rules separated by commas, e.g. `p99>2000ms,pending-full>5s`;
+    /// `none` checks nothing.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "none" {
+            return Ok(Self(Vec::new()));
+        }
+
+        let rules = s.split(',').map(str::parse).collect::<Result<_, _>>()?;
+        Ok(Self(rules))
+    }
+}
+
+/// Checks the rules as the simulation runs, keeping the response times the
+/// percentile rules need and which rules are firing.
+pub struct Alerts {
+    rules: Vec<AlertRule>,
+    response_times: SlidingWindow<u64>,
+    /// Since when the pending requests have been at their limit.
+    pending_full_since: Option<Instant>,
+    /// What each firing rule found, by rule.
+    firing: Vec<Option<String>>,
+}
+
+impl Alerts {
+    pub fn new(rules: AlertRules) -> Self {
+        Self {
+            firing: vec![None; rules.0.len()],
+            rules: rules.0,
+            response_times: SlidingWindow::default(),
+            pending_full_since: None,
+        }
+    }
+
+    pub fn record(&mut self, response_time: u64) {
+        self.response_times.push(response_time);
+    }
+
+    /// Checks every rule, returning the ones that started firing, `true`,
+    /// or stopped, `false`.
+    pub fn check(&mut self, pending_full: bool) -> Vec<(AlertRule, bool)> {
+        self.pending_full_since = match self.pending_full_since {
+            Some(since) if pending_full => Some(since),
+            _ if pending_full => Some(clock::now()),
+            _ => None,
+        };
+        let p99 = self.p99();
+
+        let mut changes = Vec::new();
+        for (rule, firing) in self.rules.iter().zip(&mut self.firing) {
+            let found = match *rule {
+                AlertRule::P99Above(threshold) => p99
+                    .filter(|p99| *p99 > threshold)
+                    .map(|p99| format!("p99 {p99}ms over {threshold}ms")),
+                AlertRule::PendingFullFor(duration) => self
+                    .pending_full_since
+                    .map(clock::since)
+                    .filter(|full| *full > duration)
+                    .map(|full| {
+                        format!(
+                            "Pending requests full for {}s over {}s",
+                            full.as_secs(),
+                            duration.as_secs()
+                        )
+                    }),
+            };
+
+            if found.is_some() != firing.is_some() {
+                changes.push((*rule, found.is_some()));
+            }
+            *firing = found;
+        }
+        changes
+    }
+
+    /// What the firing rules found, e.g. `p99 5210ms over 5000ms`.
+    pub fn firing(&self) -> impl Iterator<Item = &str> {
+        self.firing.iter().flatten().map(String::as_str)
+    }
+
+    /// Response time below which 99% of the ones over the rolling window
+    /// fell.
+    fn p99(&self) -> Option<u64> {
+        let mut sorted: Vec<u64> = self.response_times.iter().map(|(_, ms)| *ms).collect();
+        sorted.sort_unstable();
+
+        let idx = ((sorted.len() as f64 * 0.99).ceil() as usize).checked_sub(1)?;
+        sorted.get(idx).copied()
+    }
+}
@@ -0,0 +1,76 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use ratatui::buffer::Buffer;
+
+/// Whether the UI is drawn with plain ASCII only, set by `--ascii`.
+static ASCII: AtomicBool = AtomicBool::new(false);
+
+pub fn enable() {
+    ASCII.store(true, Ordering::SeqCst);
+}
+
+pub fn enabled() -> bool {
+    ASCII.load(Ordering::SeqCst)
+}
+
+/// Spells out the characters outside ASCII that text printed or shown
+/// outside a frame's cells has room for, e.g. `±` as `+/-`.
+pub fn spell_out(text: String) -> String {
+    if enabled() {
+        text.replace('±', "+/-")
+    } else {
+        text
+    }
+}
+
+/// Replaces every character outside ASCII in a rendered frame, so borders,
+/// charts, gauges and symbols all come out as plain text whichever widget
+/// drew them.
+pub fn asciify(buffer: &mut Buffer) {
+    let width = buffer.area.width as usize;
+    for idx in 0..buffer.content.len() {
+        let symbol = buffer.content[idx].symbol();
+        if symbol.is_ascii() {
+            continue;
+        }
+
+        let replacement = symbol.chars().next().map_or(' ', to_ascii);
+        buffer.content[idx].set_char(replacement);
+        // A wide character leaves the next cell empty, which would shift
+        // the rest of the row once it takes a single column.
+        if (idx + 1) % width != 0 && buffer.content[idx + 1].symbol().is_empty() {
+            buffer.content[idx + 1].set_char(' ');
+        }
+    }
+}
+
+fn to_ascii(ch: char) -> char {
+    match ch {
+        // Box drawing.
+        '─' | '━' | '═' | '┄' | '┅' | '┈' | '┉' | '╌' | '╍' => '-',
+        '│' | '┃' | '║' | '┆' | '┇' | '┊' | '┋' | '╎' | '╏' => '|',
+        '\u{2500}'..='\u{257F}' => '+',
+        // Bars, sparklines and gauges, by how much of the cell they fill.
+        '▁' | '▂' | '▃' | '▏' | '▎' | '▍' => '.',
+        '\u{2580}'..='\u{259F}' => '#',
+        // Braille chart dots, by how many there are.
+        '\u{2800}' => ' ',
+        '\u{2801}'..='\u{28FF}' if (ch as u32 - 0x2800).count_ones() <= 2 => '.',
+        '\u{2801}'..='\u{28FF}' => '*',
+        '⮜' | '◀' => '<',
+        '⮞' | '▸' | '▶' | '→' => '>',
+        '⮝' | '⇧' | '▲' | '↑' => '^',
+        '⮟' | '⇣' | '▼' | '↓' => 'v',
+        '±' => '~',
+        'λ' => 'L',
+        'Σ' => 'S',
+        '²' => '2',
+        '≡' => '=',
+        '⑂' => 'Y',
+        '↻' => '@',
+        '⛓' => '&',
+        '·' | '…' => '.',
+        '•' => '*',
+        _ => '?',
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::VecDeque;
+use std::str::FromStr;
+use std::time::Instant;
+
+use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+
+use crate::SystemEvent;
+use crate::clock::{self, Ticker};
+use crate::context;
+use crate::server::{self, INITIAL_SERVERS, MAX_SERVERS};
+
+/// When the autoscaler adds and retires servers once it's turned on.
+#[derive(Debug, Clone)]
+pub struct ScalingPolicy {
+    pub min_servers: usize,
+    pub max_servers: usize,
+    /// Scale up when this many requests are pending...
+    pub scale_up_pending: usize,
+    /// ...or when the average response time (ms) reaches this.
+    pub scale_up_wait: f64,
+    /// Scale down only when both pending requests and response time are at or
+    /// below these.
+    pub scale_down_pending: usize,
+    pub scale_down_wait: f64,
+    /// Minimum time between two scaling actions.
+    pub cooldown: Duration,
+    /// Window over which the average response time is computed.
+    pub window: Duration,
+    /// Retire the most expensive server when scaling down, rather than the
+    /// newest one.
+    pub retire_costliest: bool,
+}
+
+pub const DEFAULT_POLICY: ScalingPolicy = ScalingPolicy {
+    min_servers: 1,
+    max_servers: MAX_SERVERS,
+    scale_up_pending: 10,
+    scale_up_wait: 3000.0,
+    scale_down_pending: 1,
+    scale_down_wait: 1000.0,
+    cooldown: Duration::from_secs(5),
+    window: Duration::from_secs(10),
+    retire_costliest: true,
+};
+
+impl Default for ScalingPolicy {
+    fn default() -> Self {
+        DEFAULT_POLICY
+    }
+}
+
+impl FromStr for ScalingPolicy {
+    type Err = String;
+
+    /// Parses `<setting>=<value>` pairs separated by commas, overriding the
+    /// default policy, e.g. `min=2,max=6,up-pending=20`. The settings are
+    /// `min` and `max` servers, `up-pending`, `up-wait` (ms), `down-pending`,
+    /// `down-wait` (ms), `cooldown` and `window` (seconds) and `retire`,
+    /// `costliest` or `newest`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut policy = DEFAULT_POLICY;
+
+        for pair in s.split(',') {
+            let invalid = || format!("invalid scaling setting '{pair}'");
+            let (setting, value) = pair.trim().split_once('=').ok_or_else(invalid)?;
+            let count = || value.parse::<usize>().map_err(|_| invalid());
+            let number = || {
+                value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|n| n.is_finite() && *n >= 0.0)
+                    .ok_or_else(invalid)
+            };
+            match setting {
+                "min" => policy.min_servers = count()?,
+                "max" => policy.max_servers = count()?,
+                "up-pending" => policy.scale_up_pending = count()?,
+                "up-wait" => policy.scale_up_wait = number()?,
+                "down-pending" => policy.scale_down_pending = count()?,
+                "down-wait" => policy.scale_down_wait = number()?,
+                "cooldown" => policy.cooldown = Duration::from_secs_f64(number()?),
+                "window" => policy.window = Duration::from_secs_f64(number()?),
+                "retire" => {
+                    policy.retire_costliest = match value {
+                        "costliest" => true,
+                        "newest" => false,
+                        _ => return Err(invalid()),
+                    }
+                }
+                _ => {
+                    return Err(format!(
+                        "unknown scaling setting '{setting}', expected min, max, up-pending, \
+                         up-wait, down-pending, down-wait, cooldown, window or retire"
+                    ));
+                }
+            }
+        }
+
+        if policy.min_servers == 0
+            || policy.max_servers > MAX_SERVERS
+            || policy.min_servers > policy.max_servers
+        {
+            return Err(format!(
+                "invalid scaling policy '{s}', expected 1 <= min <= max <= {MAX_SERVERS}"
+            ));
+        }
+        if policy.scale_down_pending > policy.scale_up_pending
+            || policy.scale_down_wait > policy.scale_up_wait
+        {
+            return Err(format!(
+                "invalid scaling policy '{s}', scaling down must take less load than scaling up"
+            ));
+        }
+        if policy.window.is_zero() {
+            return Err(format!(
+                "invalid scaling policy '{s}', the window can't be empty"
+            ));
+        }
+
+        Ok(policy)
+    }
+}
+
+pub fn spawn_autoscaler(
+    event_tx: Sender<SystemEvent>,
+    mut event_rx: Receiver<SystemEvent>,
+    policy: ScalingPolicy,
+) -> JoinHandle<()> {
+    context::spawn(async move {
+        let mut enabled = false;
+        let mut paused = false;
+        let mut server_ids: Vec<u64> = (1..=INITIAL_SERVERS).collect();
+        let mut pending = 0usize;
+        let mut response_times: VecDeque<(Instant, f64)> = VecDeque::new();
+        let mut last_action = clock::now();
+
+        let mut ticker = Ticker::new(Duration::from_secs(1));
+
+        loop {
+            while let Ok(event) = event_rx.try_recv() {
+                match event {
+                    SystemEvent::RequestCreated(_)
+                    | SystemEvent::RequestRequeued { .. }
+                    | SystemEvent::RequestForwarded { .. } => {
+                        pending += 1;
+                    }
+                    SystemEvent::RequestAssigned { .. }
+                    | SystemEvent::RequestRejected { .. }
+                    | SystemEvent::RequestDeduplicated { .. }
+                    | SystemEvent::RequestExpired {
+                        server_id: None, ..
+                    } => {
+                        pending = pending.saturating_sub(1);
+                    }
+                    SystemEvent::RequestProcessed { created_at, .. } => {
+                        let now = clock::now();
+                        response_times
+                            .push_back((now, now.duration_since(created_at).as_millis() as f64));
+                    }
+                    SystemEvent::ServerAdded { server_id } => server_ids.push(server_id),
+                    SystemEvent::ServerRemoved { server_id } => {
+                        server_ids.retain(|id| *id != server_id);
+                    }
+                    SystemEvent::ConfigChanged(update) => {
+                        if let Some(autoscaling) = update.autoscaling {
+                            enabled = autoscaling;
+                        }
+                        if let Some(value) = update.paused {
+                            paused = value;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            while response_times
+                .front()
+                .is_some_and(|(at, _)| clock::since(*at) > policy.window)
+            {
+                response_times.pop_front();
+            }
+
+            if enabled && !paused && clock::since(last_action) >= policy.cooldown {
+                let avg_wait = if response_times.is_empty() {
+                    0.0
+                } else {
+                    response_times.iter().map(|(_, wait)| wait).sum::<f64>()
+                        / response_times.len() as f64
+                };
+
+                let overloaded =
+                    pending >= policy.scale_up_pending || avg_wait >= policy.scale_up_wait;
+                let underloaded =
+                    pending <= policy.scale_down_pending && avg_wait <= policy.scale_down_wait;
+
+                let reason = format!("pending {}, avg wait {:.0}ms", pending, avg_wait);
+
+                let action = if overloaded && server_ids.len() < policy.max_servers {
+                    let server_id = server::next_server_id();
+                    Some((
+                        SystemEvent::ServerAdded { server_id },
+                        format!("adding Server {} ({})", server_id, reason),
+                    ))
+                } else if underloaded && server_ids.len() > policy.min_servers {
+                    let retired = if policy.retire_costliest {
+                        server_ids.iter().max_by(|a, b| {
+                            server::cost_per_ms(**a)
+                                .total_cmp(&server::cost_per_ms(**b))
+                                .then(a.cmp(b))
+                        })
+                    } else {
+                        server_ids.iter().max()
+                    };
+
+                    retired.map(|&server_id| {
+                        (
+                            SystemEvent::ServerRemoved { server_id },
+                            format!("retiring Server {} ({})", server_id, reason),
+                        )
+                    })
+                } else {
+                    None
+                };
+
+                if let Some((event, message)) = action {
+                    event_tx.send(SystemEvent::Scaled(message)).await.ok();
+                    event_tx.send(event).await.ok();
+                    last_action = clock::now();
+                }
+            }
+
+            ticker.tick().await;
+        }
+    })
+}
@@ -0,0 +1,202 @@
+use std::env;
+use std::str::FromStr;
+use std::time::Duration;
+
+use load_balancer::alert::AlertRules;
+use load_balancer::autoscaler::ScalingPolicy;
+use load_balancer::clock::{MAX_SPEED, MIN_SPEED};
+use load_balancer::request::{PriorityMix, RetryPolicy, ServiceTimeDistribution, TagMix};
+use load_balancer::schedule::{BurstPattern, RateSchedule};
+use load_balancer::trace::{self, TraceEntry};
+
+const USAGE: &str = "usage: load-balancer [--schedule <offset:rate,...>] [--schedule-file <path>] [--burst <quiet:spike:multiplier>] [--trace <path>] [--record <path>] [--service-times <distribution>] [--tags <name:weight,...>] [--priorities <high,normal,low>] [--listen <port>] [--histogram <path>] [--metrics-csv <path>] [--queue-history <path>] [--timeline <path>] [--parquet <path>] [--summary <path>] [--statsd <host:port>] [--otlp <host:port>] [--sqlite <path>] [--warmup <seconds>] [--window <seconds>] [--speed <factor>] [--theme <name>] [--ascii] [--alerts <rule,...>] [--sita-cutoffs <ms,...>] [--weights <weight,...>] [--contention <slowdown,...>] [--retry <attempts:backoff_ms:multiplier>] [--scaling <setting=value,...>] [--seed <n>]";
+
+#[derive(Default)]
+pub struct Args {
+    /// Arrival rate schedule the generator follows instead of the fixed rate.
+    pub schedule: Option<RateSchedule>,
+    /// Quiet periods and spikes of the burst mode.
+    pub burst: BurstPattern,
+    /// Recorded requests the generator replays instead of generating its own.
+    pub trace: Option<Vec<TraceEntry>>,
+    /// File the generated requests are written to on exit.
+    pub record: Option<String>,
+    /// Distribution the generated requests' service times are sampled from.
+    pub service_times: ServiceTimeDistribution,
+    /// Tags the generated requests carry.
+    pub tags: TagMix,
+    /// Shares of the generated requests with each priority.
+    pub priorities: PriorityMix,
+    /// Local port external clients inject requests through.
+    pub listen: Option<u16>,
+    /// File the latency histogram's buckets are written to on exit.
+    pub histogram: Option<String>,
+    /// File a row of key metrics is appended to every second.
+    pub metrics_csv: Option<String>,
+    /// File the sampled queue lengths and workloads are written to on exit.
+    pub queue_history: Option<String>,
+    /// File the phases of every processed request are written to on exit.
+    pub timeline: Option<String>,
+    /// Parquet file the same records are written to on exit.
+    pub parquet: Option<String>,
+    /// File the end-of-run summary is written to as JSON.
+    pub summary: Option<String>,
+    /// StatsD server request counters and queue depths are sent to.
+    pub statsd: Option<String>,
+    /// OpenTelemetry collector a trace of every request is exported to.
+    pub otlp: Option<String>,
+    /// SQLite database every event and periodic metrics are stored in.
+    pub sqlite: Option<String>,
+    /// Time after the start the statistics are reset at, so they only cover
+    /// the steady state.
+    pub warmup: Option<Duration>,
+    /// Span of the rolling metrics instead of the default 10 seconds.
+    pub window: Option<Duration>,
+    /// Factor simulated time runs at relative to real time.
+    pub speed: Option<f64>,
+    /// Name of the UI's color theme.
+    pub theme: Option<String>,
+    /// Draws the UI with plain ASCII characters only.
+    pub ascii: bool,
+    /// Conditions the UI raises an alert on.
+    pub alerts: AlertRules,
+    /// Service-time bounds of the Size Interval mode's intervals.
+    pub sita_cutoffs: Option<Vec<u64>>,
+    /// Relative weights of the servers for the Weighted Random mode.
+    pub weights: Option<Vec<f64>>,
+    /// Slowdown of each server per waiting request under contention.
+    pub contention: Option<Vec<f64>>,
+    /// Policy failed requests are retried with once retries are on.
+    pub retry: RetryPolicy,
+    /// When the autoscaler adds and retires servers once it's on.
+    pub scaling: ScalingPolicy,
+    /// Seed of the random draws.
+    pub seed: Option<u64>,
+}
+
+pub fn parse_args() -> Result<Args, String> {
+    let mut args = Args::default();
+    let mut argv = env::args().skip(1);
+
+    while let Some(arg) = argv.next() {
+        let mut value = || {
+            argv.next()
+                .ok_or_else(|| format!("{arg} needs a value\n{USAGE}"))
+        };
+
+        match arg.as_str() {
+            "--schedule" => args.schedule = Some(RateSchedule::parse(&value()?)?),
+            "--schedule-file" => args.schedule = Some(RateSchedule::load(&value()?)?),
+            "--burst" => args.burst = BurstPattern::parse(&value()?)?,
+            "--trace" => args.trace = Some(trace::load(&value()?)?),
+            "--record" => args.record = Some(value()?),
+            "--service-times" => args.service_times = value()?.parse()?,
+            "--tags" => args.tags = value()?.parse()?,
+            "--priorities" => args.priorities = value()?.parse()?,
+            "--listen" => {
+                let port = value()?;
+                args.listen = Some(port.parse().map_err(|_| format!("invalid port '{port}'"))?);
+            }
+            "--histogram" => args.histogram = Some(value()?),
+            "--metrics-csv" => args.metrics_csv = Some(value()?),
+            "--queue-history" => args.queue_history = Some(value()?),
+            "--timeline" => args.timeline = Some(value()?),
+            "--parquet" if cfg!(feature = "parquet") => args.parquet = Some(value()?),
+            "--parquet" => {
+                return Err("--parquet needs a build with the parquet feature: \
+                     cargo run --features parquet"
+                    .to_string());
+            }
+            "--summary" => args.summary = Some(value()?),
+            "--statsd" => args.statsd = Some(value()?),
+            "--otlp" => args.otlp = Some(value()?),
+            "--sqlite" if cfg!(feature = "sqlite") => args.sqlite = Some(value()?),
+            "--sqlite" => {
+                return Err("--sqlite needs a build with the sqlite feature: \
+                     cargo run --features sqlite"
+                    .to_string());
+            }
+            "--warmup" => {
+                let seconds = value()?;
+                let seconds: u64 = seconds
+                    .parse()
+                    .map_err(|_| format!("invalid warm-up '{seconds}'"))?;
+                args.warmup = Some(Duration::from_secs(seconds));
+            }
+            "--window" => {
+                let span = value()?;
+                let seconds: u64 = span
+                    .strip_suffix('s')
+                    .unwrap_or(&span)
+                    .parse()
+                    .ok()
+                    .filter(|seconds| *seconds > 0)
+                    .ok_or_else(|| format!("invalid window '{span}'"))?;
+                args.window = Some(Duration::from_secs(seconds));
+            }
+            "--speed" => {
+                let factor = value()?;
+                let speed: f64 = factor
+                    .strip_suffix('x')
+                    .unwrap_or(&factor)
+                    .parse()
+                    .ok()
+                    .filter(|speed| (MIN_SPEED..=MAX_SPEED).contains(speed))
+                    .ok_or_else(|| {
+                        format!("invalid speed '{factor}', expected {MIN_SPEED} to {MAX_SPEED}")
+                    })?;
+                args.speed = Some(speed);
+            }
+            "--theme" => args.theme = Some(value()?),
+            "--ascii" => args.ascii = true,
+            "--alerts" => args.alerts = value()?.parse()?,
+            "--sita-cutoffs" => {
+                let list = value()?;
+                let cutoffs: Vec<u64> = parse_list(&list)
+                    .filter(|cutoffs| cutoffs.is_sorted_by(|a, b| a < b))
+                    .ok_or_else(|| {
+                        format!("invalid cutoffs '{list}', expected ascending ms such as 100,300")
+                    })?;
+                args.sita_cutoffs = Some(cutoffs);
+            }
+            "--weights" => {
+                let list = value()?;
+                let weights: Vec<f64> = parse_list(&list)
+                    .filter(|weights| weights.iter().all(|weight| *weight > 0.0))
+                    .ok_or_else(|| {
+                        format!("invalid weights '{list}', expected positive numbers such as 3,2,1")
+                    })?;
+                args.weights = Some(weights);
+            }
+            "--contention" => {
+                let list = value()?;
+                let contention: Vec<f64> = parse_list(&list)
+                    .filter(|contention| contention.iter().all(|c| *c >= 0.0))
+                    .ok_or_else(|| {
+                        format!(
+                            "invalid contention '{list}', expected non-negative numbers such as \
+                             0.1,0.1,0.05"
+                        )
+                    })?;
+                args.contention = Some(contention);
+            }
+            "--retry" => args.retry = value()?.parse()?,
+            "--scaling" => args.scaling = value()?.parse()?,
+            "--seed" => {
+                let seed = value()?;
+                args.seed = Some(seed.parse().map_err(|_| format!("invalid seed '{seed}'"))?);
+            }
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ => return Err(format!("unknown argument '{arg}'\n{USAGE}")),
+        }
+    }
+
+    Ok(args)
+}
+
+/// Parses values separated by commas, e.g. `100,300`, none of them missing.
+fn parse_list<T: FromStr>(list: &str) -> Option<Vec<T>> {
+    list.split(',')
+        .map(|item| item.trim().parse().ok())
+        .collect()
+}
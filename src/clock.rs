@@ -0,0 +1,108 @@
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use tokio::time::{self, Interval};
+
+use crate::context;
+
+/// Speeds the simulation can run at, slowest first.
+pub const SPEEDS: [f64; 6] = [0.25, 0.5, 1.0, 2.0, 5.0, 10.0];
+pub const MIN_SPEED: f64 = SPEEDS[0];
+pub const MAX_SPEED: f64 = SPEEDS[SPEEDS.len() - 1];
+
+/// Where simulated time was when the speed last changed.
+pub(crate) struct Clock {
+    real: Instant,
+    simulated: Instant,
+    speed: f64,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        let now = Instant::now();
+        Self {
+            real: now,
+            simulated: now,
+            speed: 1.0,
+        }
+    }
+}
+
+/// Simulated time, which runs `speed()` times as fast as real time. Every
+/// instant the simulation keeps comes from here, so requests, servers and
+/// the statistics all agree on it.
+pub fn now() -> Instant {
+    context::with(|context| {
+        let clock = context.clock.lock().unwrap();
+        clock.simulated + clock.real.elapsed().mul_f64(clock.speed)
+    })
+}
+
+/// Simulated time since `at`.
+pub fn since(at: Instant) -> Duration {
+    now().saturating_duration_since(at)
+}
+
+pub fn speed() -> f64 {
+    context::with(|context| context.clock.lock().unwrap().speed)
+}
+
+pub fn set_speed(speed: f64) {
+    context::with(|context| {
+        let mut clock = context.clock.lock().unwrap();
+        let real = Instant::now();
+        let passed = real.duration_since(clock.real).mul_f64(clock.speed);
+        clock.simulated += passed;
+        clock.real = real;
+        clock.speed = speed;
+    });
+}
+
+/// The next speed in `SPEEDS` up or down from the current one, if any.
+pub fn step_speed(current: f64, faster: bool) -> Option<f64> {
+    if faster {
+        SPEEDS.into_iter().find(|speed| *speed > current)
+    } else {
+        SPEEDS.into_iter().rev().find(|speed| *speed < current)
+    }
+}
+
+pub fn count_step() {
+    context::with(|context| context.steps.fetch_add(1, Ordering::SeqCst));
+}
+
+/// Steps the servers have taken while not paused, every 10ms of simulated
+/// time.
+pub fn steps() -> u64 {
+    context::with(|context| context.steps.load(Ordering::SeqCst))
+}
+
+/// Waits for `duration` of simulated time.
+pub async fn sleep(duration: Duration) {
+    time::sleep(duration.div_f64(speed())).await;
+}
+
+/// Ticks every `period` of simulated time, following changes of speed.
+pub struct Ticker {
+    period: Duration,
+    speed: f64,
+    interval: Interval,
+}
+
+impl Ticker {
+    pub fn new(period: Duration) -> Self {
+        let speed = speed();
+        Self {
+            period,
+            speed,
+            interval: time::interval(period.div_f64(speed)),
+        }
+    }
+
+    pub async fn tick(&mut self) {
+        if speed() != self.speed {
+            *self = Self::new(self.period);
+        }
+        self.interval.tick().await;
+    }
+}
@@ -0,0 +1,79 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, AtomicUsize};
+use std::sync::{Arc, Mutex};
+
+use tokio::task::JoinHandle;
+
+use crate::clock::Clock;
+use crate::request::PriorityMix;
+use crate::server::{DEFAULT_QUEUE_CAPACITY, INITIAL_SERVERS};
+use crate::strategy::StrategyEntry;
+use crate::window::DEFAULT_WINDOW;
+
+/// Clock and settings of one simulation, shared by its tasks, its state and
+/// its frontend but not by any other simulation in the same process. The
+/// functions of [`clock`](crate::clock), [`window`](crate::window) and
+/// [`server`](crate::server) read them from the context current where
+/// they're called: the simulation's tasks run in theirs, and code outside
+/// them enters it with [`Context::enter`].
+pub struct Context {
+    pub(crate) clock: Mutex<Clock>,
+    /// Steps the servers have taken, the finest tick of the simulation.
+    pub(crate) steps: AtomicU64,
+    /// Span of the rolling metrics, in ms.
+    pub(crate) window_ms: AtomicU64,
+    /// Requests every server's queue holds, set from the Config view.
+    pub(crate) queue_capacity: AtomicUsize,
+    pub(crate) next_server_id: AtomicU64,
+    /// Slowdown of each server per waiting request, by id, under contention.
+    pub(crate) contention: Vec<f64>,
+    /// Shares of the requests drawn with each priority.
+    pub(crate) priorities: PriorityMix,
+    /// Strategies the allocator can balance with, the built-in ones first.
+    pub(crate) strategies: Vec<StrategyEntry>,
+}
+
+tokio::task_local! {
+    static CURRENT: Arc<Context>;
+}
+
+impl Context {
+    pub(crate) fn new(
+        contention: Vec<f64>,
+        priorities: PriorityMix,
+        strategies: Vec<StrategyEntry>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            clock: Mutex::new(Clock::default()),
+            steps: AtomicU64::new(0),
+            window_ms: AtomicU64::new(DEFAULT_WINDOW.as_millis() as u64),
+            queue_capacity: AtomicUsize::new(DEFAULT_QUEUE_CAPACITY),
+            next_server_id: AtomicU64::new(INITIAL_SERVERS + 1),
+            contention,
+            priorities,
+            strategies,
+        })
+    }
+
+    /// Runs `f` with this context current, e.g. a frontend reading times or
+    /// rolling metrics between events.
+    pub fn enter<R>(self: &Arc<Self>, f: impl FnOnce() -> R) -> R {
+        CURRENT.sync_scope(Arc::clone(self), f)
+    }
+}
+
+/// Calls `f` with the context of the simulation the caller runs in.
+pub(crate) fn with<R>(f: impl FnOnce(&Arc<Context>) -> R) -> R {
+    CURRENT
+        .try_with(f)
+        .expect("no simulation context: call from its tasks or within Context::enter")
+}
+
+/// Spawns a task of the current simulation, which runs in its context.
+pub(crate) fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    tokio::spawn(CURRENT.scope(with(Arc::clone), future))
+}
@@ -1,24 +1,46 @@
-use crate::{
-    PENDING_REQUESTS_LIMIT, ServerChoiceMode, SystemConfig, SystemEvent, SystemState, SystemStats,
-    request::Request, server::ServerState,
+use crate::theme::{self, theme};
+use crate::{ascii, snapshot};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use load_balancer::{
+    ClassStats, ConfigUpdate, PENDING_REQUESTS_LIMIT, ServerStats, Simulation, Slowdowns,
+    SystemConfig, SystemEvent, SystemState, SystemStats,
+    alert::AlertRules,
+    clock,
+    histogram::LatencyHistogram,
+    is_blocked,
+    request::{Priority, REQ_SIZES, REQ_TYPES, Request, RequestSize, RequestType},
+    server::{self, MAX_QUEUE_CAPACITY, MAX_SERVERS, ServerState, ServerStatus},
+    slo::{SLO_OBJECTIVE, SLO_TARGETS, SLO_WINDOW, SloTracker},
+    state::{LogLine, THROUGHPUT_HISTORY},
+    strategy::ServerChoiceMode,
+    summary::Summary,
+    tenant::{TENANTS, TenantTracker},
+    timeseries::QueueHistory,
+    window::{SlidingWindow, window},
 };
-use crossterm::event::{self, Event, KeyCode, KeyEventKind};
 use ratatui::{
     Frame, Terminal, backend,
     layout::{self, Constraint, Layout, Rect},
     prelude::CrosstermBackend,
     style::{self, Style},
-    text,
-    widgets::{Block, List, ListItem, ListState, Paragraph},
+    symbols, text,
+    widgets::{
+        Axis, Bar, BarChart, BarGroup, Block, BorderType, Cell, Chart, Clear, Dataset, Gauge,
+        GraphType, List, ListItem, ListState, Paragraph, Row, Sparkline, Table, Tabs,
+    },
 };
 use std::{
-    collections::VecDeque,
+    collections::{HashMap, VecDeque},
     io,
-    sync::atomic::{AtomicUsize, Ordering},
+    ops::RangeInclusive,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, AtomicU16, AtomicUsize, Ordering},
+    },
     thread,
     time::{Duration, Instant},
 };
-use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::sync::mpsc::Sender;
 
 struct AtomicRect {
     x: AtomicUsize,
@@ -44,6 +66,15 @@ impl AtomicRect {
         self.height.store(rect.height as usize, Ordering::SeqCst);
     }
 
+    fn rect(&self) -> Rect {
+        Rect::new(
+            self.x.load(Ordering::SeqCst) as u16,
+            self.y.load(Ordering::SeqCst) as u16,
+            self.width.load(Ordering::SeqCst) as u16,
+            self.height.load(Ordering::SeqCst) as u16,
+        )
+    }
+
     fn contains(&self, x: u16, y: u16) -> bool {
         let self_x = self.x.load(Ordering::SeqCst) as u16;
         let self_y = self.y.load(Ordering::SeqCst) as u16;
@@ -54,64 +85,150 @@ impl AtomicRect {
     }
 }
 
-static SELECTED_LOG: AtomicUsize = AtomicUsize::new(0);
-
-static SERVER_AREAS: [AtomicRect; 3] = [AtomicRect::new(), AtomicRect::new(), AtomicRect::new()];
-static SERVER_SCROLL: [AtomicUsize; 3] = [
-    AtomicUsize::new(0),
-    AtomicUsize::new(0),
-    AtomicUsize::new(0),
+/// Saturation past which the gauge turns yellow...
+const SATURATION_WARNING: f64 = 0.5;
+/// ...and red.
+const SATURATION_CRITICAL: f64 = 0.8;
+/// A queue whose oldest request waited this long gets a yellow border...
+const AGE_WARNING: Duration = Duration::from_secs(5);
+/// ...and a red one past this.
+const AGE_CRITICAL: Duration = Duration::from_secs(15);
+/// How long a crashed server blinks.
+const CRASH_FLASH: Duration = Duration::from_secs(2);
+/// How long, in real time, a request and the server it was just assigned to
+/// stay highlighted, so the routing can be followed.
+const ASSIGN_FLASH: Duration = Duration::from_millis(400);
+/// How long, in real time, the alert banner shows in each of its two styles,
+/// so it flashes.
+const ALERT_FLASH: Duration = Duration::from_millis(500);
+/// Views the UI switches between with Tab or F1 to F5.
+const TABS: [&str; 5] = ["Overview", "Servers", "Statistics", "Logs", "Config"];
+const CONFIG_TAB: usize = 4;
+/// Settings the Config view's form edits, in order.
+const FORM_FIELDS: [&str; 6] = [
+    "Arrival Rate",
+    "Policy",
+    "Servers",
+    "Queue Capacity",
+    "Speed",
+    "Seed",
 ];
+const SEED_FIELD: usize = 5;
+/// Span (s) the queue history charts cover.
+const HISTORY_SPAN: f64 = 120.0;
+/// Limits (%) of the overview's splits and how much a key moves them.
+const MAIN_SPLIT_RANGE: RangeInclusive<u16> = 40..=85;
+const REQUESTS_SPLIT_RANGE: RangeInclusive<u16> = 15..=70;
+const SPLIT_STEP: u16 = 5;
+/// Span (s) the overview's live queue length chart covers.
+const LIVE_QUEUE_SPAN: f64 = 30.0;
+
+/// Number of the log line selected while scrolled back through the log.
+static SELECTED_LOG: AtomicUsize = AtomicUsize::new(0);
+/// Whether the log stays on the newest line, like `tail -f`.
+static FOLLOW_LOGS: AtomicBool = AtomicBool::new(true);
+static SELECTED_TAB: AtomicUsize = AtomicUsize::new(0);
+static SHOW_HELP: AtomicBool = AtomicBool::new(false);
+static SHOW_SERVER_DETAIL: AtomicBool = AtomicBool::new(false);
+/// Field of the Config view's form the arrow keys and typing go to.
+static FORM_FIELD: AtomicUsize = AtomicUsize::new(0);
+/// Value typed into the focused field, set with Enter.
+static FORM_INPUT: Mutex<String> = Mutex::new(String::new());
+/// Set by `S` until the next frame is drawn and saved.
+static SAVE_SNAPSHOT: AtomicBool = AtomicBool::new(false);
+/// Set by `q` until the user confirms or cancels.
+static CONFIRM_QUIT: AtomicBool = AtomicBool::new(false);
+static SELECTED_SERVER: AtomicUsize = AtomicUsize::new(0);
+/// Page of the pending requests shown, kept within the pages there are when
+/// rendering.
+static REQUESTS_PAGE: AtomicUsize = AtomicUsize::new(0);
+/// Id of the pending request clicked on, shown in detail until it leaves
+/// the pending list or is clicked again.
+static SELECTED_REQUEST: Mutex<Option<usize>> = Mutex::new(None);
+/// Where each pending request on the page is, to select them by clicking.
+static REQUEST_CELLS: Mutex<Vec<(Rect, usize)>> = Mutex::new(Vec::new());
+/// Where the log lines are and how many newer ones are scrolled past, to
+/// select them by clicking.
+static LOG_AREA: AtomicRect = AtomicRect::new();
+static LOG_OFFSET: AtomicUsize = AtomicUsize::new(0);
+static TABS_AREA: AtomicRect = AtomicRect::new();
+
+/// Share (%) of the overview's width the requests and servers take, the rest
+/// going to the configuration and statistics.
+static MAIN_SPLIT: AtomicU16 = AtomicU16::new(70);
+/// Share (%) of the overview's height the pending requests take.
+static REQUESTS_SPLIT: AtomicU16 = AtomicU16::new(40);
+/// Where the overview's panes are, to resize them by dragging their borders.
+static PROCESSING_AREA: AtomicRect = AtomicRect::new();
+static REQUESTS_AREA: AtomicRect = AtomicRect::new();
+static DRAGGING_MAIN_SPLIT: AtomicBool = AtomicBool::new(false);
+static DRAGGING_REQUESTS_SPLIT: AtomicBool = AtomicBool::new(false);
+
+static SERVER_AREAS: [AtomicRect; MAX_SERVERS] = [const { AtomicRect::new() }; MAX_SERVERS];
+static SERVER_SCROLL: [AtomicUsize; MAX_SERVERS] = [const { AtomicUsize::new(0) }; MAX_SERVERS];
+
+/// Runs the UI over the simulation until the user quits, returning the
+/// final state, whose statistics start over after the warm-up, if any, and
+/// whose alerts are raised when the rules hold.
+pub fn run_ui(
+    simulation: &mut Simulation,
+    warmup: Option<Duration>,
+    alerts: AlertRules,
+) -> io::Result<SystemState> {
+    // The clock and the window the UI reads are the simulation's own.
+    let context = simulation.context();
+    context.enter(|| {
+        let event_tx = simulation.sender();
+        let mut terminal = init_terminal()?;
+
+        let mut system_state = SystemState::new(simulation, warmup, alerts);
+
+        let mut last_frame = Instant::now();
+        let frame_rate = Duration::from_millis(33); // 30 FPS
+
+        loop {
+            let elapsed = last_frame.elapsed();
+            if elapsed < frame_rate {
+                thread::sleep(frame_rate - elapsed);
+            }
+            last_frame = Instant::now();
 
-pub fn run_ui(event_tx: Sender<SystemEvent>, mut ui_rx: Receiver<SystemEvent>) -> io::Result<()> {
-    let mut terminal = init_terminal()?;
-
-    let mut system_state = SystemState {
-        configs: SystemConfig {
-            arrival_rate: 3.0,
-            choice_mode: crate::ServerChoiceMode::Random,
-        },
-        pending_requests: VecDeque::new(),
-        servers: [
-            ServerState::new(1),
-            ServerState::new(2),
-            ServerState::new(3),
-        ],
-        logs: Vec::with_capacity(100),
-        stats: SystemStats {
-            total_requests: 0,
-            processed_requests: 0,
-            avg_wait_time: 0.0,
-            throughput: 0.0,
-            throughput_window: Vec::with_capacity(30),
-        },
-    };
+            simulation.update(&mut system_state);
 
-    let mut last_frame = Instant::now();
-    let frame_rate = Duration::from_millis(33); // 30 FPS
+            let frame = terminal.draw(|frame| {
+                render_system_ui(frame, &system_state);
+                if ascii::enabled() {
+                    ascii::asciify(frame.buffer_mut());
+                }
+            })?;
 
-    loop {
-        let elapsed = last_frame.elapsed();
-        if elapsed < frame_rate {
-            thread::sleep(frame_rate - elapsed);
-        }
-        last_frame = Instant::now();
+            if SAVE_SNAPSHOT.swap(false, Ordering::SeqCst) {
+                match snapshot::save(frame.buffer) {
+                    Ok(path) => {
+                        system_state.log(format!("Saved a snapshot of the screen to {path}"))
+                    }
+                    Err(e) => system_state.log_failure(e),
+                }
+            }
 
-        while let Ok(event) = ui_rx.try_recv() {
-            update_system_state(&mut system_state, event);
+            if handle_events(&event_tx, &system_state)? {
+                break;
+            }
         }
 
+        let summary = Summary::new(&system_state, warmup);
         terminal.draw(|frame| {
             render_system_ui(frame, &system_state);
+            render_summary(frame, frame.area(), &summary, &system_state);
+            if ascii::enabled() {
+                ascii::asciify(frame.buffer_mut());
+            }
         })?;
+        wait_for_key()?;
 
-        if handle_events(&event_tx, &system_state)? {
-            break;
-        }
-    }
-
-    restore_terminal(&mut terminal).ok();
-    Ok(())
+        restore_terminal(&mut terminal).ok();
+        Ok(system_state)
+    })
 }
 
 fn init_terminal() -> io::Result<Terminal<CrosstermBackend<std::io::Stdout>>> {
@@ -142,164 +259,1228 @@ fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>)
     Ok(())
 }
 
-fn update_system_state(state: &mut SystemState, event: SystemEvent) {
-    match event {
-        SystemEvent::RequestCreated(request) => {
-            state.pending_requests.push_back(request);
-            state.stats.total_requests += 1;
-            add_log(&mut state.logs, format!("Request #{} created", request.id));
+fn render_system_ui(frame: &mut Frame, state: &SystemState) {
+    let [tabs_area, body_area, status_area] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Fill(1),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let selected_tab = SELECTED_TAB.load(Ordering::SeqCst);
+    render_tabs(frame, tabs_area, selected_tab, state);
+    render_status_bar(frame, status_area, state);
+
+    // Taken from the top of the view while any alert is firing.
+    let alerts: Vec<&str> = state.alerts().firing().collect();
+    let body_area = if alerts.is_empty() {
+        body_area
+    } else {
+        let [banner_area, body_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(body_area);
+        render_alert_banner(frame, banner_area, &alerts, state);
+        body_area
+    };
+
+    if selected_tab > 1 {
+        // The servers are hidden, so scrolling can't reach them.
+        for area in &SERVER_AREAS {
+            area.update_from(Rect::default());
         }
-        SystemEvent::RequestAssigned { server_id, request } => {
-            state.pending_requests.retain(|r| r.id != request.id);
-
-            let server_idx = (server_id - 1) as usize;
-            if server_idx < state.servers.len() {
-                state.servers[server_idx].add_request(request);
-                add_log(
-                    &mut state.logs,
-                    format!("Request #{} assigned to Server {}", request.id, server_id),
-                );
+    }
+    if selected_tab > 0 {
+        // Nor can dragging reach the overview's borders, or clicking the
+        // pending requests.
+        PROCESSING_AREA.update_from(Rect::default());
+        REQUESTS_AREA.update_from(Rect::default());
+        REQUEST_CELLS.lock().unwrap().clear();
+    }
+    if selected_tab != 0 && selected_tab != 3 {
+        LOG_AREA.update_from(Rect::default());
+    }
+
+    match selected_tab {
+        0 => render_overview(frame, body_area, state),
+        1 => render_servers_view(frame, body_area, state),
+        2 => render_statistics_view(frame, body_area, state),
+        3 => render_logs(frame, body_area, state.logs()),
+        _ => render_config_view(frame, body_area, state),
+    }
+
+    if SHOW_SERVER_DETAIL.load(Ordering::SeqCst)
+        && let Some(server) = selected_server(state)
+    {
+        render_server_detail(
+            frame,
+            body_area,
+            server,
+            state.stats().servers().get(&server.id),
+        );
+    }
+    if SHOW_HELP.load(Ordering::SeqCst) {
+        render_help(frame, frame.area(), state);
+    }
+    if CONFIRM_QUIT.load(Ordering::SeqCst) {
+        render_quit_prompt(frame, frame.area());
+    }
+}
+
+fn render_quit_prompt(frame: &mut Frame, area: Rect) {
+    let key = Style::default().add_modifier(style::Modifier::BOLD);
+    let [popup_area] = Layout::vertical([Constraint::Length(3)])
+        .flex(layout::Flex::Center)
+        .areas(area);
+    let [popup_area] = Layout::horizontal([Constraint::Length(40)])
+        .flex(layout::Flex::Center)
+        .areas(popup_area);
+
+    let block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .title(" Quit? ")
+        .border_style(theme().warning);
+    let line = text::Line::from(vec![
+        text::Span::raw("End the simulation?  "),
+        text::Span::styled("[y]", key),
+        text::Span::raw(" Quit  "),
+        text::Span::styled("[n]", key),
+        text::Span::raw(" Stay"),
+    ])
+    .centered();
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(Paragraph::new(line).block(block), popup_area);
+}
+
+/// The results of the run over its last frame, shown once the user quits
+/// and until they press a key.
+fn render_summary(frame: &mut Frame, area: Rect, summary: &Summary, state: &SystemState) {
+    let mut lines = vec![
+        text::Line::styled(
+            format!(
+                "Ran for {} of simulated time",
+                clock_time(clock::since(state.launched_at()))
+            ),
+            theme().heading,
+        ),
+        text::Line::default(),
+    ];
+    let summary = ascii::spell_out(summary.to_string());
+    lines.extend(summary.lines().map(text::Line::raw));
+
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let width = lines.iter().map(text::Line::width).max().unwrap_or(0) as u16 + 4;
+    let [popup_area] = Layout::vertical([Constraint::Length(height)])
+        .flex(layout::Flex::Center)
+        .areas(area);
+    let [popup_area] = Layout::horizontal([Constraint::Length(width.min(area.width))])
+        .flex(layout::Flex::Center)
+        .areas(popup_area);
+
+    let block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .title(" Simulation Ended ")
+        .title_bottom(text::Line::from(" Press any key to exit ").right_aligned());
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(Paragraph::new(lines).block(block), popup_area);
+}
+
+/// Hours, minutes and seconds, e.g. `01:02:03`.
+fn clock_time(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Everything at a glance: the requests, the servers, the configuration,
+/// the statistics and the latest events.
+fn render_overview(frame: &mut Frame, body_area: Rect, state: &SystemState) {
+    let main_split = MAIN_SPLIT.load(Ordering::SeqCst);
+    let main_layout = Layout::horizontal([
+        Constraint::Percentage(main_split),
+        Constraint::Percentage(100 - main_split),
+    ])
+    .areas(body_area);
+    let [processing_area, info_area] = main_layout;
+
+    let processing_layout = Layout::vertical([
+        Constraint::Percentage(REQUESTS_SPLIT.load(Ordering::SeqCst)),
+        Constraint::Fill(1),
+        // A header and a row per server, with room for the histogram.
+        Constraint::Length((state.servers().len() as u16 + 3).max(10)),
+    ])
+    .areas(processing_area);
+    let [top_area, servers_area, bottom_area] = processing_layout;
+    PROCESSING_AREA.update_from(processing_area);
+    REQUESTS_AREA.update_from(top_area);
+    let [requests_area, gauges_area] =
+        Layout::horizontal([Constraint::Fill(2), Constraint::Fill(1)]).areas(top_area);
+    let [saturation_area, queues_area] =
+        Layout::vertical([Constraint::Length(3), Constraint::Fill(1)]).areas(gauges_area);
+    let [server_stats_area, histogram_area] =
+        Layout::horizontal([Constraint::Fill(3), Constraint::Fill(2)]).areas(bottom_area);
+
+    let info_layout = Layout::vertical([
+        Constraint::Length(26),
+        // One more line per tag seen so far, and three for the sparkline.
+        Constraint::Length(27 + state.stats().tags().len() as u16),
+        Constraint::Length(SLO_TARGETS.len() as u16 + 2),
+        Constraint::Length(TENANTS.len() as u16 + 2),
+        Constraint::Fill(1),
+    ])
+    .areas(info_area);
+    let [configs_area, stats_area, slo_area, tenants_area, logs_area] = info_layout;
+
+    render_requests(
+        frame,
+        requests_area,
+        state.pending_requests(),
+        state.servers(),
+    );
+    render_saturation(frame, saturation_area, state);
+    render_live_queues(
+        frame,
+        queues_area,
+        state.stats().queue_history(),
+        state.servers(),
+    );
+    render_servers(
+        frame,
+        servers_area,
+        state.servers(),
+        state.stats().servers(),
+    );
+    render_histogram(frame, histogram_area, state.stats().latencies());
+    render_server_stats(
+        frame,
+        server_stats_area,
+        state.servers(),
+        state.stats().servers(),
+    );
+    render_configs(frame, configs_area, state.configs(), state.servers().len());
+    render_stats(frame, stats_area, state);
+    render_slo(frame, slo_area, state.stats().slo());
+    render_tenants(
+        frame,
+        tenants_area,
+        state.stats().tenants(),
+        state.pending_requests(),
+    );
+    render_logs(frame, logs_area, state.logs());
+}
+
+/// The servers with room for long queues, their statistics and how their
+/// queues and workloads evolved.
+fn render_servers_view(frame: &mut Frame, area: Rect, state: &SystemState) {
+    let [servers_area, history_area] =
+        Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(area);
+    let [blocks_area, stats_area] = Layout::vertical([
+        Constraint::Fill(1),
+        Constraint::Length(state.servers().len() as u16 + 3),
+    ])
+    .areas(servers_area);
+
+    render_servers(frame, blocks_area, state.servers(), state.stats().servers());
+    render_server_stats(frame, stats_area, state.servers(), state.stats().servers());
+    render_queue_history(
+        frame,
+        history_area,
+        state.stats().queue_history(),
+        state.servers(),
+    );
+}
+
+/// The overall statistics and latencies next to their breakdowns by request
+/// class and server.
+fn render_statistics_view(frame: &mut Frame, area: Rect, state: &SystemState) {
+    let [overall_area, right_area] =
+        Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(area);
+    let [distribution_area, breakdowns_area] =
+        Layout::vertical([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .areas(right_area);
+    let [stats_area, slo_area, tenants_area, histogram_area] = Layout::vertical([
+        Constraint::Length(27 + state.stats().tags().len() as u16),
+        Constraint::Length(SLO_TARGETS.len() as u16 + 2),
+        Constraint::Length(TENANTS.len() as u16 + 2),
+        Constraint::Fill(1),
+    ])
+    .areas(overall_area);
+
+    render_stats(frame, stats_area, state);
+    render_slo(frame, slo_area, state.stats().slo());
+    render_tenants(
+        frame,
+        tenants_area,
+        state.stats().tenants(),
+        state.pending_requests(),
+    );
+    render_histogram(frame, histogram_area, state.stats().latencies());
+    render_distribution(frame, distribution_area, state.stats().latencies());
+    render_breakdowns(frame, breakdowns_area, state.stats(), state.servers());
+}
+
+/// The settings with the keys that change them, next to the other keys.
+fn render_config_view(frame: &mut Frame, area: Rect, state: &SystemState) {
+    let [settings_area, keys_area] =
+        Layout::horizontal([Constraint::Length(60), Constraint::Fill(1)]).areas(area);
+    let [form_area, configs_area] = Layout::vertical([
+        Constraint::Length(FORM_FIELDS.len() as u16 + 2),
+        Constraint::Fill(1),
+    ])
+    .areas(settings_area);
+
+    render_form(frame, form_area, state);
+    render_configs(frame, configs_area, state.configs(), state.servers().len());
+
+    let block = Block::bordered().title("Controls");
+    let inner_area = block.inner(keys_area);
+    frame.render_widget(block, keys_area);
+
+    let mut lines = key_section("Keys", &GLOBAL_KEYS, 10);
+    lines.extend(key_section("Selected Server", &SERVER_KEYS, 10));
+    lines.extend(key_section("Mouse", &MOUSE_ACTIONS, 22));
+    frame.render_widget(Paragraph::new(lines), inner_area);
+}
+
+/// The settings worth trying different values of, with the focused one
+/// highlighted and what's being typed into it, if anything.
+fn render_form(frame: &mut Frame, area: Rect, state: &SystemState) {
+    let focused = FORM_FIELD.load(Ordering::SeqCst);
+    let input = FORM_INPUT.lock().unwrap();
+
+    let lines: Vec<text::Line> = FORM_FIELDS
+        .iter()
+        .enumerate()
+        .map(|(field, name)| {
+            let value = if field == focused && !input.is_empty() {
+                format!("{input}_")
+            } else {
+                form_value(state, field)
+            };
+            let line = text::Line::from(format!("{name:<16}⮜ {value} ⮞"));
+            if field == focused {
+                line.style(Style::default().add_modifier(style::Modifier::REVERSED))
+            } else {
+                line
             }
-        }
-        SystemEvent::RequestProcessStarted {
-            request_id,
-            server_id,
-        } => {
-            add_log(
-                &mut state.logs,
-                format!("Server {} started at Request #{}", server_id, request_id),
-            );
-        }
-        SystemEvent::RequestProcessed {
-            request_id,
-            server_id,
-            created_at,
-        } => {
-            let server_idx = (server_id - 1) as usize;
-            if server_idx < state.servers.len() {
-                let server = &mut state.servers[server_idx];
-                server.remove_request();
-
-                state.stats.processed_requests += 1;
-
-                let now = Instant::now();
-                state.stats.throughput_window.push(now);
-
-                let cutoff = now - Duration::from_secs(10);
-                state
-                    .stats
-                    .throughput_window
-                    .retain(|timestamp| *timestamp >= cutoff);
-
-                let total_in_window = state.stats.throughput_window.len();
-                if let Some(oldest) = state.stats.throughput_window.first() {
-                    let window_duration = now.duration_since(*oldest).as_secs_f64();
-                    if window_duration > 0.0 {
-                        state.stats.throughput = total_in_window as f64 / window_duration;
-                    }
-                }
+        })
+        .collect();
+
+    let block = Block::bordered().title("Edit").title_bottom(
+        text::Line::from(" ⮝ ⮟ Field  ⮜ ⮞ Change  Type + Enter Set ").right_aligned(),
+    );
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
 
-                if created_at != Instant::now() {
-                    let wait_time = now.duration_since(created_at).as_millis() as f64;
+fn form_value(state: &SystemState, field: usize) -> String {
+    match field {
+        0 => format!("{:.1} req/sec", state.configs().arrival_rate),
+        1 => state.configs().choice_mode.to_string(),
+        2 => state.servers().len().to_string(),
+        3 => format!("{} requests", server::queue_capacity()),
+        4 => format!("{}x", clock::speed()),
+        _ => state.configs().seed.to_string(),
+    }
+}
 
-                    state.stats.avg_wait_time = if state.stats.processed_requests <= 1 {
-                        wait_time
+/// Handles a key on the Config view's form, returning whether it was one
+/// the form takes; the others keep their usual meaning.
+fn edit_form(event_tx: &Sender<SystemEvent>, state: &SystemState, key: KeyCode) -> bool {
+    let field = FORM_FIELD.load(Ordering::SeqCst);
+    let mut input = FORM_INPUT.lock().unwrap();
+
+    match key {
+        KeyCode::Up | KeyCode::Down => {
+            let field = if key == KeyCode::Up {
+                (field + FORM_FIELDS.len() - 1) % FORM_FIELDS.len()
+            } else {
+                (field + 1) % FORM_FIELDS.len()
+            };
+            FORM_FIELD.store(field, Ordering::SeqCst);
+            input.clear();
+        }
+        KeyCode::Left | KeyCode::Right => {
+            input.clear();
+            let up = key == KeyCode::Right;
+            match field {
+                0 => set_field(
+                    event_tx,
+                    state,
+                    field,
+                    state.configs().arrival_rate as f64 + if up { 0.5 } else { -0.5 },
+                ),
+                1 => {
+                    // Same direction as the arrow keys outside the form.
+                    let choice_mode = if up {
+                        state.configs().choice_mode.prev()
                     } else {
-                        (state.stats.avg_wait_time * (state.stats.processed_requests - 1) as f64
-                            + wait_time)
-                            / state.stats.processed_requests as f64
+                        state.configs().choice_mode.next()
+                    };
+                    event_tx
+                        .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+                            choice_mode: Some(choice_mode),
+                            ..Default::default()
+                        }))
+                        .ok();
+                }
+                2 => set_field(
+                    event_tx,
+                    state,
+                    field,
+                    state.servers().len() as f64 + if up { 1.0 } else { -1.0 },
+                ),
+                3 => set_field(
+                    event_tx,
+                    state,
+                    field,
+                    server::queue_capacity() as f64 + if up { 1.0 } else { -1.0 },
+                ),
+                4 => {
+                    if let Some(speed) = clock::step_speed(clock::speed(), up) {
+                        set_field(event_tx, state, field, speed);
                     }
                 }
-
-                add_log(
-                    &mut state.logs,
-                    format!("Server {} processed Request #{}", server_id, request_id),
-                );
+                _ => set_seed(
+                    event_tx,
+                    if up {
+                        state.configs().seed.wrapping_add(1)
+                    } else {
+                        state.configs().seed.wrapping_sub(1)
+                    },
+                ),
             }
         }
-        SystemEvent::ErrorEncountered(error_msg) => {
-            add_log(&mut state.logs, format!("Error: {error_msg}"));
+        // The policy is picked with the number keys, as anywhere else.
+        KeyCode::Char(ch @ ('0'..='9' | '.')) if field != 1 => input.push(ch),
+        KeyCode::Backspace => {
+            input.pop();
         }
-        SystemEvent::ConfigChanged {
-            arrival_rate,
-            choice_mode,
-        } => {
-            if let Some(arrival_rate) = arrival_rate {
-                state.configs.arrival_rate = arrival_rate;
+        // Seeds go past what a float holds exactly.
+        KeyCode::Enter if !input.is_empty() && field == SEED_FIELD => {
+            if let Ok(seed) = input.parse() {
+                set_seed(event_tx, seed);
             }
-            if let Some(choice_mode) = choice_mode {
-                state.configs.choice_mode = choice_mode;
+            input.clear();
+        }
+        KeyCode::Enter if !input.is_empty() => {
+            if let Ok(value) = input.parse() {
+                set_field(event_tx, state, field, value);
             }
+            input.clear();
         }
+        KeyCode::Esc if !input.is_empty() => input.clear(),
+        _ => return false,
     }
+    true
 }
 
-fn add_log(logs: &mut Vec<String>, message: String) {
-    if logs.len() >= logs.capacity() {
-        logs.remove(0);
-    };
+/// Starts every task's random draws over from `seed`.
+fn set_seed(event_tx: &Sender<SystemEvent>, seed: u64) {
+    event_tx
+        .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+            seed: Some(seed),
+            ..Default::default()
+        }))
+        .ok();
+}
 
-    logs.push(format!(
-        "[{}] {}",
-        chrono::Local::now().format("%H:%M:%S"),
-        message
-    ));
+/// Sets a numeric field of the form, within the range it can take.
+fn set_field(event_tx: &Sender<SystemEvent>, state: &SystemState, field: usize, value: f64) {
+    match field {
+        0 => {
+            event_tx
+                .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+                    arrival_rate: Some(value.clamp(0.0, 10.0) as f32),
+                    ..Default::default()
+                }))
+                .ok();
+        }
+        2 => {
+            let servers = (value.round() as usize).clamp(1, MAX_SERVERS);
+            for _ in state.servers().len()..servers {
+                event_tx
+                    .try_send(SystemEvent::ServerAdded {
+                        server_id: server::next_server_id(),
+                    })
+                    .ok();
+            }
+            for server in state.servers().iter().skip(servers) {
+                event_tx
+                    .try_send(SystemEvent::ServerRemoved {
+                        server_id: server.id,
+                    })
+                    .ok();
+            }
+        }
+        3 => {
+            event_tx
+                .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+                    queue_capacity: Some((value.round() as usize).clamp(1, MAX_QUEUE_CAPACITY)),
+                    ..Default::default()
+                }))
+                .ok();
+        }
+        4 => {
+            event_tx
+                .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+                    speed: Some(value.clamp(clock::MIN_SPEED, clock::MAX_SPEED)),
+                    ..Default::default()
+                }))
+                .ok();
+        }
+        _ => {}
+    }
 }
 
-fn render_system_ui(frame: &mut Frame, state: &SystemState) {
-    let main_layout = Layout::horizontal([Constraint::Percentage(70), Constraint::Percentage(30)])
-        .areas(frame.area());
-    let [processing_area, info_area] = main_layout;
+/// The views, with the time the simulation has been running for, both real
+/// and simulated, and the steps it took, to relate results to how long the
+/// run was.
+fn render_tabs(frame: &mut Frame, area: Rect, selected: usize, state: &SystemState) {
+    let clock = format!(
+        "Real {}  Simulated {}  Steps {}",
+        clock_time(state.launched_at_real().elapsed()),
+        clock_time(clock::since(state.launched_at())),
+        clock::steps()
+    );
+    let [tabs_area, clock_area, hint_area] = Layout::horizontal([
+        Constraint::Fill(1),
+        Constraint::Length(clock.len() as u16),
+        Constraint::Length(26),
+    ])
+    .areas(area);
+
+    TABS_AREA.update_from(tabs_area);
+    let tabs = Tabs::new(TABS)
+        .select(selected)
+        .highlight_style(Style::default().add_modifier(style::Modifier::REVERSED));
+
+    frame.render_widget(tabs, tabs_area);
+    frame.render_widget(Paragraph::new(clock).style(theme().muted), clock_area);
+    frame.render_widget(
+        Paragraph::new("[Tab F1-F5] Switch View").alignment(layout::Alignment::Right),
+        hint_area,
+    );
+}
 
-    let processing_layout =
-        Layout::vertical([Constraint::Percentage(40), Constraint::Percentage(60)])
-            .areas(processing_area);
-    let [requests_area, servers_area] = processing_layout;
+/// The policy, arrival rate and speed and how to change them, and whether
+/// the simulation is paused, shown on every view.
+fn render_status_bar(frame: &mut Frame, area: Rect, state: &SystemState) {
+    let key = Style::default().add_modifier(style::Modifier::BOLD);
+    let policy = match state.configs().delegate_mode {
+        Some(delegate_mode) => format!(" {} ({}) ", state.configs().choice_mode, delegate_mode),
+        None => format!(" {} ", state.configs().choice_mode),
+    };
 
-    let info_layout = Layout::vertical([
-        Constraint::Length(5),
-        Constraint::Length(6),
+    let mut spans = Vec::new();
+    if state.configs().paused {
+        spans.push(text::Span::styled(
+            " PAUSED ",
+            theme().alert.add_modifier(style::Modifier::BOLD),
+        ));
+    }
+    spans.extend([
+        text::Span::styled(policy, theme().badge.add_modifier(style::Modifier::BOLD)),
+        text::Span::styled("  [⮜ ⮞ 1-9]", key),
+        text::Span::raw(" Policy "),
+        text::Span::styled(" λ ", key),
+        text::Span::styled(
+            format!("{:.1} req/sec", state.configs().arrival_rate),
+            theme().info.add_modifier(style::Modifier::BOLD),
+        ),
+        text::Span::styled("  [+ -]", key),
+        text::Span::raw(" Arrival Rate"),
+        text::Span::styled("  [Ins Del]", key),
+        text::Span::raw(format!(" Servers ({})", state.servers().len())),
+        text::Span::styled("  [< >]", key),
+        text::Span::raw(format!(" Speed ({}x)", clock::speed())),
+        text::Span::styled("  [z]", key),
+        text::Span::raw(if state.configs().paused {
+            " Resume"
+        } else {
+            " Pause"
+        }),
+        text::Span::styled("  [?]", key),
+        text::Span::raw(" Help"),
+        text::Span::styled("  [q]", key),
+        text::Span::raw(" Quit"),
+    ]);
+
+    let elapsed = format!("Elapsed {} ", clock_time(clock::since(state.launched_at())));
+    let [keys_area, elapsed_area] = Layout::horizontal([
         Constraint::Fill(1),
+        Constraint::Length(elapsed.len() as u16),
     ])
-    .areas(info_area);
-    let [configs_area, stats_area, logs_area] = info_layout;
+    .areas(area);
+
+    frame.render_widget(
+        Paragraph::new(text::Line::from(spans)).style(theme().status_bar),
+        keys_area,
+    );
+    frame.render_widget(
+        Paragraph::new(elapsed).style(theme().status_bar),
+        elapsed_area,
+    );
+}
+
+/// What the firing alerts found, on a line that flashes between the alert
+/// and error styles.
+fn render_alert_banner(frame: &mut Frame, area: Rect, alerts: &[&str], state: &SystemState) {
+    let phase = state.launched_at_real().elapsed().as_millis() / ALERT_FLASH.as_millis();
+    let style = if phase.is_multiple_of(2) {
+        theme().alert
+    } else {
+        theme().error
+    };
+
+    let banner = Paragraph::new(format!(" ALERT  {} ", alerts.join("  ·  ")))
+        .style(style.add_modifier(style::Modifier::BOLD));
+    frame.render_widget(banner, area);
+}
+
+/// Keys that act on the whole simulation.
+const GLOBAL_KEYS: [(&str, &str); 14] = [
+    ("?", "Show or hide this help"),
+    ("Tab F1-F5", "Switch view"),
+    ("⇧ ⮜ ⮞ ⮝ ⮟", "Resize the overview's panes"),
+    ("⮜ ⮞ 1-9", "Balancing mode"),
+    ("+ - ⮝ ⮟", "Arrival rate"),
+    ("PgUp PgDn", "Page through the pending requests"),
+    ("Ins Del", "Add or remove a server"),
+    ("< >", "Run slower or faster"),
+    ("z", "Pause or resume everything"),
+    ("y", "Switch theme"),
+    ("F", "Follow the log or stop following"),
+    ("End", "Jump to the newest log line"),
+    ("S", "Save the screen to a text file"),
+    ("q", "Quit, after confirming"),
+];
+
+/// Keys that act on the server selected with `s`.
+const SERVER_KEYS: [(&str, &str); 10] = [
+    ("s", "Select the next server"),
+    ("Enter", "Show or hide its details"),
+    ("d", "Drain or reactivate it"),
+    ("o", "Switch its queue order"),
+    ("Space", "Pause or resume it"),
+    ("r", "Switch its rate limit"),
+    ("u", "Switch its failure rate"),
+    ("W", "Switch its number of workers"),
+    ("k", "Crash it, re-dispatching its work"),
+    ("K", "Crash it, failing its work"),
+];
+
+const MOUSE_ACTIONS: [(&str, &str); 8] = [
+    ("Click a tab", "Switch to its view"),
+    ("Click a server", "Select it and show its details"),
+    ("Click a request", "Select it and show its details"),
+    ("Click a log line", "Select it, leaving follow mode"),
+    ("Scroll on a server", "Scroll its queue"),
+    ("Scroll elsewhere", "Scroll the logs"),
+    ("Right-click a server", "Pause or resume it"),
+    ("Drag a border", "Resize the overview's panes"),
+];
 
-    render_requests(frame, requests_area, &state.pending_requests);
-    render_servers(frame, servers_area, &state.servers);
-    render_configs(frame, configs_area, &state.configs);
-    render_stats(frame, stats_area, &state.stats);
-    render_logs(frame, logs_area, &state.logs);
+/// Popup over the current view with every key and mouse action, and the
+/// current configuration next to the keys that change it.
+fn render_help(frame: &mut Frame, area: Rect, state: &SystemState) {
+    let config = state.configs();
+    let toggles = [
+        ("a", "Admission Control", config.admission_control),
+        ("l", "Large Anti-Affinity", config.anti_affinity),
+        ("x", "Autoscaling", config.autoscaling),
+        ("c", "Contention", config.contention),
+        ("g", "Stalls", config.stalls),
+        ("p", "SRPT Preemption", config.preemption),
+        ("t", "Retries", config.retries),
+        ("b", "Bursts", config.bursts),
+        ("e", "Sessions", config.sessions),
+        ("w", "Batching", config.batching),
+        ("f", "Fork-Join", config.forks),
+        ("i", "Pipelines", config.pipelines),
+        ("n", "Network", config.network),
+        ("h", "Dependencies", config.dependencies),
+        ("j", "Deduplication", config.deduplication),
+        ("m", "Warm Cache", config.caching),
+        ("v", "Fair Queueing", config.fair_queueing),
+    ];
+
+    let heading = theme().heading;
+    let key = Style::default().add_modifier(style::Modifier::BOLD);
+
+    let mut left = key_section("Keys", &GLOBAL_KEYS, 10);
+    left.extend(key_section("Selected Server", &SERVER_KEYS, 10));
+    left.extend(key_section("Mouse", &MOUSE_ACTIONS, 22));
+
+    let policy = match config.delegate_mode {
+        Some(delegate_mode) => format!("{} ({})", config.choice_mode, delegate_mode),
+        None => config.choice_mode.to_string(),
+    };
+    let mut right = vec![text::Line::styled("Configuration", heading)];
+    right.extend(
+        [
+            ("Policy", policy),
+            (
+                "Arrival Rate",
+                format!("{:.1} req/sec", config.arrival_rate),
+            ),
+            ("Speed", format!("{}x", clock::speed())),
+            ("Servers", state.servers().len().to_string()),
+            (
+                "Selected",
+                selected_server(state)
+                    .map_or("-".to_string(), |server| format!("Server {}", server.id)),
+            ),
+            (
+                "Simulation",
+                if config.paused { "Paused" } else { "Running" }.to_string(),
+            ),
+            ("Window", format!("{}s", window().as_secs())),
+            ("Theme", theme().name.to_string()),
+        ]
+        .into_iter()
+        .map(|(name, value)| text::Line::from(format!("{name:<14}{value}"))),
+    );
+    right.push(text::Line::default());
+    right.push(text::Line::styled("Features", heading));
+    right.extend(toggles.into_iter().map(|(keys, name, on)| {
+        text::Line::from(vec![
+            text::Span::styled(format!("{keys:<4}"), key),
+            text::Span::raw(format!("{name:<22}")),
+            if on {
+                text::Span::styled("On", theme().good)
+            } else {
+                text::Span::styled("Off", theme().muted)
+            },
+        ])
+    }));
+
+    let height = left.len().max(right.len()) as u16 + 2;
+    let [popup_area] = Layout::vertical([Constraint::Length(height)])
+        .flex(layout::Flex::Center)
+        .areas(area);
+    let [popup_area] = Layout::horizontal([Constraint::Length(86)])
+        .flex(layout::Flex::Center)
+        .areas(popup_area);
+
+    let block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .title(" Help ")
+        .title_bottom(text::Line::from(" [?] or [Esc] Close ").right_aligned());
+    let inner_area = block.inner(popup_area);
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(block, popup_area);
+
+    let [left_area, right_area] = Layout::horizontal([Constraint::Length(46), Constraint::Fill(1)])
+        .horizontal_margin(1)
+        .areas(inner_area);
+    frame.render_widget(Paragraph::new(left), left_area);
+    frame.render_widget(Paragraph::new(right), right_area);
 }
 
-fn render_requests(frame: &mut Frame, area: Rect, requests: &VecDeque<Request>) {
-    let style = if requests.len() >= PENDING_REQUESTS_LIMIT as usize {
-        Style::default().fg(style::Color::Red)
+/// Popup with everything about the selected server: its settings, how it
+/// has done so far, what it is processing and its whole queue.
+fn render_server_detail(
+    frame: &mut Frame,
+    area: Rect,
+    server: &ServerState,
+    stats: Option<&ServerStats>,
+) {
+    let heading = theme().heading;
+    let on_off = |on: bool| if on { "On" } else { "Off" };
+    let describe = |request: &Request| {
+        format!(
+            "#{:<8} {:<10} {:<6} {:<7} {}",
+            request.id,
+            format!("{:?}", request.kind),
+            format!("{:?}", request.size),
+            format!("{:?}", request.priority),
+            request.tag.unwrap_or("")
+        )
+    };
+
+    let state = if server.paused {
+        "Paused".to_string()
+    } else if server.is_stalled() {
+        format!("Stalled ({}ms left)", server.stall_remaining())
     } else {
-        Style::default()
+        format!("{:?}", server.status)
     };
-    let block = Block::bordered().title("Pending Requests").style(style);
-    let inner_area = block.inner(area);
+    let mut lines = vec![
+        text::Line::from(format!(
+            "State: {}  Breaker: {}  Warming Up: {}",
+            state,
+            server.breaker,
+            on_off(server.is_warming_up())
+        )),
+        text::Line::from(format!(
+            "Profile: {}  Tier: {}  Workers: {}  Queue Order: {}",
+            server.profile, server.tier, server.workers, server.discipline
+        )),
+        text::Line::from(format!(
+            "Memory: {}/{}MB  Workload: {}ms  Utilization: {:.0}%",
+            server.memory_used(),
+            server.memory,
+            server.remaining_workload(),
+            server.recent_utilization() * 100.0
+        )),
+        text::Line::from(format!(
+            "Rate Limit: {}  Failure Rate: {:.0}%",
+            server
+                .rate_limit
+                .map_or("none".to_string(), |limit| format!("{limit} req/sec")),
+            server.failure_rate * 100.0
+        )),
+        text::Line::default(),
+    ];
+
+    lines.push(text::Line::from(match stats {
+        Some(stats) => format!(
+            "Throughput: {:.2} req/sec  Processed: {}  Avg: {:.0}ms  p99: {}",
+            stats.throughput(),
+            stats.response_times().count(),
+            stats.response_times().average(),
+            stats
+                .percentile(0.99)
+                .map_or("-".to_string(), |p99| format!("{p99:.0}ms"))
+        ),
+        None => "Throughput: 0.00 req/sec  Processed: 0".to_string(),
+    }));
+    lines.push(text::Line::from(format!(
+        "Failed: {}  Expired: {}",
+        stats.map_or(0, |stats| stats.failed()),
+        stats.map_or(0, |stats| stats.expired())
+    )));
+    lines.push(text::Line::default());
+
+    lines.push(text::Line::styled(
+        format!("Processing ({}/{})", server.in_flight.len(), server.workers),
+        heading,
+    ));
+    for in_flight in &server.in_flight {
+        let remaining = in_flight.remaining_time();
+        lines.push(text::Line::styled(
+            format!(
+                "{}  {}ms of {}ms left",
+                describe(&in_flight.request),
+                remaining,
+                in_flight.service_time
+            ),
+            theme().good,
+        ));
+    }
+    for preempted in &server.preempted {
+        lines.push(text::Line::styled(
+            format!(
+                "{}  {}ms left, preempted",
+                describe(&preempted.request),
+                preempted.left
+            ),
+            theme().warning,
+        ));
+    }
+    lines.push(text::Line::default());
+
+    lines.push(text::Line::styled(
+        format!(
+            "Queue ({}/{})",
+            server.queue.len(),
+            server::queue_capacity()
+        ),
+        heading,
+    ));
+    for request in &server.queue {
+        lines.push(text::Line::from(format!(
+            "{}  {}ms, waiting {:.1}s",
+            describe(request),
+            request.get_time(),
+            clock::since(request.created_at).as_secs_f64()
+        )));
+    }
 
-    frame.render_widget(block, area);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let [popup_area] = Layout::vertical([Constraint::Length(height)])
+        .flex(layout::Flex::Center)
+        .areas(area);
+    let [popup_area] = Layout::horizontal([Constraint::Length(80)])
+        .flex(layout::Flex::Center)
+        .areas(popup_area);
+
+    let zone = server
+        .zone
+        .map(|zone| format!(" @{zone}"))
+        .unwrap_or_default();
+    let block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .title(format!(" Server {}{} ", server.id, zone))
+        .title_bottom(text::Line::from(" [Enter] or [Esc] Close ").right_aligned());
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(Paragraph::new(lines).block(block), popup_area);
+}
 
-    if !requests.is_empty() {
-        let req_width = 21;
-        let req_height = 4;
+/// A titled list of keys, padded to `width`, and what they do.
+fn key_section<'a>(
+    title: &'a str,
+    entries: &[(&'a str, &'a str)],
+    width: usize,
+) -> Vec<text::Line<'a>> {
+    let heading = theme().heading;
+    let key = Style::default().add_modifier(style::Modifier::BOLD);
+
+    let mut lines = vec![text::Line::styled(title, heading)];
+    lines.extend(entries.iter().map(|(keys, action)| {
+        text::Line::from(vec![
+            text::Span::styled(format!("{keys:<width$}"), key),
+            text::Span::raw(*action),
+        ])
+    }));
+    lines.push(text::Line::default());
+    lines
+}
+
+fn render_breakdowns(frame: &mut Frame, area: Rect, stats: &SystemStats, servers: &[ServerState]) {
+    let [classes_area, slowdowns_area, assignments_area] = Layout::vertical([
+        Constraint::Length(REQ_TYPES.len() as u16 + 3),
+        Constraint::Length(REQ_SIZES.len() as u16 + 3),
+        Constraint::Fill(1),
+    ])
+    .areas(area);
+
+    render_class_latencies(frame, classes_area, stats.classes());
+    render_size_slowdowns(frame, slowdowns_area, stats.size_slowdowns());
+    render_assignments(frame, assignments_area, stats.assignments(), servers);
+}
 
-        let requests_per_row = (inner_area.width as usize / req_width).max(1);
-        let rows_available = (inner_area.width as usize / req_height).max(1);
+/// Requests of each type and size assigned to each server, colored by the
+/// share of the class each server got, so a server taking most of a class
+/// stands out.
+fn render_assignments(
+    frame: &mut Frame,
+    area: Rect,
+    assignments: &HashMap<(RequestType, RequestSize, u64), usize>,
+    servers: &[ServerState],
+) {
+    let header = Row::new(
+        std::iter::once("Class".to_string())
+            .chain(servers.iter().map(|server| format!("Server {}", server.id))),
+    )
+    .style(Style::default().add_modifier(style::Modifier::BOLD));
+
+    let rows: Vec<Row> = REQ_TYPES
+        .iter()
+        .flat_map(|kind| REQ_SIZES.iter().map(move |size| (*kind, *size)))
+        .map(|(kind, size)| {
+            let counts: Vec<usize> = servers
+                .iter()
+                .map(|server| {
+                    assignments
+                        .get(&(kind, size, server.id))
+                        .copied()
+                        .unwrap_or_default()
+                })
+                .collect();
+            let total = counts.iter().sum();
+
+            let cells = counts.into_iter().map(|count| {
+                if count == 0 {
+                    return Cell::from("-");
+                }
+                let share = percentage(count, total);
+                Cell::from(format!("{count} ({share:.0}%)")).style(heat_style(share))
+            });
+            Row::new(std::iter::once(Cell::from(format!("{kind:?} {size:?}"))).chain(cells))
+        })
+        .collect();
+
+    let widths =
+        std::iter::once(Constraint::Length(16)).chain(servers.iter().map(|_| Constraint::Fill(1)));
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(Block::bordered().title("Assignments by Type and Size (count, share of the class)"));
+
+    frame.render_widget(table, area);
+}
 
-        let mut constraints_h = Vec::new();
-        let mut constraints_v = Vec::new();
+/// Average and 99th percentile slowdown of each request size, the ones far
+/// above 1 showing the sizes the strategy makes wait.
+fn render_size_slowdowns(
+    frame: &mut Frame,
+    area: Rect,
+    slowdowns: &HashMap<RequestSize, Slowdowns>,
+) {
+    let header = Row::new(["Size", "Avg", "p99", "Count"])
+        .style(Style::default().add_modifier(style::Modifier::BOLD));
+
+    let rows: Vec<Row> = REQ_SIZES
+        .iter()
+        .map(|size| match slowdowns.get(size) {
+            Some(stats) => Row::new([
+                format!("{size:?}"),
+                format!("{:.1}", stats.average()),
+                format!("{:.1}", stats.p99().unwrap_or_default()),
+                stats.count().to_string(),
+            ]),
+            None => Row::new([format!("{size:?}"), "-".into(), "-".into(), "-".into()]),
+        })
+        .collect();
+
+    let table = Table::new(rows, [Constraint::Fill(1); 4])
+        .header(header)
+        .block(Block::bordered().title("Slowdown by Size (response time / service time)"));
+
+    frame.render_widget(table, area);
+}
 
-        for _ in 0..requests_per_row {
-            constraints_h.push(Constraint::Length(req_width as u16));
-        }
-        for _ in 0..requests_per_row {
-            constraints_v.push(Constraint::Length(req_height as u16));
+/// Average and 99th percentile response time of each request type and size,
+/// the class with the highest percentile in red.
+fn render_class_latencies(
+    frame: &mut Frame,
+    area: Rect,
+    classes: &HashMap<(RequestType, RequestSize), ClassStats>,
+) {
+    let slowest = classes
+        .iter()
+        .filter_map(|(class, stats)| Some((class, stats.latencies().percentile(0.99)?)))
+        .max_by_key(|(_, p99)| *p99)
+        .map(|(class, _)| *class);
+
+    let header = Row::new(
+        std::iter::once("Type".to_string()).chain(REQ_SIZES.iter().map(|size| format!("{size:?}"))),
+    )
+    .style(Style::default().add_modifier(style::Modifier::BOLD));
+
+    let rows: Vec<Row> = REQ_TYPES
+        .iter()
+        .map(|kind| {
+            let cells = REQ_SIZES.iter().map(|size| {
+                let text = match classes.get(&(*kind, *size)) {
+                    Some(stats) => format!(
+                        "{:.0}ms / {}ms ({})",
+                        stats.response_times().average(),
+                        stats.latencies().percentile(0.99).unwrap_or_default(),
+                        stats.response_times().count()
+                    ),
+                    None => "-".to_string(),
+                };
+                let style = if slowest == Some((*kind, *size)) {
+                    theme().error
+                } else {
+                    Style::default()
+                };
+                Cell::from(text).style(style)
+            });
+
+            Row::new(std::iter::once(Cell::from(format!("{kind:?}"))).chain(cells))
+        })
+        .collect();
+
+    let table = Table::new(rows, [Constraint::Fill(1); REQ_SIZES.len() + 1])
+        .header(header)
+        .block(Block::bordered().title("Latency by Type and Size (avg / p99, count)"));
+
+    frame.render_widget(table, area);
+}
+
+fn render_queue_history(
+    frame: &mut Frame,
+    area: Rect,
+    history: &QueueHistory,
+    servers: &[ServerState],
+) {
+    let [queues_area, workloads_area] =
+        Layout::vertical([Constraint::Fill(1), Constraint::Fill(1)]).areas(area);
+
+    let end = history.latest().max(HISTORY_SPAN);
+    let bounds = [end - HISTORY_SPAN, end];
+    let series: Vec<_> = servers
+        .iter()
+        .map(|server| history.series(server.id, bounds[0]))
+        .collect();
+
+    let capacity = server::queue_capacity() as f64;
+
+    frame.render_widget(
+        history_chart(
+            "Queue Length (last 2 min)",
+            servers,
+            series.iter().map(|(queues, _)| queues.as_slice()).collect(),
+            capacity,
+            bounds,
+        ),
+        queues_area,
+    );
+    frame.render_widget(
+        history_chart(
+            "Workload in ms (last 2 min)",
+            servers,
+            series
+                .iter()
+                .map(|(_, workloads)| workloads.as_slice())
+                .collect(),
+            1000.0,
+            bounds,
+        ),
+        workloads_area,
+    );
+}
+
+/// Queue length of every server over the last seconds, so an imbalance shows
+/// as diverging lines.
+fn render_live_queues(
+    frame: &mut Frame,
+    area: Rect,
+    history: &QueueHistory,
+    servers: &[ServerState],
+) {
+    let end = history.latest().max(LIVE_QUEUE_SPAN);
+    let bounds = [end - LIVE_QUEUE_SPAN, end];
+    let series: Vec<_> = servers
+        .iter()
+        .map(|server| history.series(server.id, bounds[0]).0)
+        .collect();
+
+    let title = format!("Queue Length (last {LIVE_QUEUE_SPAN:.0}s)");
+    frame.render_widget(
+        history_chart(
+            &title,
+            servers,
+            series.iter().map(Vec::as_slice).collect(),
+            // At least 5 requests high, so a queue of one doesn't fill the chart.
+            5.0,
+            bounds,
+        ),
+        area,
+    );
+}
+
+/// A line per server over the time bounds (s), scaled to the highest value
+/// but at least to `floor`.
+fn history_chart<'a>(
+    title: &'a str,
+    servers: &[ServerState],
+    points: Vec<&'a [(f64, f64)]>,
+    floor: f64,
+    bounds: [f64; 2],
+) -> Chart<'a> {
+    let max = points
+        .iter()
+        .flat_map(|points| points.iter().map(|(_, value)| *value))
+        .fold(floor, f64::max);
+
+    let datasets = servers
+        .iter()
+        .zip(points)
+        .enumerate()
+        .map(|(idx, (server, points))| {
+            Dataset::default()
+                .name(format!("Server {}", server.id))
+                .marker(symbols::Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(theme().servers[idx % MAX_SERVERS]))
+                .data(points)
+        })
+        .collect();
+
+    Chart::new(datasets)
+        .block(Block::bordered().title(title))
+        .x_axis(
+            Axis::default()
+                .bounds(bounds)
+                .labels([format!("{:.0}s", bounds[0]), format!("{:.0}s", bounds[1])]),
+        )
+        .y_axis(
+            Axis::default()
+                .bounds([0.0, max])
+                .labels(["0".to_string(), format!("{max:.0}")]),
+        )
+        .hidden_legend_constraints((Constraint::Ratio(1, 3), Constraint::Ratio(1, 2)))
+}
+
+fn render_requests(
+    frame: &mut Frame,
+    area: Rect,
+    requests: &VecDeque<Request>,
+    servers: &[ServerState],
+) {
+    let full = requests.len() >= PENDING_REQUESTS_LIMIT as usize;
+    let style = if full {
+        theme().error
+    } else {
+        Style::default()
+    };
+    let oldest = oldest_age(requests);
+    let selected = {
+        let mut selected_request = SELECTED_REQUEST.lock().unwrap();
+        if let Some(id) = *selected_request
+            && !requests.iter().any(|request| request.id == id)
+        {
+            // Assigned, or dropped, since it was clicked.
+            *selected_request = None;
         }
+        *selected_request
+    };
+    let block = Block::bordered()
+        .title(format!(
+            "Pending Requests{}{}",
+            cue(full, " FULL"),
+            match oldest {
+                Some(age) => format!(" (oldest {:.1}s)", age.as_secs_f64()),
+                None => String::new(),
+            }
+        ))
+        .style(style)
+        // Lit up along with the servers requests were just assigned to.
+        .border_style(if servers.iter().any(holds_just_assigned) {
+            theme().info.add_modifier(style::Modifier::BOLD)
+        } else {
+            age_style(oldest)
+        });
+    let inner_area = block.inner(area);
 
-        let mut request_idx = 0;
+    let req_width = 21;
+    let req_height = 4;
+
+    let requests_per_row = (inner_area.width as usize / req_width).max(1);
+    let rows_available = (inner_area.height as usize / req_height).max(1);
+    let per_page = requests_per_row * rows_available;
+    let pages = requests.len().div_ceil(per_page).max(1);
+    let page = REQUESTS_PAGE.load(Ordering::SeqCst).min(pages - 1);
+    REQUESTS_PAGE.store(page, Ordering::SeqCst);
+
+    let block = if pages > 1 {
+        let more = requests.len().saturating_sub((page + 1) * per_page);
+        block.title_bottom(
+            text::Line::from(format!(
+                " Page {}/{pages}{}  [PgUp PgDn] ",
+                page + 1,
+                if more > 0 {
+                    format!("  +{more} more")
+                } else {
+                    String::new()
+                }
+            ))
+            .right_aligned(),
+        )
+    } else {
+        block
+    };
+    let block = match selected.and_then(|id| requests.iter().find(|request| request.id == id)) {
+        Some(request) => block.title(
+            text::Line::from(format!(
+                " #{} {}  {:?} Priority  {}  Waiting {:.1}s ",
+                request.id,
+                request.get_name(),
+                request.priority,
+                TENANTS[request.tenant].name,
+                clock::since(request.created_at).as_secs_f64()
+            ))
+            .right_aligned(),
+        ),
+        None => block,
+    };
+    frame.render_widget(block, area);
+
+    let mut cells = REQUEST_CELLS.lock().unwrap();
+    cells.clear();
+
+    if !requests.is_empty() {
+        let mut request_idx = page * per_page;
         for row in 0..rows_available {
             if request_idx >= requests.len() {
                 break;
@@ -314,14 +1495,42 @@ fn render_requests(frame: &mut Frame, area: Rect, requests: &VecDeque<Request>)
 
                 let cell_x = (col * req_width) as u16 + inner_area.x;
                 let cell_y = (row * req_height) as u16 + inner_area.y;
-                let cell_area = Rect::new(cell_x, cell_y, req_width as u16, req_height as u16);
+                let cell_area = Rect::new(cell_x, cell_y, req_width as u16, req_height as u16)
+                    .intersection(inner_area);
 
-                let req_block = Block::bordered().style(first_req_style(request_idx));
+                // Blocked requests wait on their dependencies, whatever their place.
+                let blocked = is_blocked(request, requests, servers);
+
+                let req_block = Block::bordered()
+                    .border_type(if selected == Some(request.id) {
+                        BorderType::Thick
+                    } else {
+                        BorderType::Plain
+                    })
+                    .style(if blocked {
+                        theme().muted
+                    } else {
+                        first_req_style(request_idx)
+                    });
                 frame.render_widget(req_block.clone(), cell_area);
+                cells.push((cell_area, request.id));
 
                 let inner_req_area = req_block.inner(cell_area);
-                let req_text = Paragraph::new(format!("{}\n(#{})", request.get_name(), request.id))
-                    .alignment(layout::Alignment::Center);
+                let req_label = format!(
+                    "{}{}{}\n(#{}{})",
+                    cue(request_idx == 0 && !blocked, "▶ "),
+                    request.get_name(),
+                    if blocked { " ⛓" } else { "" },
+                    request.id,
+                    request.get_attributes()
+                );
+                let req_text = Paragraph::new(req_label)
+                    .alignment(layout::Alignment::Center)
+                    .style(if blocked {
+                        theme().muted
+                    } else {
+                        priority_style(request.priority)
+                    });
 
                 frame.render_widget(req_text, inner_req_area);
 
@@ -331,53 +1540,203 @@ fn render_requests(frame: &mut Frame, area: Rect, requests: &VecDeque<Request>)
     }
 }
 
-fn render_servers(frame: &mut Frame, area: Rect, servers: &[ServerState; 3]) {
-    let servers_layout = Layout::horizontal([Constraint::Fill(1); 3]).split(area);
+fn render_servers(
+    frame: &mut Frame,
+    area: Rect,
+    servers: &[ServerState],
+    stats: &HashMap<u64, ServerStats>,
+) {
+    let servers_layout = Layout::horizontal(vec![Constraint::Fill(1); servers.len()]).split(area);
 
-    for i in 0..3 {
-        SERVER_AREAS[i].update_from(servers_layout[i]);
+    for (area, rect) in SERVER_AREAS.iter().zip(servers_layout.iter()) {
+        area.update_from(*rect);
     }
 
+    let selected_server = SELECTED_SERVER
+        .load(Ordering::SeqCst)
+        .min(servers.len().saturating_sub(1));
+    SELECTED_SERVER.store(selected_server, Ordering::SeqCst);
+
     for (idx, server) in servers.iter().enumerate() {
-        let style = if server.queue.len() >= server.queue.capacity() {
-            Style::default().fg(style::Color::Red)
+        let full = server.queue_full();
+        let style = if crash_flash(server) {
+            theme().error.add_modifier(style::Modifier::REVERSED)
+        } else if full {
+            theme().error
+        } else if server.paused {
+            theme().paused
+        } else if server.is_stalled() {
+            theme().stalled
+        } else {
+            match server.status {
+                ServerStatus::Active => Style::default(),
+                ServerStatus::Draining => theme().warning,
+                ServerStatus::Drained => theme().muted,
+            }
+        };
+        let border_type = if idx == selected_server {
+            BorderType::Thick
+        } else {
+            BorderType::Plain
+        };
+        let status = match server.status {
+            ServerStatus::Active => "",
+            ServerStatus::Draining => " Draining",
+            ServerStatus::Drained => " Drained",
+        };
+        let stalled = if server.paused {
+            " Paused"
+        } else if server.is_stalled() {
+            " Stalled"
         } else {
-            Style::default()
+            ""
+        };
+        let warming = if server.is_warming_up() {
+            " Warming"
+        } else {
+            ""
         };
 
+        let zone = server
+            .zone
+            .map(|zone| format!(" @{zone}"))
+            .unwrap_or_default();
+        let oldest = oldest_age(&server.queue);
+        let assigned = holds_just_assigned(server);
         let server_block = Block::bordered()
+            .border_type(border_type)
             .title(format!(
-                "Server {} (Load {}ms)",
-                server.id, server.total_workload
+                "Server {}{}{} {} [{}/{}] {} (Load {}ms) CB:{}{}{}{}",
+                server.id,
+                zone,
+                cue(full, " FULL"),
+                server.profile,
+                server.in_flight.len(),
+                server.workers,
+                server.discipline,
+                server.total_workload,
+                server.breaker,
+                status,
+                stalled,
+                warming
+            ))
+            .title_bottom(format!(
+                "Tier {}  Util {:.0}%  {}  Mem {}/{}MB{}{}",
+                server.tier,
+                server.recent_utilization() * 100.0,
+                match server.rate_limit {
+                    Some(limit) => format!("Rate {}/{} req/sec", server.current_rate(), limit),
+                    None => format!("Rate {} req/sec", server.current_rate()),
+                },
+                server.memory_used(),
+                server.memory,
+                if server.failure_rate > 0.0 {
+                    format!("  Fail {:.0}%", server.failure_rate * 100.0)
+                } else {
+                    String::new()
+                },
+                match server.cache_hit_rate() {
+                    Some(rate) => format!("  Cache {:.0}%", rate * 100.0),
+                    None => String::new(),
+                }
             ))
-            .style(style);
+            .style(style)
+            .border_style(if assigned {
+                theme().info.add_modifier(style::Modifier::BOLD)
+            } else {
+                age_style(oldest)
+            });
 
         let inner_area = server_block.inner(servers_layout[idx]);
 
         frame.render_widget(server_block, servers_layout[idx]);
 
-        if !server.queue.is_empty() {
+        // Its latest completions along the bottom, when there's room left
+        // for a request above them.
+        let inner_area = if inner_area.height > 3
+            && let Some(stats) = stats.get(&server.id)
+        {
+            let [requests_area, throughput_area] =
+                Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(inner_area);
+            render_throughput(frame, throughput_area, stats.completions());
+            requests_area
+        } else {
+            inner_area
+        };
+
+        let requests: Vec<(&Request, u64)> = server
+            .in_flight
+            .iter()
+            .chain(&server.preempted)
+            .map(|in_flight| (&in_flight.request, in_flight.service_time))
+            .chain(
+                server
+                    .queue
+                    .iter()
+                    .map(|request| (request, request.get_time())),
+            )
+            .collect();
+
+        if !requests.is_empty() {
             let visible_height = inner_area.height as usize / 3; // Each item is 3 rows tall
             let visible_items = visible_height.max(1);
 
             let scroll_pos = SERVER_SCROLL[idx]
                 .load(Ordering::SeqCst)
-                .min(server.queue.len().saturating_sub(visible_items));
+                .min(requests.len().saturating_sub(visible_items));
 
-            let visible_requests = server.queue.iter().skip(scroll_pos).take(visible_items);
+            let visible_requests = requests.iter().skip(scroll_pos).take(visible_items);
 
             let req_layout =
                 Layout::vertical(vec![Constraint::Length(3); visible_items]).split(inner_area);
 
-            for (req_idx, request) in visible_requests.enumerate() {
-                let req_text = Paragraph::new(text::Line::raw(format!(
-                    "{} (#{}) - {}ms",
+            for (req_idx, (request, service_time)) in visible_requests.enumerate() {
+                let position = scroll_pos + req_idx;
+                let style = if just_assigned(request) {
+                    theme().info.add_modifier(style::Modifier::BOLD)
+                } else if position < server.in_flight.len() {
+                    in_flight_style(true)
+                } else if position < server.in_flight.len() + server.preempted.len() {
+                    theme().warning
+                } else if !request.has_arrived() {
+                    // Payload still on its way to the server.
+                    theme().muted
+                } else {
+                    Style::default()
+                };
+
+                let label = format!(
+                    "{}{} (#{}{}) - {}ms{}",
+                    cue(position < server.in_flight.len(), "▶ "),
                     request.get_name(),
                     request.id,
-                    request.get_time()
-                )))
-                .alignment(layout::Alignment::Center)
-                .block(Block::bordered().style(first_req_style(req_idx)));
+                    request.get_fork_label(),
+                    service_time,
+                    if request.has_arrived() { "" } else { " ⇣" }
+                );
+
+                // The ones being processed fill up as they progress.
+                if let Some(in_flight) = server.in_flight.get(position) {
+                    let progress = 1.0
+                        - in_flight.remaining_time() as f64 / in_flight.service_time.max(1) as f64;
+                    let gauge = Gauge::default()
+                        .block(Block::bordered().style(style))
+                        .gauge_style(style)
+                        .use_unicode(true)
+                        .ratio(progress.clamp(0.0, 1.0))
+                        .label(text::Span::styled(
+                            format!("{label} {:.0}%", progress.clamp(0.0, 1.0) * 100.0),
+                            priority_style(request.priority),
+                        ));
+
+                    frame.render_widget(gauge, req_layout[req_idx]);
+                    continue;
+                }
+
+                let req_text =
+                    Paragraph::new(text::Line::styled(label, priority_style(request.priority)))
+                        .alignment(layout::Alignment::Center)
+                        .block(Block::bordered().style(style));
 
                 frame.render_widget(req_text, req_layout[req_idx]);
             }
@@ -385,55 +1744,715 @@ fn render_servers(frame: &mut Frame, area: Rect, servers: &[ServerState; 3]) {
     }
 }
 
+/// The mark if it applies and the theme marks things with text as well as
+/// color.
+fn cue(applies: bool, mark: &str) -> &str {
+    if applies && theme().cues { mark } else { "" }
+}
+
+fn priority_style(priority: Priority) -> Style {
+    match priority {
+        Priority::High => theme().high_priority,
+        Priority::Normal => Style::default(),
+        Priority::Low => theme().muted,
+    }
+}
+
+/// Whether the request was assigned within the last `ASSIGN_FLASH` of real
+/// time, whatever the speed.
+fn just_assigned(request: &Request) -> bool {
+    request
+        .assigned_at
+        .is_some_and(|at| clock::since(at) < ASSIGN_FLASH.mul_f64(clock::speed()))
+}
+
+fn holds_just_assigned(server: &ServerState) -> bool {
+    server
+        .in_flight
+        .iter()
+        .map(|in_flight| &in_flight.request)
+        .chain(&server.queue)
+        .any(just_assigned)
+}
+
+/// Blinks for a while after the server crashed.
+fn crash_flash(server: &ServerState) -> bool {
+    server.crashed_at.is_some_and(|at| {
+        let elapsed = clock::since(at);
+        elapsed < CRASH_FLASH && (elapsed.as_millis() / 250).is_multiple_of(2)
+    })
+}
+
 fn first_req_style(idx: usize) -> Style {
-    if idx == 0 {
-        Style::default().fg(style::Color::Green)
+    in_flight_style(idx == 0)
+}
+
+fn in_flight_style(in_flight: bool) -> Style {
+    if in_flight {
+        theme().good
     } else {
         Style::default()
     }
 }
 
-fn render_configs(frame: &mut Frame, area: Rect, config: &SystemConfig) {
-    let block = Block::bordered().title("Configs");
-    let inner_area = block.inner(area);
+fn render_server_stats(
+    frame: &mut Frame,
+    area: Rect,
+    servers: &[ServerState],
+    stats: &HashMap<u64, ServerStats>,
+) {
+    let header = Row::new([
+        "Server",
+        "Processed",
+        "Avg",
+        "p50",
+        "p95",
+        "p99",
+        "Util",
+        "Failed",
+        "Expired",
+        "Oldest",
+    ])
+    .style(Style::default().add_modifier(style::Modifier::BOLD));
+
+    let latency = |latency: Option<f64>| latency.map_or("-".to_string(), |l| format!("{l:.0}ms"));
+    let rows: Vec<Row> = servers
+        .iter()
+        .map(|server| {
+            let server_stats = stats.get(&server.id);
+            let percentile =
+                |share| latency(server_stats.and_then(|stats| stats.percentile(share)));
+
+            Row::new([
+                format!("Server {}", server.id),
+                server_stats
+                    .map_or(0, |stats| stats.response_times().count())
+                    .to_string(),
+                latency(
+                    server_stats
+                        .filter(|stats| stats.response_times().count() > 0)
+                        .map(|stats| stats.response_times().average()),
+                ),
+                percentile(0.5),
+                percentile(0.95),
+                percentile(0.99),
+                format!("{:.1}%", server.utilization() * 100.0),
+                server_stats.map_or(0, |stats| stats.failed()).to_string(),
+                server_stats.map_or(0, |stats| stats.expired()).to_string(),
+                oldest_age(&server.queue)
+                    .map_or("-".to_string(), |age| format!("{:.1}s", age.as_secs_f64())),
+            ])
+        })
+        .collect();
+
+    // Server and Processed are the widest; the others share the rest.
+    let widths = [Constraint::Length(9), Constraint::Length(10)]
+        .into_iter()
+        .chain([Constraint::Fill(1); 8]);
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(Block::bordered().title("Per-Server Statistics"));
+
+    frame.render_widget(table, area);
+}
 
-    frame.render_widget(block, area);
+/// Bar per power of two of the response times, labelled by its lowest value.
+fn render_histogram(frame: &mut Frame, area: Rect, latencies: &LatencyHistogram) {
+    let percentile = |share| {
+        latencies
+            .percentile(share)
+            .map_or("-".to_string(), |latency| format!("{latency}ms"))
+    };
+    let block = Block::bordered().title(format!(
+        "Latency Histogram  p50 {}  p99 {}  p99.9 {}",
+        percentile(0.5),
+        percentile(0.99),
+        percentile(0.999)
+    ));
 
-    let stats_text = text::Text::from(vec![
-        text::Line::from(format!("⮜ ⮞ Policy: {}", config.choice_mode)),
-        text::Line::from(format!(
-            "⮝ ⮟ Arrival Rate (λ): {:.1} req/sec",
-            config.arrival_rate
-        )),
-        text::Line::from(format!("[Q] Quit")),
-    ]);
+    let bars: Vec<Bar> = latencies
+        .octaves()
+        .into_iter()
+        .map(|(lower, count)| {
+            let label = if lower >= 1000 {
+                format!("{}k", lower / 1000)
+            } else {
+                lower.to_string()
+            };
+            Bar::default().value(count).label(text::Line::from(label))
+        })
+        .collect();
+
+    let chart = BarChart::default()
+        .block(block)
+        .data(BarGroup::default().bars(&bars))
+        .bar_width(4)
+        .bar_gap(1)
+        .bar_style(theme().info);
+
+    frame.render_widget(chart, area);
+}
 
-    let stats_widget = Paragraph::new(stats_text);
-    frame.render_widget(stats_widget, inner_area);
+/// Percentiles marked on the latency distribution, with their labels.
+const DISTRIBUTION_PERCENTILES: [(f64, &str); 4] =
+    [(0.5, "p50"), (0.9, "p90"), (0.99, "p99"), (0.999, "p99.9")];
+
+/// Response times at the histogram's full precision, on a logarithmic scale
+/// so the tail gets as much room as the bulk, with the percentiles marked.
+fn render_distribution(frame: &mut Frame, area: Rect, latencies: &LatencyHistogram) {
+    // Buckets are placed by the log2 of their middle; 0ms goes with 1ms.
+    let position = |latency: f64| latency.max(1.0).log2();
+    let points: Vec<(f64, f64)> = latencies
+        .buckets()
+        .map(|(lower, upper, count)| (position((lower + upper) as f64 / 2.0), count as f64))
+        .collect();
+
+    let block = Block::bordered().title("Latency Distribution");
+    if points.is_empty() {
+        frame.render_widget(
+            Paragraph::new("No processed requests yet").block(block),
+            area,
+        );
+        return;
+    }
+
+    let max = points.iter().map(|(_, count)| *count).fold(1.0, f64::max);
+    let lowest = points[0].0.floor();
+    let highest = (points[points.len() - 1].0.ceil()).max(lowest + 1.0);
+
+    let markers: Vec<(String, [(f64, f64); 2])> = DISTRIBUTION_PERCENTILES
+        .iter()
+        .filter_map(|(share, label)| {
+            let latency = latencies.percentile(*share)?;
+            let x = position(latency as f64);
+            Some((format!("{label} {latency}ms"), [(x, 0.0), (x, max)]))
+        })
+        .collect();
+    let marker_styles = [
+        theme().good,
+        theme().warning,
+        theme().error,
+        theme().high_priority,
+    ];
+
+    let mut datasets = vec![
+        Dataset::default()
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Bar)
+            .style(theme().info)
+            .data(&points),
+    ];
+    datasets.extend(
+        markers
+            .iter()
+            .zip(marker_styles)
+            .map(|((name, line), style)| {
+                Dataset::default()
+                    .name(name.as_str())
+                    .marker(symbols::Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(style)
+                    .data(line)
+            }),
+    );
+
+    // A label per power of two would crowd narrow charts, so four evenly
+    // spaced ones.
+    let labels: Vec<String> = (0..4)
+        .map(|step| {
+            let latency = (lowest + (highest - lowest) * step as f64 / 3.0).exp2();
+            if latency >= 1000.0 {
+                format!("{:.1}s", latency / 1000.0)
+            } else {
+                format!("{latency:.0}ms")
+            }
+        })
+        .collect();
+
+    let chart = Chart::new(datasets)
+        .block(block)
+        .x_axis(Axis::default().bounds([lowest, highest]).labels(labels))
+        .y_axis(
+            Axis::default()
+                .bounds([0.0, max])
+                .labels(["0".to_string(), format!("{max:.0}")]),
+        )
+        .hidden_legend_constraints((Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)));
+
+    frame.render_widget(chart, area);
 }
 
-fn render_stats(frame: &mut Frame, area: Rect, stats: &SystemStats) {
-    let block = Block::bordered().title("Statistics");
+fn render_configs(frame: &mut Frame, area: Rect, config: &SystemConfig, servers: usize) {
+    let block = Block::bordered().title("Configs");
     let inner_area = block.inner(area);
 
     frame.render_widget(block, area);
 
     let stats_text = text::Text::from(vec![
-        text::Line::from(format!("Total Requests: {}", stats.total_requests)),
-        text::Line::from(format!("Processed: {}", stats.processed_requests)),
+        text::Line::from(match config.delegate_mode {
+            Some(delegate_mode) => {
+                format!("⮜ ⮞ Policy: {} ({})", config.choice_mode, delegate_mode)
+            }
+            None => format!("⮜ ⮞ Policy: {}", config.choice_mode),
+        }),
         text::Line::from(format!(
-            "Average Response Time: {:.1}ms",
-            stats.avg_wait_time
+            "+ - Arrival Rate (λ): {:.1} req/sec",
+            config.arrival_rate
         )),
-        text::Line::from(format!("Throughput: {:.2} req/sec", stats.throughput)),
-    ]);
-
-    let stats_widget = Paragraph::new(stats_text);
-    frame.render_widget(stats_widget, inner_area);
-}
-
-fn render_logs(frame: &mut Frame, area: Rect, logs: &Vec<String>) {
-    let block = Block::bordered().title("Event Log");
+        text::Line::from(format!(
+            "[A] Admission Control: {}",
+            if config.admission_control {
+                "On"
+            } else {
+                "Off"
+            }
+        )),
+        text::Line::from(format!(
+            "[L] Large Anti-Affinity: {}",
+            if config.anti_affinity { "On" } else { "Off" }
+        )),
+        text::Line::from(format!("[Ins Del] Servers: {}", servers)),
+        text::Line::from(format!(
+            "[X] Autoscaling: {}",
+            if config.autoscaling { "On" } else { "Off" }
+        )),
+        text::Line::from(format!(
+            "[C] Contention: {}",
+            if config.contention { "On" } else { "Off" }
+        )),
+        text::Line::from(format!(
+            "[G] Stalls: {}",
+            if config.stalls { "On" } else { "Off" }
+        )),
+        text::Line::from(format!(
+            "[P] SRPT Preemption: {}",
+            if config.preemption { "On" } else { "Off" }
+        )),
+        text::Line::from(format!(
+            "[T] Retries: {}",
+            if config.retries { "On" } else { "Off" }
+        )),
+        text::Line::from(format!(
+            "[B] Bursts: {}",
+            if config.bursts { "On" } else { "Off" }
+        )),
+        text::Line::from(format!(
+            "[E] Sessions: {}",
+            if config.sessions { "On" } else { "Off" }
+        )),
+        text::Line::from(format!(
+            "[W] Batching: {}",
+            if config.batching { "On" } else { "Off" }
+        )),
+        text::Line::from(format!(
+            "[F] Fork-Join: {}",
+            if config.forks { "On" } else { "Off" }
+        )),
+        text::Line::from(format!(
+            "[I] Pipelines: {}",
+            if config.pipelines { "On" } else { "Off" }
+        )),
+        text::Line::from(format!(
+            "[N] Network: {}",
+            if config.network { "On" } else { "Off" }
+        )),
+        text::Line::from(format!(
+            "[H] Dependencies: {}",
+            if config.dependencies { "On" } else { "Off" }
+        )),
+        text::Line::from(format!(
+            "[J] Deduplication: {}",
+            if config.deduplication { "On" } else { "Off" }
+        )),
+        text::Line::from(format!(
+            "[M] Warm Cache: {}",
+            if config.caching { "On" } else { "Off" }
+        )),
+        text::Line::from(format!(
+            "[V] Fair Queueing: {}",
+            if config.fair_queueing { "On" } else { "Off" }
+        )),
+        text::Line::from(format!("[Y] Theme: {}", theme().name)),
+        text::Line::from("[S] Select Server  [D] Drain  [O] Queue Order  [K] Crash"),
+        text::Line::from("[R] Rate Limit  [U] Failure Rate  [Space] Pause"),
+        text::Line::from("[Q] Quit"),
+    ]);
+
+    let stats_widget = Paragraph::new(stats_text);
+    frame.render_widget(stats_widget, inner_area);
+}
+
+/// Requests processed in each of the last seconds, oldest first, as a
+/// sparkline ending at the present.
+fn render_throughput(frame: &mut Frame, area: Rect, completions: &SlidingWindow<()>) {
+    let seconds = THROUGHPUT_HISTORY.as_secs() as usize;
+    let mut per_second = vec![0; seconds];
+    for (completion, _) in completions.iter() {
+        let age = clock::since(completion).as_secs() as usize;
+        if age < seconds {
+            per_second[seconds - 1 - age] += 1;
+        }
+    }
+
+    // The sparkline drops what doesn't fit on the right, the newest bars.
+    let shown = per_second.len().min(area.width as usize);
+    let sparkline = Sparkline::default()
+        .data(&per_second[per_second.len() - shown..])
+        .style(theme().info);
+
+    frame.render_widget(sparkline, area);
+}
+
+/// Share of the room for requests in use, counting the pending list up to
+/// its limit and every server's queue up to its capacity, as a single "how
+/// bad is it" gauge.
+fn render_saturation(frame: &mut Frame, area: Rect, state: &SystemState) {
+    let pending = state.pending_requests().len();
+    let limit = PENDING_REQUESTS_LIMIT as usize;
+    let queued: usize = state
+        .servers()
+        .iter()
+        .map(|server| server.queue.len())
+        .sum();
+    let capacity = state.servers().len() * server::queue_capacity();
+
+    let saturation = ((pending.min(limit) + queued.min(capacity)) as f64
+        / (limit + capacity).max(1) as f64)
+        .clamp(0.0, 1.0);
+    let style = if saturation >= SATURATION_CRITICAL {
+        theme().error
+    } else if saturation >= SATURATION_WARNING {
+        theme().warning
+    } else {
+        theme().good
+    };
+
+    let gauge = Gauge::default()
+        .block(Block::bordered().title(format!(
+            "Saturation{}",
+            cue(saturation >= SATURATION_CRITICAL, " CRITICAL")
+        )))
+        .gauge_style(style)
+        .use_unicode(true)
+        .ratio(saturation)
+        .label(format!(
+            "{:.0}%  {pending}/{limit} pending  {queued}/{capacity} queued",
+            saturation * 100.0
+        ));
+
+    frame.render_widget(gauge, area);
+}
+
+/// Time since the arrival of the oldest of the requests.
+fn oldest_age<'a>(requests: impl IntoIterator<Item = &'a Request>) -> Option<Duration> {
+    requests
+        .into_iter()
+        .map(|request| clock::since(request.created_at))
+        .max()
+}
+
+/// Border of a queue whose oldest request waited this long, to surface
+/// starvation.
+fn age_style(oldest: Option<Duration>) -> Style {
+    match oldest {
+        Some(age) if age >= AGE_CRITICAL => theme().error,
+        Some(age) if age >= AGE_WARNING => theme().warning,
+        _ => Style::default(),
+    }
+}
+
+/// Heatmap cell of a share in percent, from the theme's coolest style for a
+/// small share (blue by default) to its hottest for most of it (red).
+fn heat_style(share: f64) -> Style {
+    let level = match share {
+        share if share >= 80.0 => 4,
+        share if share >= 60.0 => 3,
+        share if share >= 40.0 => 2,
+        share if share >= 20.0 => 1,
+        _ => 0,
+    };
+    theme().heat[level]
+}
+
+/// Share of the total the count is, in percent.
+fn percentage(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 / total as f64 * 100.0
+    }
+}
+
+/// Jain's fairness index of the values, `(Σx)² / (n·Σx²)`: 1 when they are
+/// all equal, down to `1/n` when a single one is nonzero. `None` while all
+/// are zero.
+fn jain_index(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (count, sum, sum_of_squares) = values.fold((0, 0.0, 0.0), |(count, sum, squares), x| {
+        (count + 1, sum + x, squares + x * x)
+    });
+
+    (sum_of_squares > 0.0).then(|| sum * sum / (count as f64 * sum_of_squares))
+}
+
+fn fairness_label(index: Option<f64>) -> String {
+    index.map_or("-".to_string(), |index| format!("{index:.2}"))
+}
+
+/// Mean of the servers' utilization over the shared window.
+fn average_utilization(servers: &[ServerState]) -> f64 {
+    if servers.is_empty() {
+        return 0.0;
+    }
+
+    servers
+        .iter()
+        .map(ServerState::recent_utilization)
+        .sum::<f64>()
+        / servers.len() as f64
+}
+
+fn render_stats(frame: &mut Frame, area: Rect, state: &SystemState) {
+    let (stats, servers) = (state.stats(), state.servers());
+    let block = Block::bordered().title(match state.warming_up_until() {
+        Some(until) => format!(
+            "Statistics ({}s window, warming up, {}s left)",
+            window().as_secs(),
+            until.saturating_duration_since(clock::now()).as_secs() + 1
+        ),
+        None => format!("Statistics ({}s window)", window().as_secs()),
+    });
+    let inner_area = block.inner(area);
+
+    frame.render_widget(block, area);
+
+    // Requests that left the system, processed or dropped along the way.
+    let dropped = stats.rejected_requests() + stats.expired_requests() + stats.failed_requests();
+    let resolved = stats.processed_requests() + dropped;
+
+    let mut stats_text = text::Text::from(vec![
+        text::Line::from(format!("Total Requests: {}", stats.total_requests())),
+        text::Line::from(format!("Processed: {}", stats.processed_requests())),
+        text::Line::from(format!(
+            "Average Response Time: {}",
+            stats.wait_times().summary()
+        )),
+        text::Line::from(format!(
+            "Pending / Queued / Processing: {:.0} / {:.0} / {:.0}ms",
+            stats.pending_times().average(),
+            stats.queue_times().average(),
+            stats.processing_times().average()
+        )),
+        text::Line::from(format!(
+            "Slowdown: {:.1} avg, {:.1} p99",
+            stats.slowdowns().average(),
+            stats.slowdowns().p99().unwrap_or_default()
+        )),
+        text::Line::from(format!("Throughput: {:.2} req/sec", stats.throughput())),
+        text::Line::from(format!(
+            "Avg Utilization: {:.0}%",
+            average_utilization(servers) * 100.0
+        )),
+        text::Line::from(format!(
+            "Jain Fairness: {} processed  {} workload",
+            fairness_label(jain_index(servers.iter().map(|server| {
+                stats
+                    .servers()
+                    .get(&server.id)
+                    .map_or(0, |stats| stats.response_times().count()) as f64
+            }))),
+            fairness_label(jain_index(
+                servers
+                    .iter()
+                    .map(|server| server.remaining_workload() as f64)
+            ))
+        )),
+        text::Line::from(format!(
+            "Rejected: {} ({:.1}%)",
+            stats.rejected_requests(),
+            percentage(stats.rejected_requests(), resolved)
+        )),
+        text::Line::from(format!(
+            "Expired: {} ({:.1}%)",
+            stats.expired_requests(),
+            percentage(stats.expired_requests(), resolved)
+        )),
+        text::Line::from(format!(
+            "Failed: {} ({:.1}%)",
+            stats.failed_requests(),
+            percentage(
+                stats.failed_requests(),
+                stats.failed_requests() + stats.processed_requests()
+            )
+        )),
+        text::Line::styled(
+            format!(
+                "Drop Rate: {:.1}% ({})",
+                percentage(dropped, resolved),
+                dropped
+            ),
+            if dropped > 0 {
+                theme().error
+            } else {
+                Style::default()
+            },
+        ),
+        text::Line::from(format!("Dedup Hits: {}", stats.dedup_hits())),
+        text::Line::from(format!(
+            "Deadline Misses: {} ({:.1}%)",
+            stats.deadline_misses(),
+            percentage(stats.deadline_misses(), stats.deadline_requests())
+        )),
+        text::Line::from(format!(
+            "Sticky Violations: {} ({:.1}%)",
+            stats.sticky_violations(),
+            percentage(stats.sticky_violations(), stats.session_requests())
+        )),
+        text::Line::from(format!(
+            "Batched: {:.1}ms ({})  Immediate: {:.1}ms ({})",
+            stats.batched().average(),
+            stats.batched().count(),
+            stats.immediate().average(),
+            stats.immediate().count()
+        )),
+        text::Line::from(format!(
+            "Join Latency: {:.1}ms ({} forks)",
+            stats.joins().average(),
+            stats.joins().count()
+        )),
+        text::Line::from(format!(
+            "Avg Transfer: {:.1}ms",
+            stats.transfers().average()
+        )),
+        text::Line::from(format!(
+            "Pipeline Latency: {:.1}ms ({})",
+            stats.pipelines().average(),
+            stats.pipelines().count()
+        )),
+        text::Line::from(format!("Total Cost: ${:.2}", stats.total_cost())),
+        text::Line::from(
+            servers
+                .iter()
+                .map(|server| format!("S{} ${:.2}", server.id, server.cost))
+                .collect::<Vec<_>>()
+                .join("  "),
+        ),
+        text::Line::from(match stats.key_remap_ratio() {
+            Some(ratio) => format!("Key Remap on Removal: {:.1}%", ratio * 100.0),
+            None => String::new(),
+        }),
+    ]);
+    stats_text.extend(stats.tags().iter().map(|(tag, times)| {
+        text::Line::from(format!(
+            "[{}] {} processed, {:.1}ms",
+            tag,
+            times.count(),
+            times.average()
+        ))
+    }));
+
+    let [text_area, sparkline_area] =
+        Layout::vertical([Constraint::Fill(1), Constraint::Length(3)]).areas(inner_area);
+
+    let stats_widget = Paragraph::new(stats_text);
+    frame.render_widget(stats_widget, text_area);
+
+    render_throughput(frame, sparkline_area, stats.throughput_window());
+}
+
+fn render_slo(frame: &mut Frame, area: Rect, slo: &SloTracker) {
+    let block = Block::bordered().title(format!("SLOs (last {}s)", SLO_WINDOW.as_secs()));
+    let inner_area = block.inner(area);
+
+    frame.render_widget(block, area);
+
+    let lines: Vec<text::Line> = SLO_TARGETS
+        .iter()
+        .map(|(size, target)| match slo.attainment(*size) {
+            Some((share, count)) => text::Line::styled(
+                format!(
+                    "{:?} < {}ms: {:.1}% ({})",
+                    size,
+                    target,
+                    share * 100.0,
+                    count
+                ),
+                if share >= SLO_OBJECTIVE {
+                    theme().good
+                } else {
+                    theme().error
+                },
+            ),
+            None => text::Line::from(format!("{:?} < {}ms: -", size, target)),
+        })
+        .collect();
+
+    frame.render_widget(Paragraph::new(lines), inner_area);
+}
+
+fn render_tenants(
+    frame: &mut Frame,
+    area: Rect,
+    tenants: &TenantTracker,
+    pending: &VecDeque<Request>,
+) {
+    let block = Block::bordered().title(format!("Tenants (last {}s)", window().as_secs()));
+    let inner_area = block.inner(area);
+
+    frame.render_widget(block, area);
+
+    let lines: Vec<text::Line> = TENANTS
+        .iter()
+        .enumerate()
+        .map(|(idx, tenant)| {
+            let queued = pending.iter().filter(|r| r.tenant == idx).count();
+            text::Line::from(format!(
+                "{} (w{}): {:.2} req/sec  Queue {:.0}%",
+                tenant.name,
+                tenant.weight,
+                tenants.throughput(idx),
+                if pending.is_empty() {
+                    0.0
+                } else {
+                    queued as f64 / pending.len() as f64 * 100.0
+                }
+            ))
+        })
+        .collect();
+
+    frame.render_widget(Paragraph::new(lines), inner_area);
+}
+
+fn render_logs(frame: &mut Frame, area: Rect, logs: &[LogLine]) {
+    // Newest first, so the selected line's place counts from the end.
+    let selected = if FOLLOW_LOGS.load(Ordering::SeqCst) {
+        0
+    } else {
+        let number = SELECTED_LOG.load(Ordering::SeqCst);
+        match logs.iter().position(|log| log.number() == number) {
+            Some(idx) => logs.len() - 1 - idx,
+            // Dropped as the log filled up, so the oldest left.
+            None => {
+                if let Some(oldest) = logs.first() {
+                    SELECTED_LOG.store(oldest.number(), Ordering::SeqCst);
+                }
+                logs.len().saturating_sub(1)
+            }
+        }
+    };
+
+    let block = if FOLLOW_LOGS.load(Ordering::SeqCst) {
+        Block::bordered().title("Event Log (following)")
+    } else {
+        Block::bordered()
+            .title(text::Line::from(vec![
+                text::Span::raw("Event Log "),
+                text::Span::styled(
+                    format!("(scrolled back, {selected} newer)"),
+                    theme().warning,
+                ),
+            ]))
+            .title_bottom(text::Line::from(" [End] Follow ").right_aligned())
+    };
     let inner_area = block.inner(area);
 
     frame.render_widget(block, area);
@@ -441,78 +2460,546 @@ fn render_logs(frame: &mut Frame, area: Rect, logs: &Vec<String>) {
     if !logs.is_empty() {
         let items: Vec<ListItem> = logs
             .iter()
-            .map(|log| ListItem::new(text::Line::from(log.clone())))
+            .map(|log| {
+                let style = if log.failed() {
+                    theme().error
+                } else {
+                    Style::default()
+                };
+                ListItem::new(text::Line::styled(log.text().to_string(), style))
+            })
             .rev()
             .collect();
 
-        let max_scroll = items.len().saturating_sub(1);
-        let current_log = SELECTED_LOG.load(Ordering::SeqCst);
-        SELECTED_LOG.store(current_log.min(max_scroll), Ordering::SeqCst);
-
         let logs_list = List::new(items)
             .block(Block::default())
             .highlight_style(Style::default().add_modifier(style::Modifier::REVERSED));
 
         let mut state = ListState::default();
-        state.select(Some(SELECTED_LOG.load(Ordering::SeqCst)));
+        state.select(Some(selected));
 
         frame.render_stateful_widget(logs_list, inner_area, &mut state);
+        LOG_OFFSET.store(state.offset(), Ordering::SeqCst);
+    }
+    LOG_AREA.update_from(inner_area);
+}
+
+/// Selects the log line at the given row, following the log again if it's
+/// the newest.
+fn click_log(logs: &[LogLine], row: u16) {
+    let area = LOG_AREA.rect();
+    let idx = LOG_OFFSET.load(Ordering::SeqCst) + (row - area.y) as usize;
+    // Newest first, as listed.
+    let Some(log) = logs.iter().rev().nth(idx) else {
+        return;
+    };
+
+    if idx == 0 {
+        FOLLOW_LOGS.store(true, Ordering::SeqCst);
+    } else {
+        FOLLOW_LOGS.store(false, Ordering::SeqCst);
+        SELECTED_LOG.store(log.number(), Ordering::SeqCst);
+    }
+}
+
+/// Index of the tab at the given column, laid out as `Tabs` does: each
+/// title padded with a space on both sides and followed by a divider.
+fn tab_at(column: u16) -> Option<usize> {
+    let mut start = TABS_AREA.rect().x;
+    for (idx, title) in TABS.iter().enumerate() {
+        let end = start + title.len() as u16 + 2;
+        if (start..end).contains(&column) {
+            return Some(idx);
+        }
+        start = end + 1;
+    }
+    None
+}
+
+/// Moves the selection one line back or forward through the log, leaving
+/// follow mode when scrolling back and taking it up again on the newest
+/// line.
+fn scroll_logs(logs: &[LogLine], older: bool) {
+    let Some(newest) = logs.last() else {
+        return;
+    };
+    if FOLLOW_LOGS.swap(false, Ordering::SeqCst) {
+        SELECTED_LOG.store(newest.number(), Ordering::SeqCst);
+    }
+
+    let number = SELECTED_LOG.load(Ordering::SeqCst);
+    let idx = logs
+        .iter()
+        .position(|log| log.number() == number)
+        .unwrap_or_default();
+    let idx = if older {
+        idx.saturating_sub(1)
+    } else {
+        idx + 1
+    };
+
+    match logs.get(idx) {
+        Some(log) if idx + 1 < logs.len() => SELECTED_LOG.store(log.number(), Ordering::SeqCst),
+        _ => FOLLOW_LOGS.store(true, Ordering::SeqCst),
+    }
+}
+
+fn selected_server(state: &SystemState) -> Option<&ServerState> {
+    state.servers().get(SELECTED_SERVER.load(Ordering::SeqCst))
+}
+
+fn toggle_pause(event_tx: &Sender<SystemEvent>, server: &ServerState) {
+    event_tx
+        .try_send(SystemEvent::ServerPaused {
+            server_id: server.id,
+            paused: !server.paused,
+        })
+        .ok();
+}
+
+/// Starts resizing the overview if the click is on the border between the
+/// servers and the statistics, or between the pending requests and the
+/// servers.
+fn start_drag((x, y): (u16, u16)) -> bool {
+    let processing = PROCESSING_AREA.rect();
+    let requests = REQUESTS_AREA.rect();
+    if processing.is_empty() {
+        return false;
+    }
+
+    let on_main_border = (processing.right().saturating_sub(1)..=processing.right()).contains(&x)
+        && (processing.top()..processing.bottom()).contains(&y);
+    let on_requests_border = (requests.bottom().saturating_sub(1)..=requests.bottom()).contains(&y)
+        && (processing.left()..processing.right()).contains(&x);
+
+    DRAGGING_MAIN_SPLIT.store(on_main_border, Ordering::SeqCst);
+    DRAGGING_REQUESTS_SPLIT.store(on_requests_border && !on_main_border, Ordering::SeqCst);
+    on_main_border || on_requests_border
+}
+
+/// Moves the border being dragged to the pointer, within the splits' limits.
+fn drag_split((x, y): (u16, u16)) {
+    let processing = PROCESSING_AREA.rect();
+    if processing.is_empty() {
+        return;
+    }
+
+    if DRAGGING_MAIN_SPLIT.load(Ordering::SeqCst) {
+        // The overview spans the whole width, of which the processing area
+        // takes the current split.
+        let width = processing.width as u32 * 100 / MAIN_SPLIT.load(Ordering::SeqCst) as u32;
+        let split = (x.saturating_sub(processing.x) as u32 * 100 / width.max(1)) as u16;
+        MAIN_SPLIT.store(
+            split.clamp(*MAIN_SPLIT_RANGE.start(), *MAIN_SPLIT_RANGE.end()),
+            Ordering::SeqCst,
+        );
+    } else if DRAGGING_REQUESTS_SPLIT.load(Ordering::SeqCst) {
+        let split =
+            (y.saturating_sub(processing.y) as u32 * 100 / processing.height.max(1) as u32) as u16;
+        REQUESTS_SPLIT.store(
+            split.clamp(*REQUESTS_SPLIT_RANGE.start(), *REQUESTS_SPLIT_RANGE.end()),
+            Ordering::SeqCst,
+        );
+    }
+}
+
+/// Blocks until a key is pressed.
+fn wait_for_key() -> io::Result<()> {
+    loop {
+        if let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            return Ok(());
+        }
     }
 }
 
 fn handle_events(event_tx: &Sender<SystemEvent>, state: &SystemState) -> io::Result<bool> {
     if event::poll(Duration::from_millis(100))? {
         match event::read()? {
+            Event::Key(key)
+                if key.kind == KeyEventKind::Press && CONFIRM_QUIT.load(Ordering::SeqCst) =>
+            {
+                match key.code {
+                    KeyCode::Char('y' | 'q') | KeyCode::Enter => return Ok(true),
+                    KeyCode::Char('n') | KeyCode::Esc => {
+                        CONFIRM_QUIT.store(false, Ordering::SeqCst);
+                    }
+                    _ => {}
+                }
+            }
+            Event::Key(key)
+                if key.kind == KeyEventKind::Press
+                    && key.modifiers.is_empty()
+                    && SELECTED_TAB.load(Ordering::SeqCst) == CONFIG_TAB
+                    && edit_form(event_tx, state, key.code) => {}
             Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
-                KeyCode::Char('q') => return Ok(true),
-                KeyCode::Left => {
-                    let new_mode = match state.configs.choice_mode {
-                        ServerChoiceMode::Random => ServerChoiceMode::RoundRobin { server_num: 0 },
-                        ServerChoiceMode::RoundRobin { .. } => ServerChoiceMode::SmallerQueue,
-                        ServerChoiceMode::SmallerQueue => ServerChoiceMode::Random,
+                KeyCode::Char('q') => {
+                    CONFIRM_QUIT.store(true, Ordering::SeqCst);
+                }
+                KeyCode::Char('?') => {
+                    SHOW_HELP.fetch_xor(true, Ordering::SeqCst);
+                }
+                KeyCode::Esc => {
+                    SHOW_HELP.store(false, Ordering::SeqCst);
+                    SHOW_SERVER_DETAIL.store(false, Ordering::SeqCst);
+                }
+                KeyCode::Enter => {
+                    SHOW_SERVER_DETAIL.fetch_xor(true, Ordering::SeqCst);
+                }
+                KeyCode::End => {
+                    FOLLOW_LOGS.store(true, Ordering::SeqCst);
+                }
+                KeyCode::Char('S') => {
+                    SAVE_SNAPSHOT.store(true, Ordering::SeqCst);
+                }
+                KeyCode::Char('F') => {
+                    // Stops on the newest line; ignored when following again.
+                    if let Some(newest) = state.logs().last() {
+                        SELECTED_LOG.store(newest.number(), Ordering::SeqCst);
+                    }
+                    FOLLOW_LOGS.fetch_xor(true, Ordering::SeqCst);
+                }
+                KeyCode::PageUp => {
+                    let page = REQUESTS_PAGE.load(Ordering::SeqCst);
+                    REQUESTS_PAGE.store(page.saturating_sub(1), Ordering::SeqCst);
+                }
+                KeyCode::PageDown => {
+                    // Kept within the pages there are when rendering.
+                    REQUESTS_PAGE.fetch_add(1, Ordering::SeqCst);
+                }
+                KeyCode::Tab => {
+                    let selected = SELECTED_TAB.load(Ordering::SeqCst);
+                    SELECTED_TAB.store((selected + 1) % TABS.len(), Ordering::SeqCst);
+                }
+                KeyCode::BackTab => {
+                    let selected = SELECTED_TAB.load(Ordering::SeqCst);
+                    SELECTED_TAB.store((selected + TABS.len() - 1) % TABS.len(), Ordering::SeqCst);
+                }
+                KeyCode::F(number @ 1..=5) => {
+                    SELECTED_TAB.store(number as usize - 1, Ordering::SeqCst);
+                }
+                KeyCode::Char(digit @ '1'..='9') => {
+                    let idx = digit as usize - '1' as usize;
+                    if let Some(mode) = ServerChoiceMode::nth(idx) {
+                        event_tx
+                            .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+                                choice_mode: Some(mode),
+                                ..Default::default()
+                            }))
+                            .ok();
+                    }
+                }
+                KeyCode::Char('z') | KeyCode::Pause => {
+                    event_tx
+                        .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+                            paused: Some(!state.configs().paused),
+                            ..Default::default()
+                        }))
+                        .ok();
+                }
+                KeyCode::Char(key @ ('<' | '>' | ',' | '.')) => {
+                    let faster = matches!(key, '>' | '.');
+                    if let Some(speed) = clock::step_speed(clock::speed(), faster) {
+                        event_tx
+                            .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+                                speed: Some(speed),
+                                ..Default::default()
+                            }))
+                            .ok();
+                    }
+                }
+                KeyCode::Char('y') => {
+                    theme::next_theme();
+                }
+                KeyCode::Char('a') => {
+                    event_tx
+                        .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+                            admission_control: Some(!state.configs().admission_control),
+                            ..Default::default()
+                        }))
+                        .ok();
+                }
+                KeyCode::Insert if state.servers().len() < MAX_SERVERS => {
+                    event_tx
+                        .try_send(SystemEvent::ServerAdded {
+                            server_id: server::next_server_id(),
+                        })
+                        .ok();
+                }
+                KeyCode::Delete if state.servers().len() > 1 => {
+                    if let Some(server) = state.servers().last() {
+                        event_tx
+                            .try_send(SystemEvent::ServerRemoved {
+                                server_id: server.id,
+                            })
+                            .ok();
+                    }
+                }
+                KeyCode::Char('s') => {
+                    let selected = SELECTED_SERVER.load(Ordering::SeqCst);
+                    SELECTED_SERVER.store((selected + 1) % state.servers().len(), Ordering::SeqCst);
+                }
+                KeyCode::Char('d') => {
+                    if let Some(server) = selected_server(state) {
+                        let status = match server.status {
+                            ServerStatus::Active => ServerStatus::Draining,
+                            ServerStatus::Draining | ServerStatus::Drained => ServerStatus::Active,
+                        };
+
+                        event_tx
+                            .try_send(SystemEvent::ServerStatusChanged {
+                                server_id: server.id,
+                                status,
+                            })
+                            .ok();
+                    }
+                }
+                KeyCode::Char('o') => {
+                    if let Some(server) = selected_server(state) {
+                        event_tx
+                            .try_send(SystemEvent::QueueDisciplineChanged {
+                                server_id: server.id,
+                                discipline: server.discipline.next(),
+                            })
+                            .ok();
+                    }
+                }
+                KeyCode::Char(' ') => {
+                    if let Some(server) = selected_server(state) {
+                        toggle_pause(event_tx, server);
+                    }
+                }
+                KeyCode::Char('r') => {
+                    if let Some(server) = selected_server(state) {
+                        event_tx
+                            .try_send(SystemEvent::RateLimitChanged {
+                                server_id: server.id,
+                                limit: server.next_rate_limit(),
+                            })
+                            .ok();
+                    }
+                }
+                KeyCode::Char('u') => {
+                    if let Some(server) = selected_server(state) {
+                        event_tx
+                            .try_send(SystemEvent::FailureRateChanged {
+                                server_id: server.id,
+                                rate: server.next_failure_rate(),
+                            })
+                            .ok();
+                    }
+                }
+                KeyCode::Char('W') => {
+                    if let Some(server) = selected_server(state) {
+                        event_tx
+                            .try_send(SystemEvent::WorkersChanged {
+                                server_id: server.id,
+                                workers: server.next_workers(),
+                            })
+                            .ok();
+                    }
+                }
+                KeyCode::Char(key @ ('k' | 'K')) => {
+                    if let Some(server) = selected_server(state) {
+                        event_tx
+                            .try_send(SystemEvent::ServerCrashed {
+                                server_id: server.id,
+                                drop_requests: key == 'K',
+                            })
+                            .ok();
+                    }
+                }
+                KeyCode::Char('x') => {
+                    event_tx
+                        .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+                            autoscaling: Some(!state.configs().autoscaling),
+                            ..Default::default()
+                        }))
+                        .ok();
+                }
+                KeyCode::Char('c') => {
+                    event_tx
+                        .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+                            contention: Some(!state.configs().contention),
+                            ..Default::default()
+                        }))
+                        .ok();
+                }
+                KeyCode::Char('g') => {
+                    event_tx
+                        .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+                            stalls: Some(!state.configs().stalls),
+                            ..Default::default()
+                        }))
+                        .ok();
+                }
+                KeyCode::Char('b') => {
+                    event_tx
+                        .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+                            bursts: Some(!state.configs().bursts),
+                            ..Default::default()
+                        }))
+                        .ok();
+                }
+                KeyCode::Char('v') => {
+                    event_tx
+                        .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+                            fair_queueing: Some(!state.configs().fair_queueing),
+                            ..Default::default()
+                        }))
+                        .ok();
+                }
+                KeyCode::Char('m') => {
+                    event_tx
+                        .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+                            caching: Some(!state.configs().caching),
+                            ..Default::default()
+                        }))
+                        .ok();
+                }
+                KeyCode::Char('j') => {
+                    event_tx
+                        .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+                            deduplication: Some(!state.configs().deduplication),
+                            ..Default::default()
+                        }))
+                        .ok();
+                }
+                KeyCode::Char('h') => {
+                    event_tx
+                        .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+                            dependencies: Some(!state.configs().dependencies),
+                            ..Default::default()
+                        }))
+                        .ok();
+                }
+                KeyCode::Char('n') => {
+                    event_tx
+                        .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+                            network: Some(!state.configs().network),
+                            ..Default::default()
+                        }))
+                        .ok();
+                }
+                KeyCode::Char('i') => {
+                    event_tx
+                        .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+                            pipelines: Some(!state.configs().pipelines),
+                            ..Default::default()
+                        }))
+                        .ok();
+                }
+                KeyCode::Char('f') => {
+                    event_tx
+                        .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+                            forks: Some(!state.configs().forks),
+                            ..Default::default()
+                        }))
+                        .ok();
+                }
+                KeyCode::Char('w') => {
+                    event_tx
+                        .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+                            batching: Some(!state.configs().batching),
+                            ..Default::default()
+                        }))
+                        .ok();
+                }
+                KeyCode::Char('e') => {
+                    event_tx
+                        .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+                            sessions: Some(!state.configs().sessions),
+                            ..Default::default()
+                        }))
+                        .ok();
+                }
+                KeyCode::Char('t') => {
+                    event_tx
+                        .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+                            retries: Some(!state.configs().retries),
+                            ..Default::default()
+                        }))
+                        .ok();
+                }
+                KeyCode::Char('p') => {
+                    event_tx
+                        .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+                            preemption: Some(!state.configs().preemption),
+                            ..Default::default()
+                        }))
+                        .ok();
+                }
+                KeyCode::Char('l') => {
+                    event_tx
+                        .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
+                            anti_affinity: Some(!state.configs().anti_affinity),
+                            ..Default::default()
+                        }))
+                        .ok();
+                }
+                KeyCode::Left | KeyCode::Right if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                    let split = MAIN_SPLIT.load(Ordering::SeqCst);
+                    let split = if key.code == KeyCode::Left {
+                        split.saturating_sub(SPLIT_STEP)
+                    } else {
+                        split + SPLIT_STEP
                     };
+                    MAIN_SPLIT.store(
+                        split.clamp(*MAIN_SPLIT_RANGE.start(), *MAIN_SPLIT_RANGE.end()),
+                        Ordering::SeqCst,
+                    );
+                }
+                KeyCode::Up | KeyCode::Down if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                    let split = REQUESTS_SPLIT.load(Ordering::SeqCst);
+                    let split = if key.code == KeyCode::Up {
+                        split.saturating_sub(SPLIT_STEP)
+                    } else {
+                        split + SPLIT_STEP
+                    };
+                    REQUESTS_SPLIT.store(
+                        split.clamp(*REQUESTS_SPLIT_RANGE.start(), *REQUESTS_SPLIT_RANGE.end()),
+                        Ordering::SeqCst,
+                    );
+                }
+                KeyCode::Left => {
+                    let new_mode = state.configs().choice_mode.next();
 
                     event_tx
-                        .try_send(SystemEvent::ConfigChanged {
-                            arrival_rate: None,
+                        .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
                             choice_mode: Some(new_mode),
-                        })
+                            ..Default::default()
+                        }))
                         .ok();
                 }
                 KeyCode::Right => {
-                    let new_mode = match state.configs.choice_mode {
-                        ServerChoiceMode::Random => ServerChoiceMode::SmallerQueue,
-                        ServerChoiceMode::RoundRobin { .. } => ServerChoiceMode::Random,
-                        ServerChoiceMode::SmallerQueue => {
-                            ServerChoiceMode::RoundRobin { server_num: 0 }
-                        }
-                    };
+                    let new_mode = state.configs().choice_mode.prev();
 
                     event_tx
-                        .try_send(SystemEvent::ConfigChanged {
-                            arrival_rate: None,
+                        .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
                             choice_mode: Some(new_mode),
-                        })
+                            ..Default::default()
+                        }))
                         .ok();
                 }
-                KeyCode::Up => {
-                    let new_rate = (state.configs.arrival_rate + 0.5).min(10.0);
+                KeyCode::Up | KeyCode::Char('+') | KeyCode::Char('=') => {
+                    let new_rate = (state.configs().arrival_rate + 0.5).min(10.0);
 
                     event_tx
-                        .try_send(SystemEvent::ConfigChanged {
+                        .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
                             arrival_rate: Some(new_rate),
-                            choice_mode: None,
-                        })
+                            ..Default::default()
+                        }))
                         .ok();
                 }
-                KeyCode::Down => {
-                    let new_rate = (state.configs.arrival_rate - 0.5).max(0.0);
+                KeyCode::Down | KeyCode::Char('-') => {
+                    let new_rate = (state.configs().arrival_rate - 0.5).max(0.0);
 
                     event_tx
-                        .try_send(SystemEvent::ConfigChanged {
+                        .try_send(SystemEvent::ConfigChanged(ConfigUpdate {
                             arrival_rate: Some(new_rate),
-                            choice_mode: None,
-                        })
+                            ..Default::default()
+                        }))
                         .ok();
                 }
                 _ => {}
@@ -528,8 +3015,10 @@ fn handle_events(event_tx: &Sender<SystemEvent>, state: &SystemState) -> io::Res
 
                         let mut hit_server = None;
                         {
-                            for idx in 0..3 {
-                                if SERVER_AREAS[idx].contains(position.0, position.1) {
+                            for (idx, area) in
+                                SERVER_AREAS.iter().take(state.servers().len()).enumerate()
+                            {
+                                if area.contains(position.0, position.1) {
                                     hit_server = Some(idx);
                                     break;
                                 }
@@ -545,15 +3034,58 @@ fn handle_events(event_tx: &Sender<SystemEvent>, state: &SystemState) -> io::Res
                                         .store(current.saturating_sub(1), Ordering::SeqCst);
                                 }
                             } else {
-                                let current = SELECTED_LOG.load(Ordering::SeqCst);
-                                if is_scrolling_up {
-                                    SELECTED_LOG.store(current.saturating_add(1), Ordering::SeqCst);
-                                } else {
-                                    SELECTED_LOG.store(current.saturating_sub(1), Ordering::SeqCst);
-                                }
+                                scroll_logs(state.logs(), is_scrolling_up);
                             }
                         }
                     }
+                    crossterm::event::MouseEventKind::Down(crossterm::event::MouseButton::Left)
+                        if start_drag(position) => {}
+                    crossterm::event::MouseEventKind::Drag(crossterm::event::MouseButton::Left) => {
+                        drag_split(position)
+                    }
+                    crossterm::event::MouseEventKind::Up(crossterm::event::MouseButton::Left) => {
+                        DRAGGING_MAIN_SPLIT.store(false, Ordering::SeqCst);
+                        DRAGGING_REQUESTS_SPLIT.store(false, Ordering::SeqCst);
+                    }
+                    crossterm::event::MouseEventKind::Down(crossterm::event::MouseButton::Left) => {
+                        let request_id = REQUEST_CELLS
+                            .lock()
+                            .unwrap()
+                            .iter()
+                            .find(|(cell, _)| cell.contains(layout::Position::from(position)))
+                            .map(|(_, id)| *id);
+
+                        if TABS_AREA.contains(position.0, position.1) {
+                            if let Some(tab) = tab_at(position.0) {
+                                SELECTED_TAB.store(tab, Ordering::SeqCst);
+                            }
+                        } else if let Some(idx) = SERVER_AREAS
+                            .iter()
+                            .take(state.servers().len())
+                            .position(|area| area.contains(position.0, position.1))
+                        {
+                            SELECTED_SERVER.store(idx, Ordering::SeqCst);
+                            SHOW_SERVER_DETAIL.store(true, Ordering::SeqCst);
+                        } else if let Some(id) = request_id {
+                            let mut selected = SELECTED_REQUEST.lock().unwrap();
+                            // Clicking it again clears the selection.
+                            *selected = (*selected != Some(id)).then_some(id);
+                        } else if LOG_AREA.contains(position.0, position.1) {
+                            click_log(state.logs(), position.1);
+                        }
+                    }
+                    crossterm::event::MouseEventKind::Down(
+                        crossterm::event::MouseButton::Right,
+                    ) => {
+                        if let Some(server) = SERVER_AREAS
+                            .iter()
+                            .zip(state.servers())
+                            .find(|(area, _)| area.contains(position.0, position.1))
+                            .map(|(_, server)| server)
+                        {
+                            toggle_pause(event_tx, server);
+                        }
+                    }
                     _ => {}
                 }
             }
@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Instant;
+
+use rand::Rng;
+use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+
+use crate::clock::{self, Ticker};
+use crate::context;
+use crate::server::{self, ServerState, find_server};
+use crate::{HEALTH_RNG, SystemEvent, task_rng};
+
+const PROBE_INTERVAL: Duration = Duration::from_secs(1);
+/// A probe times out when the server has more outstanding work (ms) than this.
+const PROBE_TIMEOUT: u64 = 4000;
+/// Chance of a probe failing on its own, like a flaky network would.
+const PROBE_FLAKINESS: f64 = 0.02;
+const FAILURE_THRESHOLD: u32 = 3;
+const SUCCESS_THRESHOLD: u32 = 2;
+const OPEN_DURATION: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    Closed,
+    Open,
+    /// Lets a trial request through to see whether the server recovered.
+    HalfOpen,
+}
+
+impl fmt::Display for BreakerState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Closed => write!(f, "Closed"),
+            Self::Open => write!(f, "Open"),
+            Self::HalfOpen => write!(f, "Half-Open"),
+        }
+    }
+}
+
+struct CircuitBreaker {
+    state: BreakerState,
+    failures: u32,
+    successes: u32,
+    opened_at: Instant,
+}
+
+impl CircuitBreaker {
+    fn new() -> Self {
+        Self {
+            state: BreakerState::Closed,
+            failures: 0,
+            successes: 0,
+            opened_at: clock::now(),
+        }
+    }
+
+    fn open(&mut self) {
+        self.state = BreakerState::Open;
+        self.opened_at = clock::now();
+        self.failures = 0;
+        self.successes = 0;
+    }
+
+    fn record(&mut self, healthy: bool) {
+        match self.state {
+            BreakerState::Closed if healthy => self.failures = 0,
+            BreakerState::Closed => {
+                self.failures += 1;
+                if self.failures >= FAILURE_THRESHOLD {
+                    self.open();
+                }
+            }
+            BreakerState::Open => {
+                if clock::since(self.opened_at) >= OPEN_DURATION {
+                    self.state = BreakerState::HalfOpen;
+                }
+            }
+            BreakerState::HalfOpen if healthy => {
+                self.successes += 1;
+                if self.successes >= SUCCESS_THRESHOLD {
+                    self.state = BreakerState::Closed;
+                    self.successes = 0;
+                }
+            }
+            BreakerState::HalfOpen => self.open(),
+        }
+    }
+}
+
+pub fn spawn_health_checker(
+    event_tx: Sender<SystemEvent>,
+    mut event_rx: Receiver<SystemEvent>,
+    seed: u64,
+) -> JoinHandle<()> {
+    context::spawn(async move {
+        let mut servers = server::initial_servers();
+        let mut breakers: HashMap<u64, CircuitBreaker> = servers
+            .iter()
+            .map(|server| (server.id, CircuitBreaker::new()))
+            .collect();
+
+        let mut rng = task_rng(seed, HEALTH_RNG);
+        let mut ticker = Ticker::new(PROBE_INTERVAL);
+        let mut paused = false;
+
+        loop {
+            while let Ok(event) = event_rx.try_recv() {
+                match event {
+                    SystemEvent::RequestAssigned { server_id, request } => {
+                        if let Some(server) = find_server(&mut servers, server_id) {
+                            server.add_request(request);
+                        }
+                    }
+                    SystemEvent::RequestProcessStarted {
+                        request_id,
+                        server_id,
+                        service_time,
+                        cache_hit,
+                    } => {
+                        if let Some(server) = find_server(&mut servers, server_id) {
+                            server.start_request(request_id, service_time, cache_hit);
+                        }
+                    }
+                    SystemEvent::RequestProcessed {
+                        request_id,
+                        server_id,
+                        ..
+                    } => {
+                        if let Some(server) = find_server(&mut servers, server_id) {
+                            server.finish_request(request_id);
+                        }
+                    }
+                    SystemEvent::RequestExpired {
+                        request,
+                        server_id: Some(server_id),
+                    } => {
+                        if let Some(server) = find_server(&mut servers, server_id) {
+                            server.remove_queued(request.id);
+                        }
+                    }
+                    SystemEvent::RequestPreempted {
+                        request_id,
+                        server_id,
+                    }
+                    | SystemEvent::RequestParked {
+                        request_id,
+                        server_id,
+                    } => {
+                        if let Some(server) = find_server(&mut servers, server_id) {
+                            server.preempt(request_id);
+                        }
+                    }
+                    SystemEvent::ServerPaused { server_id, paused } => {
+                        if let Some(server) = find_server(&mut servers, server_id) {
+                            server.set_paused(paused);
+                        }
+                    }
+                    SystemEvent::ServerCrashed { server_id, .. } => {
+                        if let Some(server) = find_server(&mut servers, server_id) {
+                            server.crash();
+                        }
+                    }
+                    SystemEvent::ServerStalled {
+                        server_id,
+                        duration,
+                    } => {
+                        if let Some(server) = find_server(&mut servers, server_id) {
+                            server.stall(duration);
+                        }
+                    }
+                    SystemEvent::ServerAdded { server_id } => {
+                        servers.push(ServerState::added(server_id));
+                        breakers.insert(server_id, CircuitBreaker::new());
+                    }
+                    SystemEvent::ServerRemoved { server_id } => {
+                        servers.retain(|server| server.id != server_id);
+                        breakers.remove(&server_id);
+                    }
+                    SystemEvent::ConfigChanged(update) => {
+                        if let Some(value) = update.paused {
+                            paused = value;
+                        }
+                        if let Some(seed) = update.seed {
+                            rng = task_rng(seed, HEALTH_RNG);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            if paused {
+                ticker.tick().await;
+                continue;
+            }
+
+            for server in &servers {
+                let Some(breaker) = breakers.get_mut(&server.id) else {
+                    continue;
+                };
+
+                let healthy = server.remaining_workload() < PROBE_TIMEOUT
+                    && !rng.random_bool(PROBE_FLAKINESS);
+
+                let previous = breaker.state;
+                breaker.record(healthy);
+
+                if breaker.state != previous {
+                    event_tx
+                        .send(SystemEvent::BreakerChanged {
+                            server_id: server.id,
+                            state: breaker.state,
+                        })
+                        .await
+                        .ok();
+                }
+            }
+
+            ticker.tick().await;
+        }
+    })
+}
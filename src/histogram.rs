@@ -0,0 +1,101 @@
+use std::fs;
+
+/// Bits of precision kept within each power of two: values are counted in
+/// buckets at most 1/16th (6.25%) as wide as the values themselves.
+const SUB_BUCKET_BITS: u32 = 4;
+const SUB_BUCKETS: usize = 1 << SUB_BUCKET_BITS;
+
+/// Latencies (ms) counted in log-linear buckets like an HdrHistogram, which
+/// bounds the relative error of percentiles while using little memory.
+#[derive(Default)]
+pub struct LatencyHistogram {
+    counts: Vec<u64>,
+    total: u64,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency: u64) {
+        let idx = bucket_index(latency);
+        if idx >= self.counts.len() {
+            self.counts.resize(idx + 1, 0);
+        }
+
+        self.counts[idx] += 1;
+        self.total += 1;
+    }
+
+    /// Latency under which the given share of the recorded ones fell, to
+    /// within the bucket precision.
+    pub fn percentile(&self, share: f64) -> Option<u64> {
+        let rank = ((self.total as f64 * share).ceil() as u64).max(1);
+
+        let mut seen = 0;
+        for (idx, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(bucket_range(idx).1);
+            }
+        }
+        None
+    }
+
+    /// Recorded latencies per power of two, as the lowest value of each
+    /// range and the count; values below `2^SUB_BUCKET_BITS` form the first.
+    pub fn octaves(&self) -> Vec<(u64, u64)> {
+        self.counts
+            .chunks(SUB_BUCKETS)
+            .enumerate()
+            .map(|(octave, counts)| (bucket_range(octave * SUB_BUCKETS).0, counts.iter().sum()))
+            .collect()
+    }
+
+    /// Every non-empty bucket, as the lowest and highest value it counts and
+    /// the count.
+    pub fn buckets(&self) -> impl Iterator<Item = (u64, u64, u64)> + '_ {
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(idx, count)| {
+                let (lower, upper) = bucket_range(idx);
+                (lower, upper, *count)
+            })
+    }
+
+    /// Writes the count of every non-empty bucket with its latency range.
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let mut lines = vec!["# lower_ms,upper_ms,count".to_string()];
+        for (lower, upper, count) in self.buckets() {
+            lines.push(format!("{lower},{upper},{count}"));
+        }
+
+        fs::write(path, lines.join("\n") + "\n").map_err(|e| format!("cannot write {path}: {e}"))
+    }
+}
+
+/// Values below `SUB_BUCKETS` get a bucket each; above, every power of two is
+/// split into `SUB_BUCKETS` equal buckets.
+fn bucket_index(value: u64) -> usize {
+    if value < SUB_BUCKETS as u64 {
+        return value as usize;
+    }
+
+    let magnitude = 63 - value.leading_zeros();
+    let shift = magnitude - SUB_BUCKET_BITS;
+    let mantissa = (value >> shift) as usize;
+
+    (shift as usize + 1) * SUB_BUCKETS + mantissa - SUB_BUCKETS
+}
+
+/// Lowest and highest value counted in the bucket.
+fn bucket_range(idx: usize) -> (u64, u64) {
+    if idx < SUB_BUCKETS {
+        return (idx as u64, idx as u64);
+    }
+
+    let shift = (idx / SUB_BUCKETS - 1) as u32;
+    let mantissa = (idx % SUB_BUCKETS + SUB_BUCKETS) as u64;
+    let lower = mantissa << shift;
+
+    (lower, lower + (1 << shift) - 1)
+}
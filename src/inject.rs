@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::Deserialize;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::Sender;
+use tokio::task::JoinHandle;
+
+use crate::context;
+use crate::request::{Request, RequestSize, RequestType};
+
+/// A request sent by an external client, one JSON object per line, e.g.
+/// `{"type": "CPUsBound", "size": "Large", "priority": "High", "key": 12}`.
+/// Attributes left out are drawn like those of generated requests.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InjectedRequest {
+    #[serde(rename = "type")]
+    kind: String,
+    size: String,
+    /// Service time (ms) instead of the fixed one of the type and size.
+    time_ms: Option<u64>,
+    priority: Option<String>,
+    key: Option<u32>,
+    zone: Option<char>,
+    tag: Option<String>,
+    /// Deadline (ms) after arrival.
+    deadline_ms: Option<u64>,
+}
+
+impl InjectedRequest {
+    fn to_request(&self, tags: &mut HashMap<String, &'static str>) -> Result<Request, String> {
+        let kind: RequestType = self.kind.parse()?;
+        let size: RequestSize = self.size.parse()?;
+        let mut request = Request::create(kind, size, self.time_ms);
+
+        if let Some(priority) = &self.priority {
+            request.priority = priority.parse()?;
+        }
+        if let Some(key) = self.key {
+            request.key = Some(key);
+        }
+        if let Some(zone) = self.zone {
+            request.zone = Some(zone);
+        }
+        if let Some(tag) = &self.tag {
+            // Each distinct tag is leaked once, like those of a tag mix.
+            let tag = *tags
+                .entry(tag.clone())
+                .or_insert_with(|| Box::leak(tag.clone().into_boxed_str()));
+            request.tag = Some(tag);
+        }
+        if let Some(deadline) = self.deadline_ms {
+            request.deadline = Some(request.created_at + Duration::from_millis(deadline));
+        }
+
+        Ok(request)
+    }
+}
+
+/// Accepts clients on the listener and passes the requests they send on to
+/// the generator. Each line gets a reply, `ok <id>` or `error: <reason>`.
+pub fn spawn_injector(listener: TcpListener, request_tx: Sender<Request>) -> JoinHandle<()> {
+    context::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            context::spawn(serve_client(stream, request_tx.clone()));
+        }
+    })
+}
+
+async fn serve_client(stream: TcpStream, request_tx: Sender<Request>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    let mut tags = HashMap::new();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let reply = match serde_json::from_str::<InjectedRequest>(line)
+            .map_err(|e| e.to_string())
+            .and_then(|injected| injected.to_request(&mut tags))
+        {
+            Ok(request) => {
+                // Waits while the pending list is full.
+                if request_tx.send(request).await.is_err() {
+                    break;
+                }
+                format!("ok {}\n", request.id)
+            }
+            Err(e) => format!("error: {e}\n"),
+        };
+
+        if writer.write_all(reply.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
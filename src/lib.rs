@@ -0,0 +1,2416 @@
+//! Simulation engine of the load balancer: a request generator, an
+//! allocator that balances the requests over the servers, the servers
+//! themselves, an autoscaler and a health checker, each a task talking to
+//! the others through an event router. [`Simulation`] starts them and hands
+//! the events out, which [`SystemState::apply`] folds into the state and
+//! statistics a frontend, such as the terminal UI, draws from. Each
+//! simulation has its own [`Context`](context::Context), with its clock,
+//! window, queue capacity and server ids, so several can run in the same
+//! process.
+
+pub mod alert;
+pub mod autoscaler;
+pub mod clock;
+pub mod context;
+pub mod health;
+pub mod histogram;
+mod inject;
+pub mod metrics;
+pub mod otlp;
+pub mod request;
+pub mod schedule;
+pub mod server;
+pub mod slo;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod state;
+pub mod statsd;
+pub mod strategy;
+pub mod summary;
+pub mod tenant;
+pub mod timeline;
+pub mod timeseries;
+pub mod trace;
+pub mod window;
+
+use rand::rngs::StdRng;
+use rand::seq::IteratorRandom;
+use rand::{Rng, SeedableRng};
+use request::{
+    PriorityMix, Request, RequestSize, RequestType, RetryPolicy, ServiceTimeDistribution, Session,
+    TagMix,
+};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+
+use crate::alert::{AlertRule, Alerts};
+use crate::autoscaler::{ScalingPolicy, spawn_autoscaler};
+use crate::clock::Ticker;
+use crate::context::Context;
+use crate::health::{BreakerState, spawn_health_checker};
+use crate::histogram::LatencyHistogram;
+use crate::inject::spawn_injector;
+use crate::metrics::MetricsCsv;
+use crate::otlp::OtlpExporter;
+use crate::schedule::{BurstPattern, RateSchedule};
+use crate::server::{QueueDiscipline, ServerState, ServerStatus, find_server};
+use crate::slo::SloTracker;
+#[cfg(feature = "sqlite")]
+use crate::sqlite::SqliteSink;
+use crate::state::{LogLine, THROUGHPUT_HISTORY};
+use crate::statsd::StatsdSink;
+use crate::strategy::{REGISTRY, ServerChoiceMode, Strategy, StrategyConfig, StrategyEntry};
+use crate::tenant::{FairQueue, TenantTracker};
+use crate::timeline::Timeline;
+use crate::timeseries::QueueHistory;
+use crate::trace::{TraceEntry, TraceRecorder};
+use crate::window::SlidingWindow;
+
+const INITIAL_AVG_RATE: i32 = 3; // requests/second
+pub const PENDING_REQUESTS_LIMIT: i32 = 20;
+/// Aggregate server queue occupancy above which admission control rejects
+/// new requests.
+const ADMISSION_THRESHOLD: f64 = 0.8;
+/// Number of latest requests the generator picks dependencies from.
+const RECENT_REQUESTS: usize = 10;
+/// How long the allocator accumulates pending requests in batching mode.
+const BATCH_WINDOW: Duration = Duration::from_millis(200);
+/// Streams of the seed the tasks draw from, so they don't share draws.
+const GENERATOR_RNG: u64 = 0;
+const ALLOCATOR_RNG: u64 = 1;
+const SERVERS_RNG: u64 = 2;
+pub(crate) const HEALTH_RNG: u64 = 3;
+
+/// Random number generator of a task, seeded from the simulation's seed.
+pub(crate) fn task_rng(seed: u64, stream: u64) -> StdRng {
+    StdRng::seed_from_u64(seed.wrapping_add(stream))
+}
+
+/// Where the generator's requests come from and how it paces them.
+struct Workload {
+    retry_policy: RetryPolicy,
+    burst: BurstPattern,
+    schedule: Option<RateSchedule>,
+    /// Replayed instead of generating requests when given.
+    trace: Option<Vec<TraceEntry>>,
+    recorder: Option<Arc<TraceRecorder>>,
+    service_times: ServiceTimeDistribution,
+    tags: TagMix,
+    /// Requests sent by external clients, created along with the generated ones.
+    injected: Option<Receiver<Request>>,
+}
+
+/// Chance of a server stalling on each 10ms servers tick when stalls are on.
+const STALL_CHANCE: f64 = 0.0005;
+/// Range of stall durations (ms).
+const STALL_DURATION: std::ops::RangeInclusive<u64> = 500..=3000;
+
+/// Current configuration, as the `ConfigChanged` events left it.
+pub struct SystemConfig {
+    pub arrival_rate: f32,
+    pub choice_mode: ServerChoiceMode,
+    pub delegate_mode: Option<ServerChoiceMode>,
+    pub admission_control: bool,
+    pub anti_affinity: bool,
+    pub autoscaling: bool,
+    pub contention: bool,
+    pub stalls: bool,
+    pub preemption: bool,
+    pub retries: bool,
+    pub bursts: bool,
+    pub sessions: bool,
+    pub batching: bool,
+    pub forks: bool,
+    pub pipelines: bool,
+    pub network: bool,
+    pub dependencies: bool,
+    pub deduplication: bool,
+    pub caching: bool,
+    pub fair_queueing: bool,
+    /// Whole simulation frozen: no arrivals, assignments or processing.
+    pub paused: bool,
+    /// Seed the tasks' random draws last started from.
+    pub seed: u64,
+}
+
+/// Configuration fields that changed; `None` leaves the current value alone.
+#[derive(Clone, Default)]
+pub struct ConfigUpdate {
+    pub arrival_rate: Option<f32>,
+    pub choice_mode: Option<ServerChoiceMode>,
+    pub delegate_mode: Option<ServerChoiceMode>,
+    pub admission_control: Option<bool>,
+    pub anti_affinity: Option<bool>,
+    pub autoscaling: Option<bool>,
+    pub contention: Option<bool>,
+    pub stalls: Option<bool>,
+    pub preemption: Option<bool>,
+    pub retries: Option<bool>,
+    pub bursts: Option<bool>,
+    pub sessions: Option<bool>,
+    pub batching: Option<bool>,
+    pub forks: Option<bool>,
+    pub pipelines: Option<bool>,
+    pub network: Option<bool>,
+    pub dependencies: Option<bool>,
+    pub deduplication: Option<bool>,
+    pub caching: Option<bool>,
+    pub fair_queueing: Option<bool>,
+    pub paused: Option<bool>,
+    /// Factor simulated time runs at, applied by the router.
+    pub speed: Option<f64>,
+    /// Requests every server's queue holds, applied by the router.
+    pub queue_capacity: Option<usize>,
+    /// Seed every task's random draws start over from.
+    pub seed: Option<u64>,
+}
+
+/// Result of processing a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestOutcome {
+    Succeeded,
+    /// Processed, but the server returned an error.
+    Failed,
+}
+
+/// What happens in the simulation, sent by its tasks to the router, which
+/// passes it on to the ones that act on it and to the frontend.
+#[derive(Clone)]
+pub enum SystemEvent {
+    RequestCreated(Request),
+    RequestAssigned {
+        server_id: u64,
+        request: Request,
+    },
+    RequestProcessStarted {
+        request_id: usize,
+        server_id: u64,
+        service_time: u64,
+        cache_hit: Option<bool>,
+    },
+    RequestProcessed {
+        request_id: usize,
+        server_id: u64,
+        created_at: Instant,
+        /// When the server got the request and started processing it.
+        assigned_at: Option<Instant>,
+        started_at: Option<Instant>,
+        outcome: RequestOutcome,
+    },
+    RequestRejected {
+        request: Request,
+    },
+    /// Dropped past its deadline, from a server queue or from the pending
+    /// list when `server_id` is `None`.
+    RequestExpired {
+        request: Request,
+        server_id: Option<u64>,
+    },
+    /// Paused in favour of a much shorter request; it resumes later.
+    RequestPreempted {
+        request_id: usize,
+        server_id: u64,
+    },
+    /// Set aside with its progress while the whole simulation is paused,
+    /// like a preempted request but for no fault of its own; it resumes once
+    /// unpaused.
+    RequestParked {
+        request_id: usize,
+        server_id: u64,
+    },
+    RequestRequeued {
+        server_id: u64,
+        request: Request,
+    },
+    /// Finished a pipeline stage on the server; the request waits for the
+    /// allocator again at its next stage.
+    RequestForwarded {
+        server_id: u64,
+        request: Request,
+    },
+    /// A duplicate held by the allocator until the in-flight request sharing
+    /// its idempotency key completes.
+    RequestCoalesced {
+        request_id: usize,
+        leader_id: usize,
+    },
+    /// A coalesced duplicate completed along with its leader.
+    RequestDeduplicated {
+        request: Request,
+        leader_id: usize,
+        outcome: RequestOutcome,
+    },
+    ServerAdded {
+        server_id: u64,
+    },
+    ServerRemoved {
+        server_id: u64,
+    },
+    ServerStatusChanged {
+        server_id: u64,
+        status: ServerStatus,
+    },
+    BreakerChanged {
+        server_id: u64,
+        state: BreakerState,
+    },
+    ServerStalled {
+        server_id: u64,
+        duration: u64,
+    },
+    ServerPaused {
+        server_id: u64,
+        paused: bool,
+    },
+    /// Loses the server's requests, which the servers task re-queues, or
+    /// fails if `drop_requests`.
+    ServerCrashed {
+        server_id: u64,
+        drop_requests: bool,
+    },
+    QueueDisciplineChanged {
+        server_id: u64,
+        discipline: QueueDiscipline,
+    },
+    RateLimitChanged {
+        server_id: u64,
+        limit: Option<u32>,
+    },
+    FailureRateChanged {
+        server_id: u64,
+        rate: f64,
+    },
+    /// Requests the server processes concurrently; lowering it lets the
+    /// ones in flight finish.
+    WorkersChanged {
+        server_id: u64,
+        workers: usize,
+    },
+    /// Autoscaler decision, logged right before the matching server event.
+    Scaled(String),
+    /// A traffic spike started at this arrival rate, or ended with `None`.
+    BurstChanged {
+        rate: Option<f32>,
+    },
+    ErrorEncountered(String),
+    ConfigChanged(ConfigUpdate),
+    /// An alert rule started firing, or stopped with `firing` false.
+    AlertChanged {
+        rule: AlertRule,
+        firing: bool,
+    },
+}
+
+pub struct SystemState {
+    pending_requests: VecDeque<Request>,
+    servers: Vec<ServerState>,
+    logs: Vec<LogLine>,
+    configs: SystemConfig,
+    stats: SystemStats,
+    /// End of the warm-up, after which the statistics start over.
+    warming_up_until: Option<Instant>,
+    /// Launch, for the time the simulation has been running.
+    launched_at: Instant,
+    /// Launch in real time, which the simulated one drifts from at speeds
+    /// other than 1x.
+    launched_at_real: Instant,
+    /// Rules checked as the simulation runs, which carry on past the
+    /// warm-up.
+    alerts: Alerts,
+    /// Whether a dropped request comes back once retries are on.
+    retry_policy: RetryPolicy,
+    /// Clock and settings of the simulation the state follows.
+    context: Arc<Context>,
+}
+
+impl SystemState {
+    /// Requests waiting to be assigned, highest priority first.
+    pub fn pending_requests(&self) -> &VecDeque<Request> {
+        &self.pending_requests
+    }
+
+    pub fn servers(&self) -> &[ServerState] {
+        &self.servers
+    }
+
+    /// Latest event log lines, oldest first.
+    pub fn logs(&self) -> &[LogLine] {
+        &self.logs
+    }
+
+    pub fn configs(&self) -> &SystemConfig {
+        &self.configs
+    }
+
+    /// Statistics since the launch or the end of the warm-up.
+    pub fn stats(&self) -> &SystemStats {
+        &self.stats
+    }
+
+    /// End of the warm-up, while it's still going.
+    pub fn warming_up_until(&self) -> Option<Instant> {
+        self.warming_up_until
+    }
+
+    pub fn launched_at(&self) -> Instant {
+        self.launched_at
+    }
+
+    pub fn launched_at_real(&self) -> Instant {
+        self.launched_at_real
+    }
+
+    pub fn alerts(&self) -> &Alerts {
+        &self.alerts
+    }
+
+    /// Response times of the processed requests, since the warm-up if any.
+    pub fn latencies(&self) -> &LatencyHistogram {
+        &self.stats.latencies
+    }
+
+    /// Queue lengths and workloads sampled over the whole run.
+    pub fn queue_history(&self) -> &QueueHistory {
+        &self.stats.queue_history
+    }
+
+    /// Phases of every processed request over the whole run.
+    pub fn timeline(&self) -> &Timeline {
+        &self.stats.timeline
+    }
+}
+
+pub struct SystemStats {
+    /// Launch or the end of the warm-up, whichever the statistics start at.
+    started_at: Instant,
+    total_requests: usize,
+    processed_requests: usize,
+    avg_wait_time: f64,
+    /// Response times of the processed requests, for their spread.
+    wait_times: ResponseTimes,
+    throughput: f64,
+    /// Processed requests over the sparkline's span or the window, if longer.
+    throughput_window: SlidingWindow<()>,
+    key_remap_ratio: Option<f64>,
+    rejected_requests: usize,
+    /// Requests dropped past their deadline, while pending or queued.
+    expired_requests: usize,
+    failed_requests: usize,
+    dedup_hits: usize,
+    /// Requests with a deadline that were processed or expired.
+    deadline_requests: usize,
+    /// Of those, the ones that expired or finished late.
+    deadline_misses: usize,
+    /// Server that got the latest request of each session.
+    session_servers: HashMap<u64, u64>,
+    /// Requests of a session that already had one assigned.
+    session_requests: usize,
+    /// Of those, the ones assigned to a different server than the previous one.
+    sticky_violations: usize,
+    /// Time processed requests spent pending, queued on their server and
+    /// being processed, which add up to their response time.
+    pending_times: ResponseTimes,
+    queue_times: ResponseTimes,
+    processing_times: ResponseTimes,
+    /// Response times of requests assigned in a batch and one at a time.
+    batched: ResponseTimes,
+    immediate: ResponseTimes,
+    /// Parts left of each forked request.
+    fork_parts: HashMap<usize, u32>,
+    /// Join latencies of forked requests: the response time of their last part.
+    joins: ResponseTimes,
+    /// End-to-end latencies of pipeline requests, across all their stages.
+    pipelines: ResponseTimes,
+    slo: SloTracker,
+    /// Network transfer times of assigned requests.
+    transfers: ResponseTimes,
+    /// Response times of the processed requests of each tag.
+    tags: BTreeMap<&'static str, ResponseTimes>,
+    tenants: TenantTracker,
+    /// Breakdown of the requests each server handled.
+    servers: HashMap<u64, ServerStats>,
+    /// Response times of the processed requests.
+    latencies: LatencyHistogram,
+    /// Response times of the processed requests of each type and size.
+    classes: HashMap<(RequestType, RequestSize), ClassStats>,
+    /// Slowdowns of the processed requests, overall and of each size.
+    slowdowns: Slowdowns,
+    size_slowdowns: HashMap<RequestSize, Slowdowns>,
+    /// Requests of each type and size assigned to each server.
+    assignments: HashMap<(RequestType, RequestSize, u64), usize>,
+    /// Queue lengths and workloads of the servers over time.
+    queue_history: QueueHistory,
+    /// Phases of every processed request.
+    timeline: Timeline,
+    total_cost: f64,
+}
+
+impl SystemStats {
+    pub fn started_at(&self) -> Instant {
+        self.started_at
+    }
+
+    pub fn total_requests(&self) -> usize {
+        self.total_requests
+    }
+
+    pub fn processed_requests(&self) -> usize {
+        self.processed_requests
+    }
+
+    pub fn wait_times(&self) -> &ResponseTimes {
+        &self.wait_times
+    }
+
+    /// Requests processed per second over the window.
+    pub fn throughput(&self) -> f64 {
+        self.throughput
+    }
+
+    pub fn throughput_window(&self) -> &SlidingWindow<()> {
+        &self.throughput_window
+    }
+
+    /// Share of keys the current mode remaps when a server is removed, if
+    /// it's keyed.
+    pub fn key_remap_ratio(&self) -> Option<f64> {
+        self.key_remap_ratio
+    }
+
+    pub fn rejected_requests(&self) -> usize {
+        self.rejected_requests
+    }
+
+    pub fn expired_requests(&self) -> usize {
+        self.expired_requests
+    }
+
+    pub fn failed_requests(&self) -> usize {
+        self.failed_requests
+    }
+
+    pub fn dedup_hits(&self) -> usize {
+        self.dedup_hits
+    }
+
+    pub fn deadline_requests(&self) -> usize {
+        self.deadline_requests
+    }
+
+    pub fn deadline_misses(&self) -> usize {
+        self.deadline_misses
+    }
+
+    pub fn session_requests(&self) -> usize {
+        self.session_requests
+    }
+
+    pub fn sticky_violations(&self) -> usize {
+        self.sticky_violations
+    }
+
+    pub fn pending_times(&self) -> &ResponseTimes {
+        &self.pending_times
+    }
+
+    pub fn queue_times(&self) -> &ResponseTimes {
+        &self.queue_times
+    }
+
+    pub fn processing_times(&self) -> &ResponseTimes {
+        &self.processing_times
+    }
+
+    pub fn batched(&self) -> &ResponseTimes {
+        &self.batched
+    }
+
+    pub fn immediate(&self) -> &ResponseTimes {
+        &self.immediate
+    }
+
+    pub fn joins(&self) -> &ResponseTimes {
+        &self.joins
+    }
+
+    pub fn pipelines(&self) -> &ResponseTimes {
+        &self.pipelines
+    }
+
+    pub fn slo(&self) -> &SloTracker {
+        &self.slo
+    }
+
+    pub fn transfers(&self) -> &ResponseTimes {
+        &self.transfers
+    }
+
+    pub fn tags(&self) -> &BTreeMap<&'static str, ResponseTimes> {
+        &self.tags
+    }
+
+    pub fn tenants(&self) -> &TenantTracker {
+        &self.tenants
+    }
+
+    /// Breakdown of the requests each server handled, by id.
+    pub fn servers(&self) -> &HashMap<u64, ServerStats> {
+        &self.servers
+    }
+
+    pub fn latencies(&self) -> &LatencyHistogram {
+        &self.latencies
+    }
+
+    pub fn classes(&self) -> &HashMap<(RequestType, RequestSize), ClassStats> {
+        &self.classes
+    }
+
+    pub fn slowdowns(&self) -> &Slowdowns {
+        &self.slowdowns
+    }
+
+    pub fn size_slowdowns(&self) -> &HashMap<RequestSize, Slowdowns> {
+        &self.size_slowdowns
+    }
+
+    pub fn assignments(&self) -> &HashMap<(RequestType, RequestSize, u64), usize> {
+        &self.assignments
+    }
+
+    pub fn queue_history(&self) -> &QueueHistory {
+        &self.queue_history
+    }
+
+    pub fn timeline(&self) -> &Timeline {
+        &self.timeline
+    }
+
+    pub fn total_cost(&self) -> f64 {
+        self.total_cost
+    }
+}
+
+/// Number of latest response times a server keeps for its percentiles.
+const SERVER_LATENCY_SAMPLES: usize = 200;
+
+pub struct ServerStats {
+    response_times: ResponseTimes,
+    /// Latest response times, most recent last.
+    recent: VecDeque<f64>,
+    failed: usize,
+    /// Requests dropped from the server's queue past their deadline.
+    expired: usize,
+    /// When requests were processed, over the shared window or the
+    /// throughput sparkline's span, whichever is longer.
+    completions: SlidingWindow<()>,
+}
+
+impl Default for ServerStats {
+    fn default() -> Self {
+        Self {
+            response_times: ResponseTimes::default(),
+            recent: VecDeque::new(),
+            failed: 0,
+            expired: 0,
+            completions: SlidingWindow::new(THROUGHPUT_HISTORY.max(window::window())),
+        }
+    }
+}
+
+impl ServerStats {
+    pub fn response_times(&self) -> &ResponseTimes {
+        &self.response_times
+    }
+
+    pub fn failed(&self) -> usize {
+        self.failed
+    }
+
+    pub fn expired(&self) -> usize {
+        self.expired
+    }
+
+    /// When requests were processed, for the throughput sparkline.
+    pub fn completions(&self) -> &SlidingWindow<()> {
+        &self.completions
+    }
+
+    fn record(&mut self, wait_time: f64) {
+        self.response_times.record(wait_time);
+        self.completions.push(());
+
+        if self.recent.len() == SERVER_LATENCY_SAMPLES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(wait_time);
+    }
+
+    /// Requests processed per second over the shared window.
+    pub fn throughput(&self) -> f64 {
+        let window = window::window();
+        self.completions.recent(window).count() as f64 / window.as_secs_f64()
+    }
+
+    /// Response time below which the given share of the latest requests fell.
+    pub fn percentile(&self, share: f64) -> Option<f64> {
+        let mut sorted: Vec<f64> = self.recent.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+
+        let idx = ((sorted.len() as f64 * share).ceil() as usize).checked_sub(1)?;
+        sorted.get(idx).copied()
+    }
+}
+
+#[derive(Default)]
+pub struct ClassStats {
+    response_times: ResponseTimes,
+    latencies: LatencyHistogram,
+}
+
+impl ClassStats {
+    pub fn response_times(&self) -> &ResponseTimes {
+        &self.response_times
+    }
+
+    pub fn latencies(&self) -> &LatencyHistogram {
+        &self.latencies
+    }
+
+    fn record(&mut self, wait_time: f64) {
+        self.response_times.record(wait_time);
+        self.latencies.record(wait_time as u64);
+    }
+}
+
+/// Response times of processed requests over their nominal service time,
+/// 1 for a request that didn't wait at all.
+#[derive(Default)]
+pub struct Slowdowns {
+    slowdowns: ResponseTimes,
+    /// In hundredths, as the histogram takes whole numbers.
+    histogram: LatencyHistogram,
+}
+
+impl Slowdowns {
+    fn record(&mut self, slowdown: f64) {
+        self.slowdowns.record(slowdown);
+        self.histogram.record((slowdown * 100.0) as u64);
+    }
+
+    pub fn count(&self) -> usize {
+        self.slowdowns.count
+    }
+
+    pub fn average(&self) -> f64 {
+        self.slowdowns.average()
+    }
+
+    pub fn p99(&self) -> Option<f64> {
+        self.histogram
+            .percentile(0.99)
+            .map(|hundredths| hundredths as f64 / 100.0)
+    }
+}
+
+/// z-score of a 95% confidence level under the normal distribution.
+const CONFIDENCE_Z: f64 = 1.96;
+
+/// Response times of a subset of the processed requests.
+#[derive(Default)]
+pub struct ResponseTimes {
+    count: usize,
+    total: f64,
+    /// Sum of squared deviations from the running average (Welford's method).
+    squared_deviations: f64,
+}
+
+impl ResponseTimes {
+    fn record(&mut self, wait_time: f64) {
+        let previous_average = self.average();
+        self.count += 1;
+        self.total += wait_time;
+        self.squared_deviations += (wait_time - previous_average) * (wait_time - self.average());
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn average(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.total / self.count as f64
+        }
+    }
+
+    /// Average with its 95% confidence interval, e.g. `812.3ms ± 41.0ms`.
+    pub fn summary(&self) -> String {
+        match self.margin_of_error() {
+            Some(margin) => format!("{:.1}ms ± {:.1}ms", self.average(), margin),
+            None => format!("{:.1}ms", self.average()),
+        }
+    }
+
+    /// Half-width of the 95% confidence interval of the average, assuming
+    /// independent samples; `None` until there are two.
+    pub fn margin_of_error(&self) -> Option<f64> {
+        if self.count < 2 {
+            return None;
+        }
+
+        let variance = self.squared_deviations / (self.count - 1) as f64;
+        Some(CONFIDENCE_Z * (variance / self.count as f64).sqrt())
+    }
+}
+
+/// Builds a [`Simulation`], with the generator creating requests at the
+/// default rate unless given a schedule or a trace to replay.
+#[derive(Default)]
+pub struct SimulationBuilder {
+    schedule: Option<RateSchedule>,
+    trace: Option<Vec<TraceEntry>>,
+    record: bool,
+    service_times: ServiceTimeDistribution,
+    tags: TagMix,
+    listener: Option<tokio::net::TcpListener>,
+    window: Option<Duration>,
+    speed: Option<f64>,
+    strategies: StrategyConfig,
+    /// Strategies registered on top of the built-in ones.
+    custom_strategies: Vec<StrategyEntry>,
+    retry_policy: RetryPolicy,
+    burst: BurstPattern,
+    scaling_policy: ScalingPolicy,
+    contention: Option<Vec<f64>>,
+    priorities: PriorityMix,
+    seed: Option<u64>,
+    sinks: Sinks,
+}
+
+impl SimulationBuilder {
+    /// Arrival rate schedule the generator follows instead of the fixed rate.
+    pub fn schedule(mut self, schedule: RateSchedule) -> Self {
+        self.schedule = Some(schedule);
+        self
+    }
+
+    /// Quiet periods and spikes the generator alternates between once burst
+    /// mode is turned on.
+    pub fn burst(mut self, burst: BurstPattern) -> Self {
+        self.burst = burst;
+        self
+    }
+
+    /// When the autoscaler adds and retires servers once it's turned on.
+    pub fn scaling_policy(mut self, policy: ScalingPolicy) -> Self {
+        self.scaling_policy = policy;
+        self
+    }
+
+    /// Recorded requests the generator replays instead of generating its own.
+    pub fn trace(mut self, trace: Vec<TraceEntry>) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
+    /// Keeps the generated requests, for [`Simulation::save_trace`].
+    pub fn record(mut self) -> Self {
+        self.record = true;
+        self
+    }
+
+    /// CSV file the metrics are appended to on every [`Simulation::update`].
+    pub fn metrics_csv(mut self, metrics: MetricsCsv) -> Self {
+        self.sinks.metrics = Some(metrics);
+        self
+    }
+
+    /// StatsD server the events are counted on and the queue depths sent to.
+    pub fn statsd(mut self, statsd: StatsdSink) -> Self {
+        self.sinks.statsd = Some(statsd);
+        self
+    }
+
+    /// OpenTelemetry collector the requests are traced to.
+    pub fn otlp(mut self, otlp: OtlpExporter) -> Self {
+        self.sinks.otlp = Some(otlp);
+        self
+    }
+
+    /// SQLite database the events and samples of the metrics are stored in.
+    #[cfg(feature = "sqlite")]
+    pub fn sqlite(mut self, sqlite: SqliteSink) -> Self {
+        self.sinks.sqlite = Some(sqlite);
+        self
+    }
+
+    /// Distribution the generated requests' service times are sampled from.
+    pub fn service_times(mut self, service_times: ServiceTimeDistribution) -> Self {
+        self.service_times = service_times;
+        self
+    }
+
+    /// Shares of the generated requests with each priority.
+    pub fn priorities(mut self, priorities: PriorityMix) -> Self {
+        self.priorities = priorities;
+        self
+    }
+
+    /// Tags the generated requests carry.
+    pub fn tags(mut self, tags: TagMix) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// Listener external clients inject requests through.
+    pub fn listener(mut self, listener: tokio::net::TcpListener) -> Self {
+        self.listener = Some(listener);
+        self
+    }
+
+    /// Span of the rolling metrics instead of the default 10 seconds.
+    pub fn window(mut self, window: Duration) -> Self {
+        self.window = Some(window);
+        self
+    }
+
+    /// Factor simulated time runs at relative to real time.
+    pub fn speed(mut self, speed: f64) -> Self {
+        self.speed = Some(speed);
+        self
+    }
+
+    /// Policy failed requests are retried with once retries are turned on.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Service-time bounds (ms) of the Size Interval mode's intervals, in
+    /// ascending order.
+    pub fn sita_cutoffs(mut self, cutoffs: Vec<u64>) -> Self {
+        self.strategies.sita_cutoffs = cutoffs;
+        self
+    }
+
+    /// Relative weights of the servers, in order, for the Weighted Random
+    /// mode.
+    pub fn server_weights(mut self, weights: Vec<f64>) -> Self {
+        self.strategies.server_weights = weights;
+        self
+    }
+
+    /// Registers a strategy under `name`, after the built-in ones, for the
+    /// allocator to balance with once a `ConfigChanged` picks it, e.g. with
+    /// the mode [`Simulation::choice_mode`] finds by name. Panics if a
+    /// strategy already has the name.
+    pub fn strategy(
+        mut self,
+        name: &'static str,
+        build: fn(&StrategyConfig) -> Box<dyn Strategy>,
+    ) -> Self {
+        assert!(
+            !REGISTRY
+                .iter()
+                .chain(&self.custom_strategies)
+                .any(|entry| entry.name.eq_ignore_ascii_case(name)),
+            "a strategy is already called {name}"
+        );
+        self.custom_strategies.push(StrategyEntry {
+            name,
+            keyed: false,
+            build,
+        });
+        self
+    }
+
+    /// Slowdown of each server, in order, per request waiting in its queue
+    /// when contention is on; servers past the last one use the last value.
+    /// Panics if empty or if any is negative.
+    pub fn contention(mut self, contention: Vec<f64>) -> Self {
+        assert!(
+            !contention.is_empty() && contention.iter().all(|c| *c >= 0.0),
+            "contention needs one or more non-negative values"
+        );
+        self.contention = Some(contention);
+        self
+    }
+
+    /// Seed of the random draws, e.g. the requests generated and the servers
+    /// the Random mode picks, instead of a random one. Runs with the same
+    /// seed draw the same numbers, though the tasks' timing still varies.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Starts the tasks on the current Tokio runtime, in a context of their
+    /// own: the clock, the window and the settings changed from the Config
+    /// view aren't shared with any other simulation in the process.
+    pub fn build(self) -> Simulation {
+        let contention = self
+            .contention
+            .clone()
+            .unwrap_or_else(|| server::DEFAULT_CONTENTION.to_vec());
+        let strategies = REGISTRY
+            .iter()
+            .chain(&self.custom_strategies)
+            .copied()
+            .collect();
+        let context = Context::new(contention, self.priorities, strategies);
+        context.enter(|| self.start(Arc::clone(&context)))
+    }
+
+    fn start(self, context: Arc<Context>) -> Simulation {
+        if let Some(window) = self.window {
+            window::set_window(window);
+        }
+        if let Some(speed) = self.speed {
+            clock::set_speed(speed);
+        }
+
+        let seed = self.seed.unwrap_or_else(rand::random);
+
+        let (main_tx, main_rx) = mpsc::channel::<SystemEvent>(1000);
+
+        let (gen_tx, gen_rx) = mpsc::channel::<SystemEvent>(1000);
+        let (allocator_tx, allocator_rx) = mpsc::channel::<SystemEvent>(1000);
+        let (server_tx, server_rx) = mpsc::channel::<SystemEvent>(1000);
+        let (autoscaler_tx, autoscaler_rx) = mpsc::channel::<SystemEvent>(1000);
+        let (health_tx, health_rx) = mpsc::channel::<SystemEvent>(1000);
+        let (ui_tx, ui_rx) = mpsc::channel::<SystemEvent>(1000);
+
+        let mut handles = vec![spawn_event_router(
+            main_rx,
+            gen_tx,
+            allocator_tx,
+            server_tx,
+            autoscaler_tx,
+            health_tx,
+            ui_tx,
+        )];
+
+        let recorder = self.record.then(|| Arc::new(TraceRecorder::new()));
+
+        let injected = self.listener.map(|listener| {
+            let (inject_tx, inject_rx) = mpsc::channel::<Request>(1);
+            handles.push(spawn_injector(listener, inject_tx));
+            inject_rx
+        });
+
+        let workload = Workload {
+            retry_policy: self.retry_policy.clone(),
+            burst: self.burst,
+            schedule: self.schedule,
+            trace: self.trace,
+            recorder: recorder.clone(),
+            service_times: self.service_times,
+            tags: self.tags,
+            injected,
+        };
+
+        handles.extend([
+            spawn_request_generator(main_tx.clone(), gen_rx, workload, seed),
+            spawn_request_allocator(main_tx.clone(), allocator_rx, self.strategies, seed),
+            spawn_servers(main_tx.clone(), server_rx, seed),
+            spawn_autoscaler(main_tx.clone(), autoscaler_rx, self.scaling_policy),
+            spawn_health_checker(main_tx.clone(), health_rx, seed),
+        ]);
+
+        Simulation {
+            context,
+            event_tx: main_tx,
+            events: ui_rx,
+            handles,
+            recorder,
+            retry_policy: self.retry_policy,
+            seed,
+            sinks: self.sinks,
+        }
+    }
+}
+
+/// The simulation's tasks, running until [`Simulation::stop`], with the
+/// events they send as it goes. The events must be read as they come, with
+/// [`Simulation::next_event`] or [`Simulation::try_next_event`]: the router
+/// waits for room once 1000 are buffered, which stalls every task behind it.
+pub struct Simulation {
+    context: Arc<Context>,
+    event_tx: Sender<SystemEvent>,
+    events: Receiver<SystemEvent>,
+    handles: Vec<JoinHandle<()>>,
+    recorder: Option<Arc<TraceRecorder>>,
+    retry_policy: RetryPolicy,
+    seed: u64,
+    sinks: Sinks,
+}
+
+impl Simulation {
+    pub fn builder() -> SimulationBuilder {
+        SimulationBuilder::default()
+    }
+
+    /// Clock and settings of this simulation, for code outside its tasks to
+    /// enter, e.g. a frontend reading the time between events.
+    pub fn context(&self) -> Arc<Context> {
+        Arc::clone(&self.context)
+    }
+
+    /// The balancing mode called `name`, built-in or registered with
+    /// [`SimulationBuilder::strategy`], if there is one.
+    pub fn choice_mode(&self, name: &str) -> Option<ServerChoiceMode> {
+        self.context.enter(|| ServerChoiceMode::find(name))
+    }
+
+    /// Sends events into the simulation as if one of its tasks had, e.g. a
+    /// `ConfigChanged` to change the arrival rate or the balancing mode.
+    pub fn sender(&self) -> Sender<SystemEvent> {
+        self.event_tx.clone()
+    }
+
+    /// Policy failed requests are retried with once retries are turned on.
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    /// Seed the tasks' random draws started from, given or picked at random.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Waits for the next event the router passes on to the frontend, which
+    /// has to keep reading them for the simulation to go on.
+    pub async fn next_event(&mut self) -> Option<SystemEvent> {
+        self.events.recv().await
+    }
+
+    /// The next event if one is waiting, without blocking.
+    pub fn try_next_event(&mut self) -> Option<SystemEvent> {
+        self.events.try_recv().ok()
+    }
+
+    /// Folds the events waiting into the state, passing them to the sinks on
+    /// the way, then keeps the state going with [`SystemState::tick`],
+    /// sending the alerts that changed back to be logged, and records the
+    /// metrics. A sink that fails is logged and dropped. Called regularly,
+    /// e.g. once a frame, it's all a frontend needs besides drawing.
+    pub fn update(&mut self, state: &mut SystemState) {
+        let context = Arc::clone(&self.context);
+        context.enter(|| {
+            while let Some(event) = self.try_next_event() {
+                self.sinks.observe(&event, state);
+                state.apply(event);
+            }
+
+            // Logged and stored like any other event once the router sends
+            // them back.
+            for (rule, firing) in state.tick() {
+                self.event_tx
+                    .try_send(SystemEvent::AlertChanged { rule, firing })
+                    .ok();
+            }
+
+            self.sinks.record(state);
+        });
+    }
+
+    /// Aborts every task; the events already sent can still be read.
+    pub fn stop(&self) {
+        for handle in &self.handles {
+            handle.abort();
+        }
+    }
+
+    /// Writes the requests generated so far to a trace file, which
+    /// `SimulationBuilder::trace` can replay.
+    pub fn save_trace(&self, path: &str) -> Result<(), String> {
+        match &self.recorder {
+            Some(recorder) => recorder.save(path),
+            None => Err("requests are only recorded when built with record()".to_string()),
+        }
+    }
+}
+
+/// Where the metrics, traces and events go besides the state, each one
+/// optional.
+#[derive(Default)]
+struct Sinks {
+    metrics: Option<MetricsCsv>,
+    statsd: Option<StatsdSink>,
+    otlp: Option<OtlpExporter>,
+    #[cfg(feature = "sqlite")]
+    sqlite: Option<SqliteSink>,
+}
+
+impl Sinks {
+    /// Passes on an event before the state applies it, while the servers
+    /// still hold the request it's about.
+    fn observe(&mut self, event: &SystemEvent, state: &SystemState) {
+        if let Some(statsd) = &self.statsd {
+            statsd.observe(event);
+        }
+        if let Some(otlp) = &mut self.otlp {
+            otlp.observe(event, state.servers());
+        }
+        #[cfg(feature = "sqlite")]
+        if let Some(sqlite) = &mut self.sqlite {
+            sqlite.observe(event);
+        }
+    }
+
+    /// Samples the metrics and sends what's due, dropping the file or the
+    /// database that can't be written to.
+    fn record(&mut self, state: &mut SystemState) {
+        if let Some(csv) = &mut self.metrics
+            && let Err(e) = csv.record(state.stats(), state.servers())
+        {
+            state.log_failure(e);
+            self.metrics = None;
+        }
+
+        #[cfg(feature = "sqlite")]
+        if let Some(db) = &mut self.sqlite
+            && let Err(e) = db.record(state)
+        {
+            state.log_failure(e);
+            self.sqlite = None;
+        }
+
+        if let Some(statsd) = &mut self.statsd {
+            statsd.gauge_queues(state.servers());
+        }
+        if let Some(otlp) = &mut self.otlp {
+            otlp.export();
+        }
+    }
+}
+
+fn spawn_event_router(
+    mut event_rx: Receiver<SystemEvent>,
+    gen_tx: Sender<SystemEvent>,
+    allocator_tx: Sender<SystemEvent>,
+    server_tx: Sender<SystemEvent>,
+    autoscaler_tx: Sender<SystemEvent>,
+    health_tx: Sender<SystemEvent>,
+    ui_tx: Sender<SystemEvent>,
+) -> JoinHandle<()> {
+    context::spawn(async move {
+        while let Some(event) = event_rx.recv().await {
+            match event {
+                SystemEvent::RequestCreated(_) => {
+                    allocator_tx.send(event.clone()).await.ok();
+                    autoscaler_tx.send(event.clone()).await.ok();
+
+                    ui_tx.send(event).await.ok();
+                }
+                SystemEvent::RequestAssigned { .. } => {
+                    gen_tx.send(event.clone()).await.ok();
+                    server_tx.send(event.clone()).await.ok();
+                    autoscaler_tx.send(event.clone()).await.ok();
+                    health_tx.send(event.clone()).await.ok();
+
+                    ui_tx.send(event).await.ok();
+                }
+                SystemEvent::RequestProcessed { .. } => {
+                    gen_tx.send(event.clone()).await.ok();
+                    allocator_tx.send(event.clone()).await.ok();
+                    autoscaler_tx.send(event.clone()).await.ok();
+                    health_tx.send(event.clone()).await.ok();
+
+                    ui_tx.send(event).await.ok();
+                }
+                SystemEvent::RequestProcessStarted { .. }
+                | SystemEvent::RequestPreempted { .. }
+                | SystemEvent::RequestParked { .. } => {
+                    allocator_tx.send(event.clone()).await.ok();
+                    health_tx.send(event.clone()).await.ok();
+
+                    ui_tx.send(event.clone()).await.ok();
+                }
+                SystemEvent::RequestRejected { .. } => {
+                    gen_tx.send(event.clone()).await.ok();
+                    autoscaler_tx.send(event.clone()).await.ok();
+
+                    ui_tx.send(event).await.ok();
+                }
+                SystemEvent::RequestExpired { server_id, .. } => {
+                    gen_tx.send(event.clone()).await.ok();
+
+                    if server_id.is_none() {
+                        autoscaler_tx.send(event.clone()).await.ok();
+                    } else {
+                        allocator_tx.send(event.clone()).await.ok();
+                        health_tx.send(event.clone()).await.ok();
+                    }
+
+                    ui_tx.send(event).await.ok();
+                }
+                SystemEvent::RequestRequeued { .. } | SystemEvent::RequestForwarded { .. } => {
+                    gen_tx.send(event.clone()).await.ok();
+                    allocator_tx.send(event.clone()).await.ok();
+                    autoscaler_tx.send(event.clone()).await.ok();
+
+                    ui_tx.send(event).await.ok();
+                }
+                SystemEvent::RequestCoalesced { .. } => {
+                    ui_tx.send(event).await.ok();
+                }
+                SystemEvent::RequestDeduplicated { .. } => {
+                    gen_tx.send(event.clone()).await.ok();
+                    autoscaler_tx.send(event.clone()).await.ok();
+
+                    ui_tx.send(event).await.ok();
+                }
+                SystemEvent::ServerAdded { .. } | SystemEvent::ServerRemoved { .. } => {
+                    allocator_tx.send(event.clone()).await.ok();
+                    server_tx.send(event.clone()).await.ok();
+                    autoscaler_tx.send(event.clone()).await.ok();
+                    health_tx.send(event.clone()).await.ok();
+
+                    ui_tx.send(event).await.ok();
+                }
+                SystemEvent::ServerStatusChanged { .. } => {
+                    allocator_tx.send(event.clone()).await.ok();
+                    server_tx.send(event.clone()).await.ok();
+
+                    ui_tx.send(event).await.ok();
+                }
+                SystemEvent::ServerCrashed { .. } | SystemEvent::ServerPaused { .. } => {
+                    allocator_tx.send(event.clone()).await.ok();
+                    server_tx.send(event.clone()).await.ok();
+                    health_tx.send(event.clone()).await.ok();
+
+                    ui_tx.send(event).await.ok();
+                }
+                SystemEvent::BreakerChanged { .. } => {
+                    allocator_tx.send(event.clone()).await.ok();
+
+                    ui_tx.send(event).await.ok();
+                }
+                SystemEvent::RateLimitChanged { .. } => {
+                    allocator_tx.send(event.clone()).await.ok();
+
+                    ui_tx.send(event).await.ok();
+                }
+                SystemEvent::QueueDisciplineChanged { .. }
+                | SystemEvent::FailureRateChanged { .. }
+                | SystemEvent::WorkersChanged { .. } => {
+                    server_tx.send(event.clone()).await.ok();
+
+                    ui_tx.send(event).await.ok();
+                }
+                SystemEvent::ServerStalled { .. } => {
+                    allocator_tx.send(event.clone()).await.ok();
+                    health_tx.send(event.clone()).await.ok();
+
+                    ui_tx.send(event).await.ok();
+                }
+                SystemEvent::Scaled(_)
+                | SystemEvent::BurstChanged { .. }
+                | SystemEvent::AlertChanged { .. } => {
+                    ui_tx.send(event).await.ok();
+                }
+                SystemEvent::ErrorEncountered(_) => {
+                    ui_tx.send(event.clone()).await.ok();
+                }
+                SystemEvent::ConfigChanged(ConfigUpdate {
+                    speed,
+                    queue_capacity,
+                    ..
+                }) => {
+                    if let Some(speed) = speed {
+                        clock::set_speed(speed);
+                    }
+                    if let Some(capacity) = queue_capacity {
+                        server::set_queue_capacity(capacity);
+                    }
+
+                    gen_tx.send(event.clone()).await.ok();
+                    allocator_tx.send(event.clone()).await.ok();
+                    server_tx.send(event.clone()).await.ok();
+                    autoscaler_tx.send(event.clone()).await.ok();
+                    health_tx.send(event.clone()).await.ok();
+
+                    ui_tx.send(event.clone()).await.ok();
+                }
+            }
+        }
+    })
+}
+
+fn spawn_request_generator(
+    event_tx: Sender<SystemEvent>,
+    mut event_rx: Receiver<SystemEvent>,
+    workload: Workload,
+    seed: u64,
+) -> JoinHandle<()> {
+    context::spawn(async move {
+        let Workload {
+            retry_policy,
+            burst,
+            schedule,
+            trace,
+            recorder,
+            service_times,
+            tags,
+            mut injected,
+        } = workload;
+        let mut started = clock::now();
+        // Set while the simulation is paused.
+        let mut paused_at: Option<Instant> = None;
+        // Trace entries already sent.
+        let mut replayed = 0;
+        let mut arrival_rate = INITIAL_AVG_RATE as f32;
+        let mut retries = false;
+        let mut bursts = false;
+        let mut sessions_enabled = false;
+        let mut forks = false;
+        let mut pipelines = false;
+        let mut sessions: Vec<Session> = Vec::new();
+        let mut next_session_id = 1;
+        let mut dependencies = false;
+        // Ids of the latest new requests, which new ones may depend on.
+        let mut recent: VecDeque<usize> = VecDeque::with_capacity(RECENT_REQUESTS);
+        let mut spiking = false;
+        let mut phase_started = clock::now();
+        // Rejected, expired or failed requests waiting out their backoff.
+        let mut scheduled: Vec<(Instant, Request)> = Vec::new();
+        // Requests on a server, by id, to retry the ones that fail.
+        let mut assigned: HashMap<usize, Request> = HashMap::new();
+
+        let mut rng = task_rng(seed, GENERATOR_RNG);
+        let mut ticker = Ticker::new(Duration::from_millis(100));
+
+        let mut pending_requests = 0;
+
+        loop {
+            while let Ok(event) = event_rx.try_recv() {
+                let failed = match event {
+                    SystemEvent::RequestRejected { request } => {
+                        pending_requests -= 1;
+                        Some(request)
+                    }
+                    SystemEvent::RequestExpired { request, server_id } => {
+                        match server_id {
+                            Some(_) => {
+                                assigned.remove(&request.id);
+                            }
+                            None => pending_requests -= 1,
+                        }
+                        Some(request)
+                    }
+                    SystemEvent::RequestAssigned { request, .. } => {
+                        pending_requests -= 1;
+                        assigned.insert(request.id, request);
+                        None
+                    }
+                    SystemEvent::RequestProcessed {
+                        request_id,
+                        outcome,
+                        ..
+                    } => assigned
+                        .remove(&request_id)
+                        .filter(|_| outcome == RequestOutcome::Failed),
+                    SystemEvent::RequestDeduplicated {
+                        request, outcome, ..
+                    } => {
+                        pending_requests -= 1;
+                        (outcome == RequestOutcome::Failed).then_some(request)
+                    }
+                    SystemEvent::RequestRequeued { .. } | SystemEvent::RequestForwarded { .. } => {
+                        pending_requests += 1;
+                        None
+                    }
+                    SystemEvent::ConfigChanged(update) => {
+                        if let Some(seed) = update.seed {
+                            rng = task_rng(seed, GENERATOR_RNG);
+                        }
+                        if let Some(new_rate) = update.arrival_rate {
+                            arrival_rate = new_rate;
+                        }
+                        if let Some(enabled) = update.retries {
+                            retries = enabled;
+                        }
+                        if let Some(enabled) = update.forks {
+                            forks = enabled;
+                        }
+                        if let Some(enabled) = update.pipelines {
+                            pipelines = enabled;
+                        }
+                        if let Some(enabled) = update.dependencies {
+                            dependencies = enabled;
+                        }
+                        if let Some(enabled) = update.sessions {
+                            sessions_enabled = enabled;
+                            if !enabled {
+                                sessions.clear();
+                            }
+                        }
+                        if let Some(paused) = update.paused {
+                            match (paused, paused_at) {
+                                (true, None) => paused_at = Some(clock::now()),
+                                (false, Some(at)) => {
+                                    // Picks the schedule, trace, bursts and
+                                    // retries up where they were.
+                                    let pause = clock::since(at);
+                                    started += pause;
+                                    phase_started += pause;
+                                    for (retry_at, _) in &mut scheduled {
+                                        *retry_at += pause;
+                                    }
+                                    paused_at = None;
+                                }
+                                _ => {}
+                            }
+                        }
+                        if let Some(enabled) = update.bursts {
+                            bursts = enabled;
+                            phase_started = clock::now();
+
+                            if spiking {
+                                spiking = false;
+                                event_tx
+                                    .send(SystemEvent::BurstChanged { rate: None })
+                                    .await
+                                    .ok();
+                            }
+                        }
+                        None
+                    }
+                    _ => None,
+                };
+
+                if retries
+                    && let Some(request) = failed
+                    && let Some(backoff) = retry_policy.backoff(request.attempt)
+                {
+                    scheduled.push((clock::now() + backoff, request));
+                }
+            }
+
+            if paused_at.is_some() {
+                ticker.tick().await;
+                continue;
+            }
+
+            if let Some(schedule) = &schedule {
+                let scheduled_rate =
+                    (schedule.rate_at(clock::since(started)) * 10.0).round() / 10.0;
+
+                if scheduled_rate != arrival_rate {
+                    arrival_rate = scheduled_rate;
+
+                    event_tx
+                        .send(SystemEvent::ConfigChanged(ConfigUpdate {
+                            arrival_rate: Some(arrival_rate),
+                            ..Default::default()
+                        }))
+                        .await
+                        .ok();
+                }
+            }
+
+            let phase = if spiking { burst.spike } else { burst.quiet };
+            if bursts && clock::since(phase_started) >= phase {
+                spiking = !spiking;
+                phase_started = clock::now();
+
+                event_tx
+                    .send(SystemEvent::BurstChanged {
+                        rate: spiking.then_some(arrival_rate * burst.multiplier),
+                    })
+                    .await
+                    .ok();
+            }
+
+            let rate = if spiking {
+                arrival_rate * burst.multiplier
+            } else {
+                arrival_rate
+            };
+
+            // Expected arrivals per 100ms tick, which may exceed one in a spike.
+            let expected = rate / 10.0;
+            let arrivals = match &trace {
+                Some(trace) => trace[replayed..]
+                    .iter()
+                    .take_while(|entry| entry.offset <= clock::since(started))
+                    .count() as u32,
+                None => expected.floor() as u32 + rng.random_bool(expected.fract() as f64) as u32,
+            };
+
+            for _ in 0..arrivals {
+                if pending_requests >= PENDING_REQUESTS_LIMIT {
+                    break;
+                }
+
+                let requests = match &trace {
+                    Some(trace) => {
+                        replayed += 1;
+                        vec![trace[replayed - 1].to_request()]
+                    }
+                    None => {
+                        let mut request = Request::create_random(service_times, &mut rng);
+                        request.tag = tags.sample(&mut rng);
+                        if sessions_enabled
+                            && let Some(session) =
+                                Session::start(&mut request, next_session_id, &mut rng)
+                        {
+                            sessions.push(session);
+                            next_session_id += 1;
+                        }
+
+                        if dependencies && rng.random_bool(request::DEPENDENT_RATIO) {
+                            let count = rng.random_range(1..=request::MAX_DEPENDENCIES);
+                            for (slot, id) in request
+                                .depends_on
+                                .iter_mut()
+                                .zip(recent.iter().copied().choose_multiple(&mut rng, count))
+                            {
+                                *slot = Some(id);
+                            }
+                        }
+                        if pipelines && rng.random_bool(request::PIPELINE_RATIO) {
+                            request = request.pipeline();
+                        }
+
+                        // A fork only goes out whole, so it needs room in
+                        // the pending list for its widest split.
+                        let fork_fits = pending_requests + *request::FORK_WIDTH.end() as i32
+                            <= PENDING_REQUESTS_LIMIT;
+
+                        let requests = if forks && fork_fits && rng.random_bool(request::FORK_RATIO)
+                        {
+                            request.fork(&mut rng)
+                        } else if rng.random_bool(request::DUPLICATE_RATIO) {
+                            vec![request, request.duplicate(&mut rng)]
+                        } else {
+                            vec![request]
+                        };
+
+                        // Later requests only depend on ones that go through
+                        // the servers: a fork's parts rather than the request
+                        // split into them, and not a duplicate, which may be
+                        // coalesced into the original.
+                        for created in requests
+                            .iter()
+                            .filter(|created| created.fork.is_some() || created.id == request.id)
+                        {
+                            if recent.len() == RECENT_REQUESTS {
+                                recent.pop_front();
+                            }
+                            recent.push_back(created.id);
+                        }
+                        requests
+                    }
+                };
+
+                for request in requests {
+                    if let Some(recorder) = &recorder {
+                        recorder.record(&request);
+                    }
+
+                    event_tx
+                        .send(SystemEvent::RequestCreated(request))
+                        .await
+                        .ok();
+
+                    pending_requests += 1;
+                }
+            }
+
+            if let Some(injected) = &mut injected {
+                while pending_requests < PENDING_REQUESTS_LIMIT
+                    && let Ok(request) = injected.try_recv()
+                {
+                    if let Some(recorder) = &recorder {
+                        recorder.record(&request);
+                    }
+
+                    event_tx
+                        .send(SystemEvent::RequestCreated(request))
+                        .await
+                        .ok();
+
+                    pending_requests += 1;
+                }
+            }
+
+            for session in &mut sessions {
+                if pending_requests >= PENDING_REQUESTS_LIMIT {
+                    break;
+                }
+
+                if let Some(mut request) = session.next_request(service_times, &mut rng) {
+                    request.tag = tags.sample(&mut rng);
+
+                    if let Some(recorder) = &recorder {
+                        recorder.record(&request);
+                    }
+
+                    event_tx
+                        .send(SystemEvent::RequestCreated(request))
+                        .await
+                        .ok();
+
+                    pending_requests += 1;
+                }
+            }
+            sessions.retain(|session| !session.is_over());
+
+            while let Some(idx) = scheduled.iter().position(|(at, _)| *at <= clock::now()) {
+                let (_, request) = scheduled.remove(idx);
+
+                event_tx
+                    .send(SystemEvent::RequestCreated(request.retry()))
+                    .await
+                    .ok();
+
+                pending_requests += 1;
+            }
+
+            ticker.tick().await;
+        }
+    })
+}
+
+fn spawn_request_allocator(
+    event_tx: Sender<SystemEvent>,
+    mut event_rx: Receiver<SystemEvent>,
+    strategies: StrategyConfig,
+    seed: u64,
+) -> JoinHandle<()> {
+    context::spawn(async move {
+        let mut server_states = server::initial_servers();
+        let mut requests = VecDeque::new();
+        let mut strategy = ServerChoiceMode::default().build(&strategies);
+        let mut delegate_mode = None;
+        let mut ticker = Ticker::new(Duration::from_millis(50));
+
+        let mut rng = task_rng(seed, ALLOCATOR_RNG);
+
+        let mut admission_control = false;
+        let mut anti_affinity = false;
+        let mut batching = false;
+        let mut network = false;
+        let mut deduplication = false;
+        let mut fair_queueing = false;
+        let mut paused = false;
+        let mut fair_queue = FairQueue::default();
+        // Coalesced duplicates by idempotency key.
+        let mut duplicates: HashMap<usize, Vec<Request>> = HashMap::new();
+        let mut batch_started = clock::now();
+        let mut consecutive_full_errors = 0;
+
+        loop {
+            while let Ok(event) = event_rx.try_recv() {
+                strategy.observe(&event);
+
+                match event {
+                    SystemEvent::RequestCreated(request) => {
+                        let leader_id = deduplication
+                            .then(|| {
+                                requests
+                                    .iter()
+                                    .chain(
+                                        server_states.iter().flat_map(ServerState::held_requests),
+                                    )
+                                    .find(|leader| {
+                                        leader.idempotency_key == request.idempotency_key
+                                            && leader.id != request.id
+                                    })
+                                    .map(|leader| leader.id)
+                            })
+                            .flatten();
+
+                        if let Some(leader_id) = leader_id {
+                            duplicates
+                                .entry(request.idempotency_key)
+                                .or_default()
+                                .push(request);
+
+                            event_tx
+                                .send(SystemEvent::RequestCoalesced {
+                                    request_id: request.id,
+                                    leader_id,
+                                })
+                                .await
+                                .ok();
+                        } else if admission_control
+                            && occupancy(&server_states) >= ADMISSION_THRESHOLD
+                        {
+                            let event = SystemEvent::RequestRejected { request };
+                            strategy.observe(&event);
+                            event_tx.send(event).await.ok();
+                        } else {
+                            request::insert_by_priority(&mut requests, request);
+                        }
+                    }
+                    SystemEvent::RequestProcessed {
+                        request_id,
+                        server_id,
+                        outcome,
+                        ..
+                    } => {
+                        let finished = find_server(&mut server_states, server_id)
+                            .and_then(|server| server.finish_request(request_id));
+
+                        // Duplicates share the outcome of their leader's last stage.
+                        if let Some(leader) = finished
+                            && (outcome == RequestOutcome::Failed || leader.next_stage().is_none())
+                        {
+                            for request in duplicates
+                                .remove(&leader.idempotency_key)
+                                .unwrap_or_default()
+                            {
+                                event_tx
+                                    .send(SystemEvent::RequestDeduplicated {
+                                        request,
+                                        leader_id: leader.id,
+                                        outcome,
+                                    })
+                                    .await
+                                    .ok();
+                            }
+                        }
+                    }
+                    SystemEvent::RequestProcessStarted {
+                        request_id,
+                        server_id,
+                        service_time,
+                        cache_hit,
+                    } => {
+                        if let Some(server) = find_server(&mut server_states, server_id) {
+                            server.start_request(request_id, service_time, cache_hit);
+                        }
+                    }
+                    SystemEvent::RequestRequeued { request, .. }
+                    | SystemEvent::RequestForwarded { request, .. } => {
+                        request::insert_by_priority(&mut requests, request);
+                    }
+                    SystemEvent::ServerAdded { server_id } => {
+                        server_states.push(ServerState::added(server_id));
+                    }
+                    SystemEvent::ServerRemoved { server_id } => {
+                        server_states.retain(|server| server.id != server_id);
+                    }
+                    SystemEvent::ServerStatusChanged { server_id, status } => {
+                        if let Some(server) = find_server(&mut server_states, server_id) {
+                            server.set_status(status);
+                        }
+                    }
+                    SystemEvent::BreakerChanged { server_id, state } => {
+                        if let Some(server) = find_server(&mut server_states, server_id) {
+                            server.breaker = state;
+                        }
+                    }
+                    SystemEvent::RequestPreempted {
+                        request_id,
+                        server_id,
+                    }
+                    | SystemEvent::RequestParked {
+                        request_id,
+                        server_id,
+                    } => {
+                        if let Some(server) = find_server(&mut server_states, server_id) {
+                            server.preempt(request_id);
+                        }
+                    }
+                    SystemEvent::RequestExpired {
+                        request,
+                        server_id: Some(server_id),
+                    } => {
+                        if let Some(server) = find_server(&mut server_states, server_id) {
+                            server.remove_queued(request.id);
+                        }
+                        release_duplicates(&mut duplicates, &mut requests, request.idempotency_key);
+                    }
+                    SystemEvent::ServerCrashed { server_id, .. } => {
+                        if let Some(server) = find_server(&mut server_states, server_id) {
+                            server.crash();
+                        }
+                    }
+                    SystemEvent::RateLimitChanged { server_id, limit } => {
+                        if let Some(server) = find_server(&mut server_states, server_id) {
+                            server.rate_limit = limit;
+                        }
+                    }
+                    SystemEvent::ServerPaused { server_id, paused } => {
+                        if let Some(server) = find_server(&mut server_states, server_id) {
+                            server.set_paused(paused);
+                        }
+                    }
+                    SystemEvent::ServerStalled {
+                        server_id,
+                        duration,
+                    } => {
+                        if let Some(server) = find_server(&mut server_states, server_id) {
+                            server.stall(duration);
+                        }
+                    }
+                    SystemEvent::ConfigChanged(update) => {
+                        if let Some(seed) = update.seed {
+                            rng = task_rng(seed, ALLOCATOR_RNG);
+                        }
+                        if let Some(value) = update.paused {
+                            paused = value;
+                        }
+                        if let Some(new_mode) = update.choice_mode {
+                            strategy = new_mode.build(&strategies);
+                        }
+                        if let Some(enabled) = update.admission_control {
+                            admission_control = enabled;
+                        }
+                        if let Some(enabled) = update.anti_affinity {
+                            anti_affinity = enabled;
+                        }
+                        if let Some(enabled) = update.batching {
+                            batching = enabled;
+                            batch_started = clock::now();
+                        }
+                        if let Some(enabled) = update.network {
+                            network = enabled;
+                        }
+                        if let Some(enabled) = update.fair_queueing {
+                            fair_queueing = enabled;
+                        }
+                        if let Some(enabled) = update.deduplication {
+                            deduplication = enabled;
+                            if !enabled {
+                                let keys: Vec<usize> = duplicates.keys().copied().collect();
+                                for key in keys {
+                                    release_duplicates(&mut duplicates, &mut requests, key);
+                                }
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            // Requests wait in the pending list until the simulation resumes.
+            if paused {
+                ticker.tick().await;
+                continue;
+            }
+
+            while let Some(idx) = requests.iter().position(Request::is_expired) {
+                if let Some(request) = requests.remove(idx) {
+                    release_duplicates(&mut duplicates, &mut requests, request.idempotency_key);
+                    event_tx
+                        .send(SystemEvent::RequestExpired {
+                            request,
+                            server_id: None,
+                        })
+                        .await
+                        .ok();
+                }
+            }
+            strategy.observe_pending(requests.len());
+
+            if batching {
+                if clock::since(batch_started) >= BATCH_WINDOW {
+                    batch_started = clock::now();
+
+                    // Longest requests first, each onto the server that will be
+                    // done soonest, so the batch evens out the servers' loads.
+                    let (blocked, mut batch): (Vec<Request>, Vec<Request>) = requests
+                        .iter()
+                        .copied()
+                        .partition(|request| is_blocked(request, &requests, &server_states));
+                    requests = blocked.into();
+                    batch.sort_by_key(|request| std::cmp::Reverse(request.get_time()));
+
+                    for mut request in batch {
+                        let Some(idx) = place_in_batch(&server_states, &request) else {
+                            // Waits for the next batch.
+                            request::insert_by_priority(&mut requests, request);
+                            continue;
+                        };
+
+                        let server = &mut server_states[idx];
+                        request.batched = true;
+                        request.assigned_at = Some(clock::now());
+                        fair_queue.charge(&request);
+                        start_transfer(server, &mut request, network);
+                        server.add_request(request);
+
+                        let event = SystemEvent::RequestAssigned {
+                            server_id: server.id,
+                            request,
+                        };
+                        strategy.observe(&event);
+                        event_tx.send(event).await.ok();
+                    }
+                }
+            } else if let Some(next) = {
+                let unblocked = |request: &Request| !is_blocked(request, &requests, &server_states);
+                if fair_queueing {
+                    fair_queue.pick(&requests, unblocked)
+                } else {
+                    requests.iter().position(unblocked)
+                }
+            } {
+                let request = &requests[next];
+                let mut assigned = false;
+
+                let mut server_indices = strategy.choose(&server_states, request, &mut rng);
+
+                // Paused servers only get requests nobody else has room for.
+                server_indices.sort_by_key(|idx| server_states[*idx].paused);
+
+                if anti_affinity && matches!(request.size, RequestSize::Large) {
+                    // Stable sort keeps the strategy's order among the remaining servers.
+                    server_indices.sort_by_key(|idx| {
+                        server_states[*idx]
+                            .queue
+                            .back()
+                            .is_some_and(|last| matches!(last.size, RequestSize::Large))
+                    });
+                }
+
+                if let Some(fork) = request.fork {
+                    // Parts of a fork only run in parallel on different servers.
+                    server_indices.sort_by_key(|idx| server_states[*idx].holds_fork(fork.parent));
+                }
+
+                if let Some(tier) = stage_tier(&server_states, request) {
+                    server_indices.retain(|idx| server_states[*idx].tier == tier);
+                }
+
+                for &idx in &server_indices {
+                    let server = &mut server_states[idx];
+
+                    if server.accepts_requests() && !server.queue_full() {
+                        let mut request = requests.remove(next).unwrap();
+                        request.batched = false;
+                        request.assigned_at = Some(clock::now());
+                        fair_queue.charge(&request);
+                        start_transfer(server, &mut request, network);
+                        server.add_request(request);
+
+                        let event = SystemEvent::RequestAssigned {
+                            server_id: server.id,
+                            request,
+                        };
+                        strategy.observe(&event);
+                        event_tx.send(event).await.ok();
+
+                        assigned = true;
+                        break;
+                    }
+                }
+
+                if strategy.delegate() != delegate_mode {
+                    delegate_mode = strategy.delegate();
+
+                    event_tx
+                        .send(SystemEvent::ConfigChanged(ConfigUpdate {
+                            delegate_mode,
+                            ..Default::default()
+                        }))
+                        .await
+                        .ok();
+                }
+
+                if assigned {
+                    consecutive_full_errors = 0;
+                } else {
+                    consecutive_full_errors += 1;
+
+                    if consecutive_full_errors % 10 == 1 {
+                        event_tx
+                            .send(SystemEvent::ErrorEncountered(
+                                "All servers are full".to_string(),
+                            ))
+                            .await
+                            .ok();
+                    }
+
+                    // With admission control the backlog is bounded by rejections instead.
+                    if !admission_control && consecutive_full_errors > 5 {
+                        clock::sleep(Duration::from_millis(50 * consecutive_full_errors.min(20)))
+                            .await;
+                    }
+                }
+            }
+
+            ticker.tick().await;
+        }
+    })
+}
+
+/// Starts sending the request to the server, which can't process it before
+/// its payload arrives; instant when the network isn't modeled.
+fn start_transfer(server: &ServerState, request: &mut Request, network: bool) {
+    request.arrives_at =
+        network.then(|| clock::now() + Duration::from_millis(server.transfer_time(request)));
+}
+
+/// Server a batched request goes to: the one with the least outstanding work
+/// among those with room, preferring ones without another part of its fork,
+/// unpaused ones and ones it fits in memory.
+fn place_in_batch(server_states: &[ServerState], request: &Request) -> Option<usize> {
+    let tier = stage_tier(server_states, request);
+
+    server_states
+        .iter()
+        .enumerate()
+        .filter(|(_, server)| {
+            server.accepts_requests()
+                && !server.queue_full()
+                && tier.is_none_or(|tier| server.tier == tier)
+        })
+        .min_by_key(|(_, server)| {
+            (
+                request
+                    .fork
+                    .is_some_and(|fork| server.holds_fork(fork.parent)),
+                server.paused,
+                server.memory_used() + request.memory > server.memory,
+                server.remaining_workload(),
+            )
+        })
+        .map(|(idx, _)| idx)
+}
+
+/// Puts the duplicates of a request that won't complete back in the pending
+/// list, to run on their own.
+fn release_duplicates(
+    duplicates: &mut HashMap<usize, Vec<Request>>,
+    requests: &mut VecDeque<Request>,
+    idempotency_key: usize,
+) {
+    for request in duplicates.remove(&idempotency_key).unwrap_or_default() {
+        request::insert_by_priority(requests, request);
+    }
+}
+
+/// Whether any of the request's dependencies is still pending or on a server.
+/// Dependencies that left the system, however they did, count as complete.
+pub fn is_blocked(
+    request: &Request,
+    pending: &VecDeque<Request>,
+    server_states: &[ServerState],
+) -> bool {
+    request.dependencies().any(|id| {
+        pending.iter().any(|request| request.id == id)
+            || server_states.iter().any(|server| server.holds_request(id))
+    })
+}
+
+/// Server tier the request's pipeline stage must run on. Requests outside a
+/// pipeline, or whose tier has no servers left, may run anywhere.
+fn stage_tier(server_states: &[ServerState], request: &Request) -> Option<u32> {
+    request
+        .stage
+        .map(|stage| stage.stage)
+        .filter(|tier| server_states.iter().any(|server| server.tier == *tier))
+}
+
+fn occupancy(server_states: &[ServerState]) -> f64 {
+    let queued: usize = server_states.iter().map(|server| server.queue.len()).sum();
+    let capacity = server::queue_capacity() * server_states.len();
+
+    queued as f64 / capacity.max(1) as f64
+}
+
+fn spawn_servers(
+    event_tx: Sender<SystemEvent>,
+    mut event_rx: Receiver<SystemEvent>,
+    seed: u64,
+) -> JoinHandle<()> {
+    context::spawn(async move {
+        let mut servers = server::initial_servers();
+        // Removed servers still finishing their in-flight requests.
+        let mut retiring: Vec<ServerState> = Vec::new();
+        let mut contention = false;
+        let mut stalls = false;
+        let mut preemption = false;
+        let mut caching = false;
+        let mut paused = false;
+        // Servers whose out-of-memory crash hasn't come back through the router.
+        let mut crashing: Vec<u64> = Vec::new();
+        let mut rng = task_rng(seed, SERVERS_RNG);
+
+        let mut ticker = Ticker::new(Duration::from_millis(10));
+
+        loop {
+            while let Ok(event) = event_rx.try_recv() {
+                match event {
+                    SystemEvent::RequestAssigned { server_id, request } => {
+                        if let Some(server) = find_server(&mut servers, server_id) {
+                            server.add_request(request);
+                        } else {
+                            // Assigned while the server was being removed.
+                            event_tx
+                                .send(SystemEvent::RequestRequeued { server_id, request })
+                                .await
+                                .ok();
+                        }
+                    }
+                    SystemEvent::ServerAdded { server_id } => {
+                        servers.push(ServerState::added(server_id));
+                    }
+                    SystemEvent::ServerStatusChanged { server_id, status } => {
+                        if let Some(server) = find_server(&mut servers, server_id) {
+                            server.set_status(status);
+                        }
+                    }
+                    SystemEvent::QueueDisciplineChanged {
+                        server_id,
+                        discipline,
+                    } => {
+                        if let Some(server) = find_server(&mut servers, server_id) {
+                            server.discipline = discipline;
+                        }
+                    }
+                    SystemEvent::FailureRateChanged { server_id, rate } => {
+                        if let Some(server) = find_server(&mut servers, server_id) {
+                            server.failure_rate = rate;
+                        }
+                    }
+                    SystemEvent::WorkersChanged { server_id, workers } => {
+                        if let Some(server) = find_server(&mut servers, server_id) {
+                            server.workers = workers;
+                        }
+                    }
+                    SystemEvent::ServerPaused { server_id, paused } => {
+                        if let Some(server) = find_server(&mut servers, server_id) {
+                            server.set_paused(paused);
+                        }
+                    }
+                    SystemEvent::ServerCrashed {
+                        server_id,
+                        drop_requests,
+                    } => {
+                        crashing.retain(|id| *id != server_id);
+
+                        if let Some(server) = find_server(&mut servers, server_id) {
+                            for request in server.crash() {
+                                let event = if drop_requests {
+                                    SystemEvent::RequestProcessed {
+                                        server_id,
+                                        request_id: request.id,
+                                        created_at: request.created_at,
+                                        assigned_at: request.assigned_at,
+                                        started_at: request.started_at,
+                                        outcome: RequestOutcome::Failed,
+                                    }
+                                } else {
+                                    SystemEvent::RequestRequeued { server_id, request }
+                                };
+                                event_tx.send(event).await.ok();
+                            }
+                        }
+                    }
+                    SystemEvent::ConfigChanged(update) => {
+                        if let Some(seed) = update.seed {
+                            rng = task_rng(seed, SERVERS_RNG);
+                        }
+                        if let Some(enabled) = update.contention {
+                            contention = enabled;
+                        }
+                        if let Some(enabled) = update.stalls {
+                            stalls = enabled;
+                        }
+                        if let Some(enabled) = update.preemption {
+                            preemption = enabled;
+                        }
+                        if let Some(enabled) = update.caching {
+                            caching = enabled;
+                        }
+                        if let Some(value) = update.paused
+                            && value != paused
+                        {
+                            paused = value;
+                            if paused {
+                                // Sets the requests being processed aside with
+                                // their progress; they resume once unpaused.
+                                for server in servers.iter_mut().chain(retiring.iter_mut()) {
+                                    let request_ids: Vec<usize> = server
+                                        .in_flight
+                                        .iter()
+                                        .map(|in_flight| in_flight.request.id)
+                                        .collect();
+
+                                    for request_id in request_ids {
+                                        server.preempt(request_id);
+                                        event_tx
+                                            .send(SystemEvent::RequestParked {
+                                                request_id,
+                                                server_id: server.id,
+                                            })
+                                            .await
+                                            .ok();
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    SystemEvent::ServerRemoved { server_id } => {
+                        if let Some(idx) = servers.iter().position(|server| server.id == server_id)
+                        {
+                            let mut server = servers.remove(idx);
+
+                            while let Some(request) = server.remove_request() {
+                                event_tx
+                                    .send(SystemEvent::RequestRequeued { server_id, request })
+                                    .await
+                                    .ok();
+                            }
+
+                            if !server.in_flight.is_empty() {
+                                retiring.push(server);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            if paused {
+                ticker.tick().await;
+                continue;
+            }
+            clock::count_step();
+
+            for server in servers.iter_mut().chain(retiring.iter_mut()) {
+                for request in server.take_finished() {
+                    let outcome = if rng.random_bool(server.failure_rate) {
+                        RequestOutcome::Failed
+                    } else {
+                        RequestOutcome::Succeeded
+                    };
+
+                    event_tx
+                        .send(SystemEvent::RequestProcessed {
+                            server_id: server.id,
+                            request_id: request.id,
+                            created_at: request.created_at,
+                            assigned_at: request.assigned_at,
+                            started_at: request.started_at,
+                            outcome,
+                        })
+                        .await
+                        .ok();
+
+                    // A failed stage ends the pipeline.
+                    if outcome == RequestOutcome::Succeeded
+                        && let Some(request) = request.next_stage()
+                    {
+                        event_tx
+                            .send(SystemEvent::RequestForwarded {
+                                server_id: server.id,
+                                request,
+                            })
+                            .await
+                            .ok();
+                    }
+                }
+            }
+            retiring.retain(|server| !server.in_flight.is_empty());
+
+            for server in &mut servers {
+                if server.status == ServerStatus::Draining && server.is_idle() {
+                    server.status = ServerStatus::Drained;
+
+                    event_tx
+                        .send(SystemEvent::ServerStatusChanged {
+                            server_id: server.id,
+                            status: ServerStatus::Drained,
+                        })
+                        .await
+                        .ok();
+                }
+
+                if crashing.contains(&server.id) {
+                    continue;
+                }
+
+                for request in server.take_expired() {
+                    event_tx
+                        .send(SystemEvent::RequestExpired {
+                            request,
+                            server_id: Some(server.id),
+                        })
+                        .await
+                        .ok();
+                }
+
+                if server.is_out_of_memory() {
+                    crashing.push(server.id);
+
+                    event_tx
+                        .send(SystemEvent::ErrorEncountered(format!(
+                            "Server {} ran out of memory ({}/{}MB)",
+                            server.id,
+                            server.memory_used(),
+                            server.memory
+                        )))
+                        .await
+                        .ok();
+                    event_tx
+                        .send(SystemEvent::ServerCrashed {
+                            server_id: server.id,
+                            drop_requests: false,
+                        })
+                        .await
+                        .ok();
+                    continue;
+                }
+
+                if stalls && !server.is_stalled() && rng.random_bool(STALL_CHANCE) {
+                    let duration = rng.random_range(STALL_DURATION);
+                    server.stall(duration);
+
+                    event_tx
+                        .send(SystemEvent::ServerStalled {
+                            server_id: server.id,
+                            duration,
+                        })
+                        .await
+                        .ok();
+                }
+
+                if preemption
+                    && !server.has_free_worker()
+                    && !server.is_stalled()
+                    && let Some(request_id) = server.preemption_victim()
+                {
+                    server.preempt(request_id);
+
+                    event_tx
+                        .send(SystemEvent::RequestPreempted {
+                            request_id,
+                            server_id: server.id,
+                        })
+                        .await
+                        .ok();
+                }
+
+                while server.has_free_worker()
+                    && let Some(in_flight) = server.start_next(contention, caching)
+                {
+                    event_tx
+                        .send(SystemEvent::RequestProcessStarted {
+                            request_id: in_flight.request.id,
+                            server_id: server.id,
+                            service_time: in_flight.service_time,
+                            cache_hit: in_flight.cache_hit,
+                        })
+                        .await
+                        .ok();
+                }
+            }
+
+            ticker.tick().await;
+        }
+    })
+}
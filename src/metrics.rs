@@ -0,0 +1,71 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use crate::SystemStats;
+use crate::server::{MAX_SERVERS, ServerState};
+
+/// How often a row of metrics is appended.
+pub const METRICS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Appends a row of key metrics to a CSV file every `METRICS_INTERVAL`. The
+/// servers fill `server_<n>` and `queue_<n>` with their id and queue length in
+/// order; the columns past the last server are left empty.
+pub struct MetricsCsv {
+    file: File,
+    written_at: Instant,
+}
+
+impl MetricsCsv {
+    /// Opens the file for appending, writing the header if it is new.
+    pub fn open(path: &str) -> Result<Self, String> {
+        let error = |e: std::io::Error| format!("cannot write {path}: {e}");
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(error)?;
+
+        if file.metadata().map_err(error)?.len() == 0 {
+            let queues: Vec<String> = (1..=MAX_SERVERS)
+                .map(|n| format!("server_{n},queue_{n}"))
+                .collect();
+            writeln!(
+                file,
+                "timestamp,throughput,avg_wait_ms,{}",
+                queues.join(",")
+            )
+            .map_err(error)?;
+        }
+
+        Ok(Self {
+            file,
+            written_at: Instant::now(),
+        })
+    }
+
+    /// Appends a row if the last one is `METRICS_INTERVAL` old.
+    pub fn record(&mut self, stats: &SystemStats, servers: &[ServerState]) -> Result<(), String> {
+        if self.written_at.elapsed() < METRICS_INTERVAL {
+            return Ok(());
+        }
+        self.written_at = Instant::now();
+
+        let queues: Vec<String> = (0..MAX_SERVERS)
+            .map(|idx| match servers.get(idx) {
+                Some(server) => format!("{},{}", server.id, server.queue.len()),
+                None => ",".to_string(),
+            })
+            .collect();
+
+        writeln!(
+            self.file,
+            "{},{:.2},{:.1},{}",
+            chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%:z"),
+            stats.throughput,
+            stats.avg_wait_time,
+            queues.join(",")
+        )
+        .map_err(|e| format!("cannot write metrics: {e}"))
+    }
+}
@@ -0,0 +1,276 @@
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use rand::Rng;
+use serde_json::{Value, json};
+
+use crate::clock;
+use crate::request::Request;
+use crate::server::ServerState;
+use crate::{RequestOutcome, SystemEvent};
+
+/// How often the finished spans are sent to the collector.
+const EXPORT_INTERVAL: Duration = Duration::from_secs(1);
+/// Longest wait for the collector to accept a connection or reply.
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(2);
+const SERVICE_NAME: &str = "load-balancer";
+
+/// Exports a trace per request to an OpenTelemetry collector over OTLP/HTTP
+/// with JSON encoding. Each trace has a root `request` span with a child span
+/// per phase the request went through: `pending`, `queued` and `processing`.
+/// Requests are traced once processed, failed, rejected or expired; batches
+/// the collector doesn't take are dropped.
+pub struct OtlpExporter {
+    spans: Vec<Value>,
+    exported_at: Instant,
+    /// The same moment on both clocks, to turn instants into wall time,
+    /// taken on the first trace, when the simulation's clock is current.
+    clock: Option<(Instant, SystemTime)>,
+    batch_tx: Option<Sender<String>>,
+    sender: Option<JoinHandle<()>>,
+}
+
+impl OtlpExporter {
+    /// Sends to the collector listening for OTLP/HTTP at `host:port`.
+    pub fn connect(address: &str) -> Result<Self, String> {
+        // Each batch gets its own connection, so only the address is checked.
+        address
+            .to_socket_addrs()
+            .map_err(|e| format!("invalid collector address {address}: {e}"))?;
+
+        let address = address.to_string();
+        let (batch_tx, batch_rx) = mpsc::channel::<String>();
+        let sender = thread::spawn(move || {
+            for body in batch_rx {
+                post_traces(&address, &body).ok();
+            }
+        });
+
+        Ok(Self {
+            spans: Vec::new(),
+            exported_at: Instant::now(),
+            clock: None,
+            batch_tx: Some(batch_tx),
+            sender: Some(sender),
+        })
+    }
+
+    /// Traces the request that left the system with the event, if any. A
+    /// processed request's attributes come from the server still holding it.
+    pub fn observe(&mut self, event: &SystemEvent, servers: &[ServerState]) {
+        let now = clock::now();
+
+        match event {
+            SystemEvent::RequestProcessed {
+                request_id,
+                server_id,
+                created_at,
+                assigned_at,
+                started_at,
+                outcome,
+            } => {
+                let error = (*outcome == RequestOutcome::Failed).then_some("failed");
+                let mut attributes = vec![attribute("server.id", server_id)];
+                if let Some(in_flight) = servers
+                    .iter()
+                    .filter(|server| server.id == *server_id)
+                    .flat_map(|server| &server.in_flight)
+                    .find(|in_flight| in_flight.request.id == *request_id)
+                {
+                    attributes.extend(request_attributes(&in_flight.request));
+                }
+
+                let trace = self.trace(*request_id, *created_at, now, error);
+                self.spans.push(trace.root(attributes));
+                if let (Some(assigned_at), Some(started_at)) = (*assigned_at, *started_at) {
+                    self.spans
+                        .push(trace.child("pending", *created_at, assigned_at));
+                    self.spans
+                        .push(trace.child("queued", assigned_at, started_at));
+                    self.spans.push(trace.child("processing", started_at, now));
+                }
+            }
+            SystemEvent::RequestRejected { request } => self.push_dropped(request, now, "rejected"),
+            SystemEvent::RequestExpired { request, .. } => {
+                self.push_dropped(request, now, "expired")
+            }
+            _ => {}
+        }
+    }
+
+    /// Sends the spans gathered so far if the last batch is
+    /// `EXPORT_INTERVAL` old.
+    pub fn export(&mut self) {
+        if self.exported_at.elapsed() >= EXPORT_INTERVAL {
+            self.flush();
+        }
+    }
+
+    /// A request dropped while pending or queued: the root and the phase it
+    /// was dropped in.
+    fn push_dropped(&mut self, request: &Request, now: Instant, reason: &'static str) {
+        let trace = self.trace(request.id, request.created_at, now, Some(reason));
+        self.spans.push(trace.root(request_attributes(request)));
+        self.spans.push(match request.assigned_at {
+            Some(assigned_at) => trace.child("queued", assigned_at, now),
+            None => trace.child("pending", request.created_at, now),
+        });
+    }
+
+    fn trace(
+        &mut self,
+        request_id: usize,
+        start: Instant,
+        end: Instant,
+        error: Option<&'static str>,
+    ) -> Trace {
+        Trace {
+            trace_id: random_id(16),
+            root_id: random_id(8),
+            request_id,
+            start,
+            end,
+            error,
+            clock: *self
+                .clock
+                .get_or_insert_with(|| (clock::now(), SystemTime::now())),
+        }
+    }
+
+    fn flush(&mut self) {
+        self.exported_at = Instant::now();
+        if self.spans.is_empty() {
+            return;
+        }
+
+        let body = json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [attribute("service.name", SERVICE_NAME)],
+                },
+                "scopeSpans": [{
+                    "scope": { "name": SERVICE_NAME },
+                    "spans": std::mem::take(&mut self.spans),
+                }],
+            }],
+        });
+
+        if let Some(batch_tx) = &self.batch_tx {
+            batch_tx.send(body.to_string()).ok();
+        }
+    }
+}
+
+impl Drop for OtlpExporter {
+    /// Sends the last spans and waits for the batches to go out.
+    fn drop(&mut self) {
+        self.flush();
+        self.batch_tx.take();
+        if let Some(sender) = self.sender.take() {
+            sender.join().ok();
+        }
+    }
+}
+
+/// Ids and span of a request's trace.
+struct Trace {
+    trace_id: String,
+    root_id: String,
+    request_id: usize,
+    start: Instant,
+    end: Instant,
+    error: Option<&'static str>,
+    clock: (Instant, SystemTime),
+}
+
+impl Trace {
+    fn root(&self, mut attributes: Vec<Value>) -> Value {
+        attributes.push(attribute("request.id", self.request_id));
+
+        let mut span = self.span(&self.root_id, "request", self.start, self.end);
+        span["attributes"] = json!(attributes);
+        span["status"] = match self.error {
+            Some(error) => json!({ "code": 2, "message": error }),
+            None => json!({ "code": 1 }),
+        };
+        span
+    }
+
+    fn child(&self, name: &str, start: Instant, end: Instant) -> Value {
+        let mut span = self.span(&random_id(8), name, start, end);
+        span["parentSpanId"] = json!(self.root_id);
+        span
+    }
+
+    fn span(&self, span_id: &str, name: &str, start: Instant, end: Instant) -> Value {
+        json!({
+            "traceId": self.trace_id,
+            "spanId": span_id,
+            "name": name,
+            // Internal, as the simulated requests aren't remote calls.
+            "kind": 1,
+            "startTimeUnixNano": self.unix_nanos(start),
+            "endTimeUnixNano": self.unix_nanos(end.max(start)),
+        })
+    }
+
+    /// Wall time of the instant, as OTLP/JSON encodes it.
+    fn unix_nanos(&self, at: Instant) -> String {
+        let (instant, time) = self.clock;
+        let at = if at >= instant {
+            time + at.duration_since(instant)
+        } else {
+            time - instant.duration_since(at)
+        };
+
+        at.duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos()
+            .to_string()
+    }
+}
+
+fn request_attributes(request: &Request) -> Vec<Value> {
+    vec![
+        attribute("request.type", format!("{:?}", request.kind)),
+        attribute("request.size", format!("{:?}", request.size)),
+        attribute("request.priority", format!("{:?}", request.priority)),
+    ]
+}
+
+fn attribute(key: &str, value: impl ToString) -> Value {
+    json!({ "key": key, "value": { "stringValue": value.to_string() } })
+}
+
+/// Hex id of `bytes` random bytes, as OTLP/JSON encodes trace and span ids.
+fn random_id(bytes: usize) -> String {
+    let mut rng = rand::rng();
+    (0..bytes)
+        .map(|_| format!("{:02x}", rng.random::<u8>()))
+        .collect()
+}
+
+fn post_traces(address: &str, body: &str) -> std::io::Result<()> {
+    let socket_address = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or(std::io::ErrorKind::NotFound)?;
+
+    let mut stream = TcpStream::connect_timeout(&socket_address, EXPORT_TIMEOUT)?;
+    stream.set_read_timeout(Some(EXPORT_TIMEOUT))?;
+    stream.set_write_timeout(Some(EXPORT_TIMEOUT))?;
+
+    write!(
+        stream,
+        "POST /v1/traces HTTP/1.1\r\nHost: {address}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+
+    // Waits for the reply so the collector isn't cut off mid-request.
+    let mut reply = Vec::new();
+    stream.read_to_end(&mut reply).ok();
+    Ok(())
+}
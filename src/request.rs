@@ -1,15 +1,337 @@
-use std::time::Instant;
+use std::collections::VecDeque;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
+use crate::server::ZONES;
+use crate::tenant::TENANTS;
+use crate::{clock, context};
+
+pub const KEY_SPACE: u32 = 50;
+const KEYED_RATIO: f64 = 0.7;
+const ZONED_RATIO: f64 = 0.8;
+const DEADLINE_RATIO: f64 = 0.5;
+/// Range of deadlines (seconds after creation) of requests that have one.
+const DEADLINE_RANGE: std::ops::RangeInclusive<u64> = 3..=10;
+/// Share of generated requests with High, Normal and Low priority, unless
+/// the simulation was built with its own.
+pub const DEFAULT_PRIORITY_MIX: PriorityMix = PriorityMix([0.1, 0.7, 0.2]);
+/// Share of new requests that start a session when sessions are on.
+const SESSION_RATIO: f64 = 0.2;
+/// Number of further requests in a session.
+const SESSION_LENGTH: std::ops::RangeInclusive<u32> = 2..=6;
+/// Think time (ms) between two requests of a session.
+const SESSION_GAP: std::ops::RangeInclusive<u64> = 200..=1000;
+/// Share of new requests going through a pipeline when pipelines are on.
+pub const PIPELINE_RATIO: f64 = 0.3;
+/// Number of stages of a pipeline request, one per server tier.
+const PIPELINE_STAGES: u32 = 2;
+/// Share of new requests their client sends twice, as after a timeout.
+pub const DUPLICATE_RATIO: f64 = 0.1;
+/// Share of new requests depending on earlier ones when dependencies are on.
+pub const DEPENDENT_RATIO: f64 = 0.2;
+/// Most requests a request can depend on.
+pub const MAX_DEPENDENCIES: usize = 2;
+/// Share of new requests split into sub-requests when fork-join is on.
+pub const FORK_RATIO: f64 = 0.2;
+/// Number of sub-requests of a forked request.
+pub const FORK_WIDTH: std::ops::RangeInclusive<u32> = 2..=4;
+/// Tags generated requests carry by default, with their relative weights.
+const DEFAULT_TAG_MIX: [(&str, f64); 4] = [
+    ("search", 5.0),
+    ("browse", 3.0),
+    ("checkout", 1.5),
+    ("login", 0.5),
+];
+/// Cap on sampled service times (ms), so a heavy tail can't stall a server
+/// for the rest of the run.
+const MAX_SERVICE_TIME: f64 = 60_000.0;
+
+/// How `Request::create_random` picks service times.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ServiceTimeDistribution {
+    /// The fixed time of a random type and size.
+    #[default]
+    Fixed,
+    Exponential {
+        mean: f64,
+    },
+    Pareto {
+        scale: f64,
+        shape: f64,
+    },
+    /// Parameters of the underlying normal distribution of ln(ms).
+    LogNormal {
+        mu: f64,
+        sigma: f64,
+    },
+}
+
+impl ServiceTimeDistribution {
+    /// Service time (ms), or `None` for the fixed sizes.
+    fn sample(&self, rng: &mut impl Rng) -> Option<u64> {
+        // 1 - U lies in (0, 1], so its logarithm is finite.
+        let uniform = 1.0 - rng.random::<f64>();
+
+        let time = match *self {
+            Self::Fixed => return None,
+            Self::Exponential { mean } => -mean * uniform.ln(),
+            Self::Pareto { scale, shape } => scale / uniform.powf(1.0 / shape),
+            Self::LogNormal { mu, sigma } => {
+                // Box-Muller transform for a standard normal sample.
+                let normal = (-2.0 * uniform.ln()).sqrt()
+                    * (2.0 * std::f64::consts::PI * rng.random::<f64>()).cos();
+                (mu + sigma * normal).exp()
+            }
+        };
+
+        Some(time.clamp(1.0, MAX_SERVICE_TIME) as u64)
+    }
+}
+
+/// Weighted tags the generator picks from for each request.
+#[derive(Debug, Clone)]
+pub struct TagMix(Vec<(&'static str, f64)>);
+
+impl TagMix {
+    pub fn sample(&self, rng: &mut impl Rng) -> Option<&'static str> {
+        let total: f64 = self.0.iter().map(|(_, weight)| weight).sum();
+        let mut roll = rng.random::<f64>() * total;
+
+        for (tag, weight) in &self.0 {
+            if roll < *weight {
+                return Some(tag);
+            }
+            roll -= weight;
+        }
+        self.0.last().map(|(tag, _)| *tag)
+    }
+}
+
+impl Default for TagMix {
+    fn default() -> Self {
+        Self(DEFAULT_TAG_MIX.to_vec())
+    }
+}
+
+impl FromStr for TagMix {
+    type Err = String;
+
+    /// Parses `name:weight` pairs separated by commas, e.g.
+    /// `checkout:1,search:4`; `none` leaves requests untagged.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "none" {
+            return Ok(Self(Vec::new()));
+        }
+
+        let tags = s
+            .split(',')
+            .map(|pair| {
+                let (name, weight) = pair
+                    .trim()
+                    .split_once(':')
+                    .ok_or_else(|| format!("invalid tag '{pair}', expected name:weight"))?;
+                let weight: f64 = weight
+                    .parse()
+                    .ok()
+                    .filter(|weight: &f64| *weight > 0.0)
+                    .ok_or_else(|| format!("invalid weight '{weight}' of tag '{name}'"))?;
+                if name.is_empty() {
+                    return Err(format!("missing name in tag '{pair}'"));
+                }
+
+                // Parsed once at startup, so tags can be copied around with requests.
+                let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+                Ok((name, weight))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self(tags))
+    }
+}
+
+/// Shares of the generated requests with High, Normal and Low priority,
+/// which add up to 1.
 #[derive(Debug, Clone, Copy)]
+pub struct PriorityMix([f64; 3]);
+
+impl PriorityMix {
+    pub fn new(high: f64, normal: f64, low: f64) -> Result<Self, String> {
+        let shares = [high, normal, low];
+        if !shares.iter().all(|share| *share >= 0.0)
+            || (shares.iter().sum::<f64>() - 1.0).abs() > 1e-9
+        {
+            return Err(format!(
+                "invalid priority shares {high}, {normal} and {low}, expected \
+                 non-negative shares adding up to 1"
+            ));
+        }
+        Ok(Self(shares))
+    }
+
+    pub fn sample(&self, rng: &mut impl Rng) -> Priority {
+        let [high, normal, _] = self.0;
+        let roll = rng.random::<f64>();
+        if roll < high {
+            Priority::High
+        } else if roll < high + normal {
+            Priority::Normal
+        } else {
+            Priority::Low
+        }
+    }
+}
+
+impl Default for PriorityMix {
+    fn default() -> Self {
+        DEFAULT_PRIORITY_MIX
+    }
+}
+
+impl FromStr for PriorityMix {
+    type Err = String;
+
+    /// Parses the High, Normal and Low shares separated by commas, e.g.
+    /// `0.1,0.7,0.2`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let shares: Vec<f64> = s
+            .split(',')
+            .map(|share| share.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("invalid priorities '{s}', expected high,normal,low"))?;
+
+        match shares[..] {
+            [high, normal, low] => Self::new(high, normal, low),
+            _ => Err(format!(
+                "invalid priorities '{s}', expected high,normal,low"
+            )),
+        }
+    }
+}
+
+impl FromStr for ServiceTimeDistribution {
+    type Err = String;
+
+    /// Parses `fixed`, `exp:<mean>`, `pareto:<scale>:<shape>` or
+    /// `lognormal:<mu>:<sigma>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':');
+        let name = parts.next().unwrap_or_default();
+        let params: Vec<f64> = parts
+            .map(|param| {
+                param
+                    .parse()
+                    .map_err(|_| format!("invalid parameter '{param}' in '{s}'"))
+            })
+            .collect::<Result<_, _>>()?;
+
+        let distribution = match (name, params.as_slice()) {
+            ("fixed", []) => Self::Fixed,
+            ("exp", &[mean]) if mean > 0.0 => Self::Exponential { mean },
+            ("pareto", &[scale, shape]) if scale > 0.0 && shape > 0.0 => {
+                Self::Pareto { scale, shape }
+            }
+            ("lognormal", &[mu, sigma]) if sigma >= 0.0 => Self::LogNormal { mu, sigma },
+            _ => {
+                return Err(format!(
+                    "invalid distribution '{s}', expected fixed, exp:<mean>, \
+                     pareto:<scale>:<shape> or lognormal:<mu>:<sigma>"
+                ));
+            }
+        };
+
+        Ok(distribution)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Attempts in total, including the first one.
+    pub max_attempts: u32,
+    /// Wait before the first retry; each later retry waits `multiplier` times
+    /// longer than the previous one.
+    pub initial_backoff: Duration,
+    pub multiplier: u32,
+}
+
+pub const DEFAULT_RETRY_POLICY: RetryPolicy = RetryPolicy {
+    max_attempts: 3,
+    initial_backoff: Duration::from_millis(500),
+    multiplier: 2,
+};
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        DEFAULT_RETRY_POLICY
+    }
+}
+
+impl FromStr for RetryPolicy {
+    type Err = String;
+
+    /// Parses `<attempts>:<backoff_ms>:<multiplier>`, e.g. `3:500:2`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            || format!("invalid retry policy '{s}', expected <attempts>:<backoff_ms>:<multiplier>");
+        let parts: Vec<u32> = s
+            .split(':')
+            .map(|part| part.parse().map_err(|_| invalid()))
+            .collect::<Result<_, _>>()?;
+
+        match parts[..] {
+            [max_attempts, backoff, multiplier] if max_attempts > 0 && multiplier > 0 => Ok(Self {
+                max_attempts,
+                initial_backoff: Duration::from_millis(backoff.into()),
+                multiplier,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before retrying a request whose `attempt` failed, if it has
+    /// attempts left.
+    pub fn backoff(&self, attempt: u32) -> Option<Duration> {
+        (attempt < self.max_attempts).then(|| {
+            self.initial_backoff
+                .saturating_mul(self.multiplier.saturating_pow(attempt - 1))
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum RequestSize {
     Small,
     Mid,
     Large,
 }
 
+pub const REQ_SIZES: [RequestSize; 3] = [RequestSize::Small, RequestSize::Mid, RequestSize::Large];
+
+impl FromStr for RequestSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        REQ_SIZES
+            .into_iter()
+            .find(|size| format!("{:?}", size).eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown request size '{s}'"))
+    }
+}
+
 impl RequestSize {
+    /// Size class of a sampled service time (ms).
+    fn for_time(time: u64) -> Self {
+        match time {
+            0..200 => RequestSize::Small,
+            200..600 => RequestSize::Mid,
+            _ => RequestSize::Large,
+        }
+    }
+
     fn mult_factor(&self) -> u64 {
         match self {
             RequestSize::Small => 1,
@@ -17,15 +339,50 @@ impl RequestSize {
             RequestSize::Large => 10,
         }
     }
+
+    /// Typical memory footprint (MB).
+    fn memory(&self) -> u64 {
+        match self {
+            RequestSize::Small => 64,
+            RequestSize::Mid => 256,
+            RequestSize::Large => 1024,
+        }
+    }
+
+    /// Typical payload (KB) sent to the server along with the request.
+    fn payload(&self) -> u64 {
+        match self {
+            RequestSize::Small => 50,
+            RequestSize::Mid => 500,
+            RequestSize::Large => 5000,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum RequestType {
     CPUsBound,
     IOBound,
     Mixed,
 }
 
+pub const REQ_TYPES: [RequestType; 3] = [
+    RequestType::CPUsBound,
+    RequestType::IOBound,
+    RequestType::Mixed,
+];
+
+impl FromStr for RequestType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        REQ_TYPES
+            .into_iter()
+            .find(|kind| format!("{:?}", kind).eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown request type '{s}'"))
+    }
+}
+
 impl RequestType {
     fn cpu_time(&self) -> u64 {
         match self {
@@ -43,40 +400,389 @@ impl RequestType {
         }
     }
 }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    High,
+    Normal,
+    Low,
+}
+
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [Priority::High, Priority::Normal, Priority::Low]
+            .into_iter()
+            .find(|priority| format!("{:?}", priority).eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown priority '{s}'"))
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct Request {
     pub id: usize,
+    /// Shared by the duplicates of a request, which only need to run once.
+    pub idempotency_key: usize,
     pub kind: RequestType,
     pub size: RequestSize,
+    /// Nominal service time (ms), split between CPU and IO by the type.
+    pub time: u64,
+    pub key: Option<u32>,
+    pub priority: Priority,
+    pub zone: Option<char>,
+    /// Requests of the same session should be served by the same server.
+    pub session_id: Option<u64>,
+    /// Free-form label, e.g. the endpoint the request is for.
+    pub tag: Option<&'static str>,
+    /// Index in `TENANTS` of the tenant the request comes from.
+    pub tenant: usize,
+    /// Set on the sub-requests of a forked request.
+    pub fork: Option<Fork>,
+    /// Requests that must complete before this one is assigned.
+    pub depends_on: [Option<usize>; MAX_DEPENDENCIES],
+    /// Set on requests going through a pipeline of server tiers.
+    pub stage: Option<Stage>,
+    /// Memory (MB) the request holds on a server until it is processed.
+    pub memory: u64,
+    /// Data (KB) sent to the server before it can be processed.
+    pub payload: u64,
+    /// Dropped if still waiting past this.
+    pub deadline: Option<Instant>,
+    /// 1 for the first attempt, incremented by each retry.
+    pub attempt: u32,
+    /// When its payload reaches the server it was assigned to, if the network
+    /// is modeled; the server can't start it before.
+    pub arrives_at: Option<Instant>,
+    /// Whether the allocator assigned it as part of a batch.
+    pub batched: bool,
     pub created_at: Instant,
+    /// When it was last assigned to a server and started there; pipeline
+    /// requests get both again at every stage.
+    pub assigned_at: Option<Instant>,
+    pub started_at: Option<Instant>,
 }
 
 impl Request {
     pub fn get_time(&self) -> u64 {
-        let total_time = self.kind.cpu_time() + self.kind.io_time();
-        total_time * self.size.mult_factor()
+        self.time
+    }
+
+    /// Nominal service time (ms) over all its stages.
+    pub fn total_time(&self) -> u64 {
+        self.time * self.stage.map_or(1, |stage| stage.stages as u64)
+    }
+
+    pub fn cpu_time(&self) -> u64 {
+        self.time * self.kind.cpu_time() / (self.kind.cpu_time() + self.kind.io_time())
+    }
+
+    pub fn io_time(&self) -> u64 {
+        self.time - self.cpu_time()
     }
 
     pub fn get_name(&self) -> String {
         format!("{:?} {:?}", self.size, self.kind)
     }
 
-    pub fn create_random() -> Self {
-        let mut rng = rand::rng();
+    /// Short suffix grouping the parts of a fork by the last digits of its
+    /// parent's id, e.g. ` ⑂337 1/3`.
+    pub fn get_fork_label(&self) -> String {
+        match self.fork {
+            Some(fork) => format!(" ⑂{:03} {}/{}", fork.parent % 1000, fork.part, fork.parts),
+            None => String::new(),
+        }
+    }
+
+    /// Short suffix with the optional fork, original of a duplicate, stage,
+    /// key, session, zone, time left to the deadline, attempt and tag, e.g.
+    /// ` ≡337 ▸1/2 k12 s3 @A 4s ↻2 [search]`.
+    pub fn get_attributes(&self) -> String {
+        let mut attributes = self.get_fork_label();
+        if self.idempotency_key != self.id {
+            attributes.push_str(&format!(" ≡{:03}", self.idempotency_key % 1000));
+        }
+        if let Some(stage) = self.stage {
+            attributes.push_str(&format!(" ▸{}/{}", stage.stage, stage.stages));
+        }
+        if let Some(key) = self.key {
+            attributes.push_str(&format!(" k{key}"));
+        }
+        if let Some(session_id) = self.session_id {
+            attributes.push_str(&format!(" s{session_id}"));
+        }
+        if let Some(zone) = self.zone {
+            attributes.push_str(&format!(" @{zone}"));
+        }
+        if let Some(deadline) = self.deadline {
+            let left = deadline.saturating_duration_since(clock::now());
+            attributes.push_str(&format!(" {}s", left.as_secs()));
+        }
+        if self.attempt > 1 {
+            attributes.push_str(&format!(" ↻{}", self.attempt));
+        }
+        if let Some(tag) = self.tag {
+            attributes.push_str(&format!(" [{tag}]"));
+        }
+        attributes
+    }
+
+    /// Splits the request into sub-requests sharing its work, to be processed
+    /// in parallel on different servers.
+    pub fn fork(&self, rng: &mut impl Rng) -> Vec<Request> {
+        let parts = rng.random_range(FORK_WIDTH);
+        let time = (self.time / parts as u64).max(1);
+
+        (1..=parts)
+            .map(|part| {
+                let id = rng.random_range(1000000..10000000);
+                Request {
+                    id,
+                    idempotency_key: id,
+                    size: RequestSize::for_time(time),
+                    time,
+                    memory: self.memory / parts as u64,
+                    fork: Some(Fork {
+                        parent: self.id,
+                        part,
+                        parts,
+                    }),
+                    ..*self
+                }
+            })
+            .collect()
+    }
+
+    /// The request as the first stage of a pipeline, its work split evenly
+    /// between the stages.
+    pub fn pipeline(&self) -> Self {
+        let time = (self.time / PIPELINE_STAGES as u64).max(1);
 
-        const REQ_TYPES: [RequestType; 3] = [
-            RequestType::CPUsBound,
-            RequestType::IOBound,
-            RequestType::Mixed,
-        ];
-        const REQ_SIZES: [RequestSize; 3] =
-            [RequestSize::Small, RequestSize::Mid, RequestSize::Large];
+        Self {
+            size: RequestSize::for_time(time),
+            time,
+            stage: Some(Stage {
+                stage: 1,
+                stages: PIPELINE_STAGES,
+            }),
+            ..*self
+        }
+    }
+
+    /// The request at its next pipeline stage, if it has one left.
+    pub fn next_stage(&self) -> Option<Self> {
+        let stage = self.stage.filter(|stage| stage.stage < stage.stages)?;
+
+        Some(Self {
+            stage: Some(Stage {
+                stage: stage.stage + 1,
+                ..stage
+            }),
+            ..*self
+        })
+    }
 
+    /// The request sent again by its client under a new id, sharing its
+    /// idempotency key.
+    pub fn duplicate(&self, rng: &mut impl Rng) -> Self {
         Self {
             id: rng.random_range(1000000..10000000),
-            kind: REQ_TYPES[rng.random_range(0..REQ_TYPES.len())],
-            size: REQ_SIZES[rng.random_range(0..REQ_SIZES.len())],
-            created_at: Instant::now(),
+            created_at: clock::now(),
+            ..*self
+        }
+    }
+
+    /// The next attempt at this request, with a fresh deadline of the same
+    /// length.
+    pub fn retry(&self) -> Self {
+        let now = clock::now();
+
+        Self {
+            attempt: self.attempt + 1,
+            deadline: self
+                .deadline
+                .map(|deadline| now + deadline.duration_since(self.created_at)),
+            created_at: now,
+            ..*self
+        }
+    }
+
+    pub fn dependencies(&self) -> impl Iterator<Item = usize> + '_ {
+        self.depends_on.iter().flatten().copied()
+    }
+
+    pub fn has_arrived(&self) -> bool {
+        self.arrives_at.is_none_or(|at| at <= clock::now())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| deadline <= clock::now())
+    }
+
+    pub fn create_random(service_times: ServiceTimeDistribution, rng: &mut impl Rng) -> Self {
+        let id = rng.random_range(1000000..10000000);
+        let kind = REQ_TYPES[rng.random_range(0..REQ_TYPES.len())];
+        let (size, time) = match service_times.sample(rng) {
+            Some(time) => (RequestSize::for_time(time), time),
+            None => {
+                let size = REQ_SIZES[rng.random_range(0..REQ_SIZES.len())];
+                (size, fixed_time(kind, size))
+            }
+        };
+
+        Self::with_random_attributes(rng, id, kind, size, time)
+    }
+
+    /// A request of the given type and size with random attributes. Without a
+    /// service time, the fixed one of the type and size is used.
+    pub fn create(kind: RequestType, size: RequestSize, time: Option<u64>) -> Self {
+        let mut rng = rand::rng();
+
+        let id = rng.random_range(1000000..10000000);
+        let time = time.unwrap_or_else(|| fixed_time(kind, size));
+
+        Self::with_random_attributes(&mut rng, id, kind, size, time)
+    }
+
+    /// A request replayed from a trace; the attributes a trace doesn't record
+    /// are derived from the id, so every replay gets the same ones. Without a
+    /// recorded service time, the fixed one of the type and size is used.
+    pub fn from_trace(id: usize, kind: RequestType, size: RequestSize, time: Option<u64>) -> Self {
+        let mut rng = StdRng::seed_from_u64(id as u64);
+        let time = time.unwrap_or_else(|| fixed_time(kind, size));
+
+        Self::with_random_attributes(&mut rng, id, kind, size, time)
+    }
+
+    fn with_random_attributes(
+        rng: &mut impl Rng,
+        id: usize,
+        kind: RequestType,
+        size: RequestSize,
+        time: u64,
+    ) -> Self {
+        let created_at = clock::now();
+
+        Self {
+            id,
+            idempotency_key: id,
+            kind,
+            size,
+            time,
+            key: rng
+                .random_bool(KEYED_RATIO)
+                .then(|| rng.random_range(0..KEY_SPACE)),
+            priority: random_priority(rng),
+            zone: rng
+                .random_bool(ZONED_RATIO)
+                .then(|| ZONES[rng.random_range(0..ZONES.len())]),
+            session_id: None,
+            tag: None,
+            fork: None,
+            depends_on: [None; MAX_DEPENDENCIES],
+            stage: None,
+            memory: (size.memory() as f64 * rng.random_range(0.5..1.5)) as u64,
+            deadline: rng
+                .random_bool(DEADLINE_RATIO)
+                .then(|| created_at + Duration::from_secs(rng.random_range(DEADLINE_RANGE))),
+            payload: (size.payload() as f64 * rng.random_range(0.5..1.5)) as u64,
+            tenant: random_tenant(rng),
+            arrives_at: None,
+            attempt: 1,
+            batched: false,
+            created_at,
+            assigned_at: None,
+            started_at: None,
+        }
+    }
+}
+
+/// Sub-request `part` of `parts` of a forked request, which only completes
+/// when every part is processed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fork {
+    pub parent: usize,
+    pub part: u32,
+    pub parts: u32,
+}
+
+/// Stage `stage` of `stages` of a pipeline request, which runs on the server
+/// tier of the same number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stage {
+    pub stage: u32,
+    pub stages: u32,
+}
+
+/// Requests of one user arriving one after another, which should be served by
+/// the same server.
+pub struct Session {
+    id: u64,
+    left: u32,
+    next_at: Instant,
+}
+
+impl Session {
+    /// Starts a session for a new request, with `SESSION_RATIO` chance.
+    pub fn start(request: &mut Request, id: u64, rng: &mut impl Rng) -> Option<Self> {
+        if !rng.random_bool(SESSION_RATIO) {
+            return None;
+        }
+
+        request.session_id = Some(id);
+        Some(Self {
+            id,
+            left: rng.random_range(SESSION_LENGTH),
+            next_at: clock::now() + Duration::from_millis(rng.random_range(SESSION_GAP)),
+        })
+    }
+
+    /// The session's next request, once its think time has passed.
+    pub fn next_request(
+        &mut self,
+        service_times: ServiceTimeDistribution,
+        rng: &mut impl Rng,
+    ) -> Option<Request> {
+        if self.is_over() || self.next_at > clock::now() {
+            return None;
+        }
+
+        self.left -= 1;
+        self.next_at = clock::now() + Duration::from_millis(rng.random_range(SESSION_GAP));
+
+        Some(Request {
+            session_id: Some(self.id),
+            ..Request::create_random(service_times, rng)
+        })
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.left == 0
+    }
+}
+
+fn fixed_time(kind: RequestType, size: RequestSize) -> u64 {
+    (kind.cpu_time() + kind.io_time()) * size.mult_factor()
+}
+
+fn random_priority(rng: &mut impl Rng) -> Priority {
+    context::with(|context| context.priorities).sample(rng)
+}
+
+fn random_tenant(rng: &mut impl Rng) -> usize {
+    let mut roll = rng.random::<f64>();
+    for (idx, tenant) in TENANTS.iter().enumerate() {
+        if roll < tenant.traffic {
+            return idx;
         }
+        roll -= tenant.traffic;
     }
+    TENANTS.len() - 1
+}
+
+/// Inserts the request behind every request of equal or higher priority, so
+/// higher priorities jump the queue while equal priorities stay FIFO.
+pub fn insert_by_priority(queue: &mut VecDeque<Request>, request: Request) {
+    let position = queue.partition_point(|queued| queued.priority <= request.priority);
+    queue.insert(position, request);
 }
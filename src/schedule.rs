@@ -0,0 +1,124 @@
+use std::fs;
+use std::time::Duration;
+
+/// Arrival rates at given offsets from the start of the run, interpolated
+/// linearly and repeated once the last point is reached.
+pub struct RateSchedule {
+    points: Vec<(Duration, f32)>,
+}
+
+impl RateSchedule {
+    /// Parses `offset:rate` points (seconds and requests/second) separated by
+    /// commas or new lines, e.g. `0:1, 60:8, 120:1`. Lines starting with `#`
+    /// are ignored.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut points = Vec::new();
+
+        for entry in spec
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .flat_map(|line| line.split(','))
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            let (offset, rate) = entry
+                .split_once(':')
+                .ok_or_else(|| format!("invalid schedule point '{entry}', expected offset:rate"))?;
+            let offset: f64 = offset
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid offset in schedule point '{entry}'"))?;
+            let rate: f32 = rate
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid rate in schedule point '{entry}'"))?;
+
+            if offset < 0.0 || !(0.0..=10.0).contains(&rate) {
+                return Err(format!(
+                    "schedule point '{entry}' out of range (offset >= 0, rate 0-10)"
+                ));
+            }
+            points.push((Duration::from_secs_f64(offset), rate));
+        }
+
+        if points.is_empty() {
+            return Err("empty rate schedule".to_string());
+        }
+        points.sort_by_key(|(offset, _)| *offset);
+
+        Ok(Self { points })
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let spec = fs::read_to_string(path).map_err(|e| format!("cannot read {path}: {e}"))?;
+        Self::parse(&spec)
+    }
+
+    pub fn rate_at(&self, elapsed: Duration) -> f32 {
+        let period = self.points[self.points.len() - 1].0;
+        let elapsed = if period.is_zero() {
+            elapsed
+        } else {
+            Duration::from_secs_f64(elapsed.as_secs_f64() % period.as_secs_f64())
+        };
+
+        let next = self
+            .points
+            .partition_point(|(offset, _)| *offset <= elapsed);
+        if next == 0 {
+            return self.points[0].1;
+        }
+
+        let (start, from) = self.points[next - 1];
+        match self.points.get(next) {
+            Some(&(end, to)) => {
+                let progress = (elapsed - start).as_secs_f32() / (end - start).as_secs_f32();
+                from + (to - from) * progress
+            }
+            None => from,
+        }
+    }
+}
+
+/// Alternating quiet periods and traffic spikes of the generator's burst mode.
+#[derive(Debug, Clone)]
+pub struct BurstPattern {
+    pub quiet: Duration,
+    pub spike: Duration,
+    /// Arrival rate multiplier during a spike.
+    pub multiplier: f32,
+}
+
+pub const DEFAULT_BURST: BurstPattern = BurstPattern {
+    quiet: Duration::from_secs(15),
+    spike: Duration::from_secs(5),
+    multiplier: 10.0,
+};
+
+impl Default for BurstPattern {
+    fn default() -> Self {
+        DEFAULT_BURST
+    }
+}
+
+impl BurstPattern {
+    /// Parses `quiet:spike:multiplier`, the periods in seconds, e.g. `15:5:10`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid burst pattern '{spec}', expected quiet:spike:multiplier");
+        let parts: Vec<f32> = spec
+            .split(':')
+            .map(|part| part.trim().parse().map_err(|_| invalid()))
+            .collect::<Result<_, _>>()?;
+
+        match parts[..] {
+            [quiet, spike, multiplier] if quiet > 0.0 && spike > 0.0 && multiplier > 0.0 => {
+                Ok(Self {
+                    quiet: Duration::from_secs_f32(quiet),
+                    spike: Duration::from_secs_f32(spike),
+                    multiplier,
+                })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
@@ -1,30 +1,747 @@
 use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
 
-use crate::request::Request;
+use crate::health::BreakerState;
+use crate::request::{Request, RequestType};
+use crate::window::SlidingWindow;
+use crate::{clock, context};
+
+pub const INITIAL_SERVERS: u64 = 3;
+pub const MAX_SERVERS: usize = 8;
+/// Requests every server's queue holds until set otherwise from the Config
+/// view.
+pub const DEFAULT_QUEUE_CAPACITY: usize = 10;
+/// Most requests a server's queue can be set to hold.
+pub const MAX_QUEUE_CAPACITY: usize = 50;
+pub const ZONES: [char; 2] = ['A', 'B'];
+/// Processing cost ($/ms) of a busy worker on servers 1, 2 and 3; others use
+/// the last value.
+const SERVER_COSTS: [f64; 3] = [0.001, 0.002, 0.004];
+/// Share of the busy cost an idle worker still costs.
+const IDLE_COST_RATIO: f64 = 0.2;
+/// Requests servers 1, 2 and 3 process concurrently; others get one worker.
+const SERVER_WORKERS: [usize; 3] = [1, 1, 2];
+/// Hardware profiles of servers 1, 2 and 3; others are balanced.
+const SERVER_PROFILES: [ServerProfile; 3] = [
+    ServerProfile::CpuOptimized,
+    ServerProfile::IoOptimized,
+    ServerProfile::Balanced,
+];
+/// Pipeline tier of servers 1, 2 and 3, repeating for later servers; stage
+/// `n` of a pipeline request runs on tier `n`.
+const SERVER_TIERS: [u32; 3] = [1, 1, 2];
+/// Memory capacity (MB) of servers 1, 2 and 3; others use the last value.
+const SERVER_MEMORY: [u64; 3] = [3072, 4096, 6144];
+/// Service time slowdown of servers 1, 2 and 3 per request waiting in their
+/// queue when contention is enabled, unless the simulation was built with
+/// its own; others use the last value.
+pub const DEFAULT_CONTENTION: [f64; 3] = [0.1, 0.1, 0.05];
+/// One-way network latency (ms) from the balancer to servers 1, 2 and 3;
+/// others use the last value.
+const SERVER_LATENCY: [u64; 3] = [2, 5, 10];
+/// Network bandwidth (KB/ms) from the balancer to servers 1, 2 and 3; others
+/// use the last value.
+const SERVER_BANDWIDTH: [u64; 3] = [100, 50, 20];
+/// Extra latency (ms) of sending a request to a server in another zone.
+const CROSS_ZONE_LATENCY: u64 = 40;
+/// Rate limits (requests/second) a server cycles through from the UI.
+const RATE_LIMITS: [Option<u32>; 4] = [None, Some(1), Some(2), Some(5)];
+/// Failure rates a server cycles through from the UI.
+const FAILURE_RATES: [f64; 4] = [0.0, 0.05, 0.2, 0.5];
+/// Worker counts a server cycles through from the UI.
+const WORKER_COUNTS: [usize; 4] = [1, 2, 4, 8];
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+/// A server added at runtime starts this many times slower...
+const COLD_START_PENALTY: f64 = 3.0;
+/// ...and speeds up linearly to normal over this window.
+const WARMUP: Duration = Duration::from_secs(10);
+/// Keys a server keeps warm when the cache is modeled, least recently used
+/// first out.
+const CACHE_SIZE: usize = 8;
+/// Share of its service time a request takes when its key is warm.
+const CACHE_HIT_FACTOR: f64 = 0.4;
+/// With preemption on, a queued request preempts an in-flight one that has at
+/// least this many times its service time left.
+const PREEMPTION_FACTOR: u64 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServerStatus {
+    Active,
+    /// Finishes its queue but receives no new assignments.
+    Draining,
+    Drained,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServerProfile {
+    CpuOptimized,
+    IoOptimized,
+    Balanced,
+}
+
+impl ServerProfile {
+    /// Multipliers applied to a request's CPU and IO time.
+    fn factors(self) -> (f64, f64) {
+        match self {
+            Self::CpuOptimized => (0.5, 1.5),
+            Self::IoOptimized => (1.5, 0.5),
+            Self::Balanced => (1.0, 1.0),
+        }
+    }
+
+    /// Profile that processes requests of this type fastest.
+    pub fn suited_to(kind: RequestType) -> Self {
+        match kind {
+            RequestType::CPUsBound => Self::CpuOptimized,
+            RequestType::IOBound => Self::IoOptimized,
+            RequestType::Mixed => Self::Balanced,
+        }
+    }
+
+    pub fn scale(self, request: &Request) -> f64 {
+        let (cpu_factor, io_factor) = self.factors();
+        request.cpu_time() as f64 * cpu_factor + request.io_time() as f64 * io_factor
+    }
+}
+
+impl fmt::Display for ServerProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CpuOptimized => write!(f, "CPU"),
+            Self::IoOptimized => write!(f, "IO"),
+            Self::Balanced => write!(f, "Balanced"),
+        }
+    }
+}
+
+/// Order in which a server picks the next queued request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueDiscipline {
+    Fifo,
+    Lifo,
+    Priority,
+    ShortestJobFirst,
+}
+
+impl QueueDiscipline {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Fifo => Self::Lifo,
+            Self::Lifo => Self::Priority,
+            Self::Priority => Self::ShortestJobFirst,
+            Self::ShortestJobFirst => Self::Fifo,
+        }
+    }
+
+    /// Index of the request to start next among those whose payload arrived,
+    /// ties going to the oldest one.
+    fn pick(self, queue: &VecDeque<Request>) -> Option<usize> {
+        let mut entries = queue.iter().enumerate().filter(|(_, r)| r.has_arrived());
+
+        match self {
+            Self::Fifo => entries.next().map(|(idx, _)| idx),
+            Self::Lifo => entries.next_back().map(|(idx, _)| idx),
+            Self::Priority => entries.min_by_key(|(_, r)| r.priority).map(|(idx, _)| idx),
+            Self::ShortestJobFirst => entries
+                .min_by_key(|(_, r)| r.get_time())
+                .map(|(idx, _)| idx),
+        }
+    }
+}
+
+impl fmt::Display for QueueDiscipline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fifo => write!(f, "FIFO"),
+            Self::Lifo => write!(f, "LIFO"),
+            Self::Priority => write!(f, "Priority"),
+            Self::ShortestJobFirst => write!(f, "SJF"),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct InFlight {
+    pub request: Request,
+    pub started_at: Instant,
+    /// Actual processing time (ms), which may exceed the request's nominal time.
+    pub service_time: u64,
+    /// Processing time (ms) still needed when it was last started or paused.
+    pub left: u64,
+    /// Whether its key was warm on the server, for keyed requests when the
+    /// cache is modeled.
+    pub cache_hit: Option<bool>,
+}
+
+impl InFlight {
+    pub fn remaining_time(&self) -> u64 {
+        self.left
+            .saturating_sub(clock::since(self.started_at).as_millis() as u64)
+    }
+}
 
 pub struct ServerState {
     pub id: u64,
+    pub zone: Option<char>,
+    pub cost_per_ms: f64,
+    /// Running cost of the busy and idle time of the server's workers.
+    pub cost: f64,
+    cost_updated_at: Instant,
+    /// Time (ms) the server's workers spent processing, averaged over them.
+    busy_time: f64,
+    /// Busy time at each accrual over the shared window.
+    busy_history: SlidingWindow<f64>,
+    created_at: Instant,
+    pub status: ServerStatus,
+    pub breaker: BreakerState,
+    pub workers: usize,
+    /// Memory capacity (MB); the server crashes when its requests need more.
+    pub memory: u64,
+    pub profile: ServerProfile,
+    pub tier: u32,
+    /// Network latency (ms) and bandwidth (KB/ms) from the balancer.
+    pub latency: u64,
+    pub bandwidth: u64,
+    pub contention: f64,
+    pub discipline: QueueDiscipline,
+    /// Maximum requests accepted per second, if any.
+    pub rate_limit: Option<u32>,
+    /// Chance of each processed request failing.
+    pub failure_rate: f64,
+    /// When the requests of the last second were assigned.
+    accepted: VecDeque<Instant>,
+    /// Requests waiting for a free worker.
     pub queue: VecDeque<Request>,
+    pub in_flight: Vec<InFlight>,
+    /// Requests paused by a shorter one, waiting to be resumed.
+    pub preempted: Vec<InFlight>,
+    /// Nominal service time of every queued, in-flight and preempted request.
     pub total_workload: u64,
-    pub is_processing: bool,
+    /// Starts no new requests until then, like during a GC pause.
+    pub stalled_until: Option<Instant>,
+    pub crashed_at: Option<Instant>,
+    /// Keeps its queue but processes nothing until resumed.
+    pub paused: bool,
+    /// Set for servers added at runtime, which start cold.
+    pub added_at: Option<Instant>,
+    /// Keys of the latest requests started, most recent last.
+    cache: VecDeque<u32>,
+    pub cache_hits: u64,
+    pub cache_lookups: u64,
 }
 
 impl ServerState {
     pub fn new(id: u64) -> Self {
         Self {
             id,
-            queue: VecDeque::with_capacity(10),
+            zone: Some(ZONES[(id as usize - 1) % ZONES.len()]),
+            cost_per_ms: cost_per_ms(id),
+            cost: 0.0,
+            cost_updated_at: clock::now(),
+            busy_time: 0.0,
+            busy_history: SlidingWindow::default(),
+            created_at: clock::now(),
+            status: ServerStatus::Active,
+            breaker: BreakerState::Closed,
+            workers: SERVER_WORKERS.get(id as usize - 1).copied().unwrap_or(1),
+            memory: SERVER_MEMORY[(id as usize - 1).min(SERVER_MEMORY.len() - 1)],
+            profile: SERVER_PROFILES
+                .get(id as usize - 1)
+                .copied()
+                .unwrap_or(ServerProfile::Balanced),
+            tier: SERVER_TIERS[(id as usize - 1) % SERVER_TIERS.len()],
+            latency: SERVER_LATENCY[(id as usize - 1).min(SERVER_LATENCY.len() - 1)],
+            bandwidth: SERVER_BANDWIDTH[(id as usize - 1).min(SERVER_BANDWIDTH.len() - 1)],
+            queue: VecDeque::with_capacity(queue_capacity()),
+            contention: contention_of(id),
+            discipline: QueueDiscipline::Fifo,
+            rate_limit: None,
+            failure_rate: 0.0,
+            accepted: VecDeque::new(),
+            in_flight: Vec::new(),
+            preempted: Vec::new(),
             total_workload: 0,
-            is_processing: false,
+            stalled_until: None,
+            crashed_at: None,
+            paused: false,
+            added_at: None,
+            cache: VecDeque::with_capacity(CACHE_SIZE),
+            cache_hits: 0,
+            cache_lookups: 0,
+        }
+    }
+
+    /// A server joining at runtime, which is slow until it warms up.
+    pub fn added(id: u64) -> Self {
+        Self {
+            added_at: Some(clock::now()),
+            ..Self::new(id)
+        }
+    }
+
+    /// Adds the cost incurred since the last call and returns it. The busy
+    /// time is accrued along with it.
+    pub fn accrue_cost(&mut self) -> f64 {
+        let elapsed = clock::since(self.cost_updated_at).as_secs_f64() * 1000.0;
+        self.cost_updated_at = clock::now();
+
+        let busy = self.in_flight.len().min(self.workers) as f64;
+        let idle = self.workers as f64 - busy;
+        let cost = elapsed * self.cost_per_ms * (busy + idle * IDLE_COST_RATIO);
+
+        self.busy_time += elapsed * busy / self.workers as f64;
+        self.cost += cost;
+        self.busy_history.push(self.busy_time);
+
+        cost
+    }
+
+    /// Share of the server's lifetime its workers spent processing.
+    pub fn utilization(&self) -> f64 {
+        let lifetime = clock::since(self.created_at).as_secs_f64() * 1000.0;
+        if lifetime == 0.0 {
+            0.0
+        } else {
+            (self.busy_time / lifetime).min(1.0)
+        }
+    }
+
+    /// Share of the shared window its workers spent processing, or of its
+    /// lifetime if younger.
+    pub fn recent_utilization(&self) -> f64 {
+        let (Some((start, busy_start)), Some((end, busy_end))) = (
+            self.busy_history.iter().next(),
+            self.busy_history.iter().next_back(),
+        ) else {
+            return 0.0;
+        };
+
+        let span = end.duration_since(start).as_secs_f64() * 1000.0;
+        if span == 0.0 {
+            self.utilization()
+        } else {
+            ((busy_end - busy_start) / span).min(1.0)
+        }
+    }
+
+    /// Starts the cost, utilization and cache hit rate over.
+    pub fn reset_stats(&mut self) {
+        self.cost = 0.0;
+        self.busy_time = 0.0;
+        self.busy_history.clear();
+        self.created_at = clock::now();
+        self.cache_hits = 0;
+        self.cache_lookups = 0;
+    }
+
+    pub fn is_warming_up(&self) -> bool {
+        self.added_at.is_some_and(|at| clock::since(at) < WARMUP)
+    }
+
+    /// Service time multiplier from a cold start, decaying from
+    /// `COLD_START_PENALTY` to 1.
+    fn warmup_factor(&self) -> f64 {
+        match self.added_at {
+            Some(at) if clock::since(at) < WARMUP => {
+                let cold = 1.0 - clock::since(at).as_secs_f64() / WARMUP.as_secs_f64();
+                1.0 + (COLD_START_PENALTY - 1.0) * cold
+            }
+            _ => 1.0,
         }
     }
 
     pub fn add_request(&mut self, request: Request) {
         self.total_workload += request.get_time();
         self.queue.push_back(request);
+
+        while self
+            .accepted
+            .front()
+            .is_some_and(|at| clock::since(*at) >= RATE_WINDOW)
+        {
+            self.accepted.pop_front();
+        }
+        self.accepted.push_back(clock::now());
+    }
+
+    /// Memory (MB) held by every request on the server, queued or not.
+    pub fn memory_used(&self) -> u64 {
+        self.in_flight
+            .iter()
+            .chain(&self.preempted)
+            .map(|in_flight| in_flight.request.memory)
+            .chain(self.queue.iter().map(|request| request.memory))
+            .sum()
+    }
+
+    /// Time (ms) to send the request's payload to the server.
+    pub fn transfer_time(&self, request: &Request) -> u64 {
+        let cross_zone = request.zone.is_some_and(|zone| Some(zone) != self.zone);
+
+        self.latency
+            + request.payload / self.bandwidth
+            + if cross_zone { CROSS_ZONE_LATENCY } else { 0 }
+    }
+
+    /// Every request on the server, queued or not.
+    pub fn held_requests(&self) -> impl Iterator<Item = &Request> {
+        self.in_flight
+            .iter()
+            .chain(&self.preempted)
+            .map(|in_flight| &in_flight.request)
+            .chain(&self.queue)
+    }
+
+    /// Whether a part of the given fork is on the server, queued or not.
+    pub fn holds_fork(&self, parent: usize) -> bool {
+        self.held_requests()
+            .any(|request| request.fork.is_some_and(|fork| fork.parent == parent))
+    }
+
+    pub fn holds_request(&self, request_id: usize) -> bool {
+        self.held_requests().any(|request| request.id == request_id)
+    }
+
+    pub fn is_out_of_memory(&self) -> bool {
+        self.memory_used() > self.memory
+    }
+
+    /// Requests assigned over the last second.
+    pub fn current_rate(&self) -> usize {
+        self.accepted
+            .iter()
+            .filter(|at| clock::since(**at) < RATE_WINDOW)
+            .count()
+    }
+
+    pub fn next_rate_limit(&self) -> Option<u32> {
+        let idx = RATE_LIMITS
+            .iter()
+            .position(|limit| *limit == self.rate_limit)
+            .unwrap_or(0);
+        RATE_LIMITS[(idx + 1) % RATE_LIMITS.len()]
+    }
+
+    pub fn next_failure_rate(&self) -> f64 {
+        let idx = FAILURE_RATES
+            .iter()
+            .position(|rate| *rate == self.failure_rate)
+            .unwrap_or(0);
+        FAILURE_RATES[(idx + 1) % FAILURE_RATES.len()]
+    }
+
+    pub fn next_workers(&self) -> usize {
+        let idx = WORKER_COUNTS
+            .iter()
+            .position(|workers| *workers == self.workers)
+            .unwrap_or(0);
+        WORKER_COUNTS[(idx + 1) % WORKER_COUNTS.len()]
+    }
+
+    pub fn set_status(&mut self, status: ServerStatus) {
+        // A drain can be cancelled while its completion is still in flight.
+        if status == ServerStatus::Drained && self.status != ServerStatus::Draining {
+            return;
+        }
+        self.status = status;
+    }
+
+    pub fn queue_full(&self) -> bool {
+        self.queue.len() >= queue_capacity()
+    }
+
+    pub fn accepts_requests(&self) -> bool {
+        let breaker_allows = match self.breaker {
+            BreakerState::Closed => true,
+            BreakerState::Open => false,
+            BreakerState::HalfOpen => self.is_idle(),
+        };
+
+        let rate_allows = self
+            .rate_limit
+            .is_none_or(|limit| self.current_rate() < limit as usize);
+
+        self.status == ServerStatus::Active && breaker_allows && rate_allows
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.queue.is_empty() && self.in_flight.is_empty() && self.preempted.is_empty()
+    }
+
+    pub fn has_free_worker(&self) -> bool {
+        self.in_flight.len() < self.workers && !self.is_stalled() && !self.paused
+    }
+
+    /// Pausing sets the in-flight requests aside with their progress, to be
+    /// resumed like preempted ones.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+
+        if paused {
+            let request_ids: Vec<usize> = self
+                .in_flight
+                .iter()
+                .map(|in_flight| in_flight.request.id)
+                .collect();
+
+            for request_id in request_ids {
+                self.preempt(request_id);
+            }
+        }
+    }
+
+    pub fn stall(&mut self, duration: u64) {
+        self.stalled_until = Some(clock::now() + Duration::from_millis(duration));
+    }
+
+    pub fn is_stalled(&self) -> bool {
+        self.stalled_until.is_some_and(|until| until > clock::now())
+    }
+
+    pub fn stall_remaining(&self) -> u64 {
+        self.stalled_until
+            .map(|until| until.saturating_duration_since(clock::now()).as_millis() as u64)
+            .unwrap_or(0)
+    }
+
+    /// Service time of a request started now: scaled by the server's profile,
+    /// inflated while it warms up and by the number of requests still waiting
+    /// when `contended`.
+    pub fn service_time(&self, request: &Request, contended: bool) -> u64 {
+        let contention = if contended {
+            1.0 + self.contention * self.queue.len() as f64
+        } else {
+            1.0
+        };
+
+        (self.profile.scale(request) * contention * self.warmup_factor()) as u64
+    }
+
+    /// Marks the key as the most recently used one and returns whether it was
+    /// warm already.
+    fn warm_cache(&mut self, key: u32) -> bool {
+        let hit = match self.cache.iter().position(|warm| *warm == key) {
+            Some(idx) => self.cache.remove(idx).is_some(),
+            None => false,
+        };
+
+        if self.cache.len() == CACHE_SIZE {
+            self.cache.pop_front();
+        }
+        self.cache.push_back(key);
+
+        hit
+    }
+
+    fn record_cache_lookup(&mut self, cache_hit: Option<bool>) {
+        if let Some(hit) = cache_hit {
+            self.cache_lookups += 1;
+            self.cache_hits += hit as u64;
+        }
+    }
+
+    /// Share of the keyed requests started with their key warm.
+    pub fn cache_hit_rate(&self) -> Option<f64> {
+        (self.cache_lookups > 0).then(|| self.cache_hits as f64 / self.cache_lookups as f64)
+    }
+
+    /// Moves the next request to a worker: the queued one picked by the queue
+    /// discipline, or a preempted one if it has less work left. With `cached`,
+    /// a request whose key is warm takes a fraction of its service time.
+    pub fn start_next(&mut self, contended: bool, cached: bool) -> Option<InFlight> {
+        let queued = self.discipline.pick(&self.queue);
+        let preempted = self
+            .preempted
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, paused)| paused.left)
+            .map(|(idx, _)| idx);
+
+        let resume = match (queued, preempted) {
+            (Some(queued), Some(preempted)) => {
+                self.preempted[preempted].left <= self.queue[queued].get_time()
+            }
+            (None, Some(_)) => true,
+            _ => false,
+        };
+
+        let in_flight = if resume {
+            InFlight {
+                started_at: clock::now(),
+                ..self.preempted.remove(preempted?)
+            }
+        } else {
+            let mut request = self.queue.remove(queued?)?;
+            request.started_at = Some(clock::now());
+            let cache_hit = request
+                .key
+                .filter(|_| cached)
+                .map(|key| self.warm_cache(key));
+            self.record_cache_lookup(cache_hit);
+
+            let mut service_time = self.service_time(&request, contended);
+            if cache_hit == Some(true) {
+                service_time = (service_time as f64 * CACHE_HIT_FACTOR) as u64;
+            }
+
+            InFlight {
+                request,
+                started_at: clock::now(),
+                service_time,
+                left: service_time,
+                cache_hit,
+            }
+        };
+
+        self.in_flight.push(in_flight);
+        Some(in_flight)
     }
 
+    /// Moves a specific queued or preempted request to a worker, as reported
+    /// by the servers task.
+    pub fn start_request(&mut self, request_id: usize, service_time: u64, cache_hit: Option<bool>) {
+        if let Some(idx) = self
+            .preempted
+            .iter()
+            .position(|p| p.request.id == request_id)
+        {
+            let paused = self.preempted.remove(idx);
+            self.in_flight.push(InFlight {
+                started_at: clock::now(),
+                ..paused
+            });
+        } else if let Some(idx) = self.queue.iter().position(|r| r.id == request_id)
+            && let Some(mut request) = self.queue.remove(idx)
+        {
+            request.started_at = Some(clock::now());
+            self.record_cache_lookup(cache_hit);
+            self.in_flight.push(InFlight {
+                request,
+                started_at: clock::now(),
+                service_time,
+                left: service_time,
+                cache_hit,
+            });
+        }
+    }
+
+    /// In-flight request that the next request to start should preempt, if
+    /// any: the one with the most work left, when that is far longer.
+    pub fn preemption_victim(&self) -> Option<usize> {
+        let next = &self.queue[self.discipline.pick(&self.queue)?];
+        let victim = self.in_flight.iter().max_by_key(|f| f.remaining_time())?;
+
+        (victim.remaining_time() >= next.get_time() * PREEMPTION_FACTOR)
+            .then_some(victim.request.id)
+    }
+
+    pub fn preempt(&mut self, request_id: usize) {
+        if let Some(idx) = self
+            .in_flight
+            .iter()
+            .position(|in_flight| in_flight.request.id == request_id)
+        {
+            let in_flight = self.in_flight.remove(idx);
+            self.preempted.push(InFlight {
+                left: in_flight.remaining_time(),
+                ..in_flight
+            });
+        }
+    }
+
+    pub fn finish_request(&mut self, request_id: usize) -> Option<Request> {
+        let idx = self
+            .in_flight
+            .iter()
+            .position(|in_flight| in_flight.request.id == request_id)?;
+        let request = self.in_flight.remove(idx).request;
+
+        self.total_workload = self.total_workload.saturating_sub(request.get_time());
+        Some(request)
+    }
+
+    /// Removes and returns the in-flight requests whose processing is done.
+    pub fn take_finished(&mut self) -> Vec<Request> {
+        let finished: Vec<usize> = self
+            .in_flight
+            .iter()
+            .filter(|in_flight| in_flight.remaining_time() == 0)
+            .map(|in_flight| in_flight.request.id)
+            .collect();
+
+        finished
+            .into_iter()
+            .filter_map(|request_id| self.finish_request(request_id))
+            .collect()
+    }
+
+    /// Workload still to be done: the queued requests plus whatever is left of
+    /// the in-flight and preempted ones and of a stall.
+    pub fn remaining_workload(&self) -> u64 {
+        let started_nominal: u64 = self
+            .in_flight
+            .iter()
+            .chain(&self.preempted)
+            .map(|in_flight| in_flight.request.get_time())
+            .sum();
+        let in_flight_remaining: u64 = self.in_flight.iter().map(InFlight::remaining_time).sum();
+        let preempted_remaining: u64 = self.preempted.iter().map(|paused| paused.left).sum();
+
+        self.total_workload.saturating_sub(started_nominal)
+            + in_flight_remaining
+            + preempted_remaining
+            + self.stall_remaining()
+    }
+
+    /// Loses every request on the server, returning them so they can be
+    /// dispatched again.
+    pub fn crash(&mut self) -> Vec<Request> {
+        self.crashed_at = Some(clock::now());
+        self.total_workload = 0;
+        self.cache.clear();
+
+        self.in_flight
+            .drain(..)
+            .chain(self.preempted.drain(..))
+            .map(|in_flight| in_flight.request)
+            .chain(self.queue.drain(..))
+            .collect()
+    }
+
+    /// Removes and returns the queued requests past their deadline.
+    pub fn take_expired(&mut self) -> Vec<Request> {
+        let expired: Vec<usize> = self
+            .queue
+            .iter()
+            .filter(|request| request.is_expired())
+            .map(|request| request.id)
+            .collect();
+
+        expired
+            .into_iter()
+            .filter_map(|request_id| self.remove_queued(request_id))
+            .collect()
+    }
+
+    pub fn remove_queued(&mut self, request_id: usize) -> Option<Request> {
+        let idx = self.queue.iter().position(|r| r.id == request_id)?;
+        let request = self.queue.remove(idx)?;
+
+        self.total_workload = self.total_workload.saturating_sub(request.get_time());
+        Some(request)
+    }
+
+    /// Takes back a request that hasn't started or was preempted.
     pub fn remove_request(&mut self) -> Option<Request> {
+        if let Some(in_flight) = self.preempted.pop() {
+            self.total_workload = self
+                .total_workload
+                .saturating_sub(in_flight.request.get_time());
+            return Some(in_flight.request);
+        }
+
         if let Some(request) = self.queue.pop_front() {
             self.total_workload = self.total_workload.saturating_sub(request.get_time());
             Some(request)
@@ -33,3 +750,40 @@ impl ServerState {
         }
     }
 }
+
+pub fn cost_per_ms(server_id: u64) -> f64 {
+    SERVER_COSTS[(server_id as usize - 1).min(SERVER_COSTS.len() - 1)]
+}
+
+pub fn initial_servers() -> Vec<ServerState> {
+    (1..=INITIAL_SERVERS).map(ServerState::new).collect()
+}
+
+/// Ids are never reused, so events about a removed server can't be mistaken
+/// for a newer one.
+pub fn next_server_id() -> u64 {
+    context::with(|context| context.next_server_id.fetch_add(1, Ordering::SeqCst))
+}
+
+pub fn queue_capacity() -> usize {
+    context::with(|context| context.queue_capacity.load(Ordering::SeqCst))
+}
+
+/// Changes the capacity of every queue. Queues holding more keep their
+/// requests, but take no more until they are below it.
+pub fn set_queue_capacity(capacity: usize) {
+    context::with(|context| context.queue_capacity.store(capacity, Ordering::SeqCst));
+}
+
+/// Slowdown per waiting request of the server with the id under contention,
+/// from the simulation's list, the last entry covering the servers past it.
+pub fn contention_of(id: u64) -> f64 {
+    context::with(|context| {
+        let contention = &context.contention;
+        contention[(id as usize - 1).min(contention.len() - 1)]
+    })
+}
+
+pub fn find_server(servers: &mut [ServerState], server_id: u64) -> Option<&mut ServerState> {
+    servers.iter_mut().find(|server| server.id == server_id)
+}
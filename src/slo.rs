@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use crate::request::RequestSize;
+use crate::window::SlidingWindow;
+
+/// Latency targets (ms) of each request class.
+pub const SLO_TARGETS: [(RequestSize, u64); 3] = [
+    (RequestSize::Small, 500),
+    (RequestSize::Mid, 1500),
+    (RequestSize::Large, 4000),
+];
+/// Share of a class's requests that should meet its target.
+pub const SLO_OBJECTIVE: f64 = 0.95;
+/// Span of the sliding window attainment is computed over.
+pub const SLO_WINDOW: Duration = Duration::from_secs(60);
+
+/// Whether each request finished over the last `SLO_WINDOW` met its target.
+pub struct SloTracker {
+    samples: SlidingWindow<(RequestSize, bool)>,
+}
+
+impl Default for SloTracker {
+    fn default() -> Self {
+        Self {
+            samples: SlidingWindow::new(SLO_WINDOW),
+        }
+    }
+}
+
+impl SloTracker {
+    /// Records a finished request; failed ones, without a latency, count as
+    /// missing their target.
+    pub fn record(&mut self, size: RequestSize, latency: Option<u64>) {
+        let met = latency.is_some_and(|latency| latency <= target(size));
+        self.samples.push((size, met));
+    }
+
+    /// Share of the class's requests in the window that met the target, and
+    /// how many requests that is out of; `None` without any.
+    pub fn attainment(&self, size: RequestSize) -> Option<(f64, usize)> {
+        let samples: Vec<bool> = self
+            .samples
+            .iter()
+            .filter(|(_, (class, _))| *class == size)
+            .map(|(_, (_, met))| *met)
+            .collect();
+
+        if samples.is_empty() {
+            return None;
+        }
+
+        let met = samples.iter().filter(|met| **met).count();
+        Some((met as f64 / samples.len() as f64, samples.len()))
+    }
+}
+
+fn target(size: RequestSize) -> u64 {
+    SLO_TARGETS
+        .iter()
+        .find(|(class, _)| *class == size)
+        .map_or(u64::MAX, |(_, target)| *target)
+}
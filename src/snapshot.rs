@@ -0,0 +1,26 @@
+use std::fs;
+
+use ratatui::buffer::Buffer;
+
+/// Writes a rendered frame as plain text, one line per row without the
+/// trailing spaces and with the colors left out, to a file named after the
+/// time in the current directory, returning its name.
+pub fn save(buffer: &Buffer) -> Result<String, String> {
+    let path = format!(
+        "snapshot-{}.txt",
+        chrono::Local::now().format("%Y%m%d-%H%M%S-%3f")
+    );
+
+    let width = buffer.area.width as usize;
+    let mut text = String::new();
+    for row in buffer.content.chunks(width.max(1)) {
+        // Wide characters leave the next cell empty, so joining the symbols
+        // keeps the columns lined up.
+        let line: String = row.iter().map(|cell| cell.symbol()).collect();
+        text.push_str(line.trim_end());
+        text.push('\n');
+    }
+
+    fs::write(&path, text).map_err(|e| format!("cannot write {path}: {e}"))?;
+    Ok(path)
+}
@@ -0,0 +1,388 @@
+use std::time::{Duration, Instant};
+
+use rusqlite::{Connection, params};
+
+use crate::request::Request;
+use crate::{ConfigUpdate, SystemEvent, SystemState};
+
+/// How often the samples are taken and the events written out.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS events (
+    time_ms INTEGER NOT NULL,
+    event TEXT NOT NULL,
+    request_id INTEGER,
+    server_id INTEGER,
+    detail TEXT
+);
+CREATE TABLE IF NOT EXISTS metrics (
+    time_ms INTEGER NOT NULL,
+    throughput REAL NOT NULL,
+    avg_response_ms REAL NOT NULL,
+    pending INTEGER NOT NULL,
+    processed INTEGER NOT NULL,
+    dropped INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS server_samples (
+    time_ms INTEGER NOT NULL,
+    server_id INTEGER NOT NULL,
+    queue INTEGER NOT NULL,
+    in_flight INTEGER NOT NULL,
+    workload_ms INTEGER NOT NULL,
+    utilization REAL NOT NULL
+);
+";
+
+/// An event as stored, with the request and server it is about, if any.
+struct EventRow {
+    time_ms: i64,
+    event: &'static str,
+    request_id: Option<usize>,
+    server_id: Option<u64>,
+    detail: Option<String>,
+}
+
+/// Stores every event, and samples of the metrics and of each server every
+/// `SAMPLE_INTERVAL`, in a SQLite database. Times are Unix timestamps in ms,
+/// so several runs can go to the same file. Events are buffered and written
+/// along with the samples, in a single transaction.
+pub struct SqliteSink {
+    connection: Connection,
+    events: Vec<EventRow>,
+    sampled_at: Instant,
+}
+
+impl SqliteSink {
+    /// Opens the database, creating the tables if they don't exist.
+    pub fn open(path: &str) -> Result<Self, String> {
+        let error = |e: rusqlite::Error| format!("cannot write {path}: {e}");
+        let connection = Connection::open(path).map_err(error)?;
+        connection.execute_batch(SCHEMA).map_err(error)?;
+
+        Ok(Self {
+            connection,
+            events: Vec::new(),
+            sampled_at: Instant::now(),
+        })
+    }
+
+    pub fn observe(&mut self, event: &SystemEvent) {
+        let (name, request_id, server_id, detail) = describe(event);
+        self.events.push(EventRow {
+            time_ms: now_ms(),
+            event: name,
+            request_id,
+            server_id,
+            detail,
+        });
+    }
+
+    /// Writes the events so far and a sample if the last one is
+    /// `SAMPLE_INTERVAL` old.
+    pub fn record(&mut self, state: &SystemState) -> Result<(), String> {
+        if self.sampled_at.elapsed() < SAMPLE_INTERVAL {
+            return Ok(());
+        }
+        self.sampled_at = Instant::now();
+
+        self.write(Some(state))
+            .map_err(|e| format!("cannot write to the database: {e}"))
+    }
+
+    fn write(&mut self, state: Option<&SystemState>) -> rusqlite::Result<()> {
+        let transaction = self.connection.transaction()?;
+
+        {
+            let mut insert = transaction.prepare_cached(
+                "INSERT INTO events (time_ms, event, request_id, server_id, detail)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for row in self.events.drain(..) {
+                insert.execute(params![
+                    row.time_ms,
+                    row.event,
+                    row.request_id.map(|id| id as i64),
+                    row.server_id.map(|id| id as i64),
+                    row.detail
+                ])?;
+            }
+        }
+
+        if let Some(state) = state {
+            let time_ms = now_ms();
+            let stats = &state.stats;
+            transaction.execute(
+                "INSERT INTO metrics (time_ms, throughput, avg_response_ms, pending, processed, dropped)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    time_ms,
+                    stats.throughput,
+                    stats.avg_wait_time,
+                    state.pending_requests.len() as i64,
+                    stats.processed_requests as i64,
+                    (stats.rejected_requests + stats.expired_requests + stats.failed_requests)
+                        as i64
+                ],
+            )?;
+
+            let mut insert = transaction.prepare_cached(
+                "INSERT INTO server_samples (time_ms, server_id, queue, in_flight, workload_ms, utilization)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for server in &state.servers {
+                insert.execute(params![
+                    time_ms,
+                    server.id as i64,
+                    server.queue.len() as i64,
+                    server.in_flight.len() as i64,
+                    server.remaining_workload() as i64,
+                    server.recent_utilization()
+                ])?;
+            }
+        }
+
+        transaction.commit()
+    }
+}
+
+impl Drop for SqliteSink {
+    /// Writes the events observed since the last sample.
+    fn drop(&mut self) {
+        self.write(None).ok();
+    }
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+/// Name of the event, the request and server it is about and anything else
+/// worth keeping, as text.
+fn describe(event: &SystemEvent) -> (&'static str, Option<usize>, Option<u64>, Option<String>) {
+    let class = |request: &Request| Some(format!("{:?} {:?}", request.kind, request.size));
+
+    match event {
+        SystemEvent::RequestCreated(request) => {
+            ("RequestCreated", Some(request.id), None, class(request))
+        }
+        SystemEvent::RequestAssigned { server_id, request } => (
+            "RequestAssigned",
+            Some(request.id),
+            Some(*server_id),
+            class(request),
+        ),
+        SystemEvent::RequestProcessStarted {
+            request_id,
+            server_id,
+            service_time,
+            cache_hit,
+        } => (
+            "RequestProcessStarted",
+            Some(*request_id),
+            Some(*server_id),
+            Some(match cache_hit {
+                Some(true) => format!("{service_time}ms, cache hit"),
+                _ => format!("{service_time}ms"),
+            }),
+        ),
+        SystemEvent::RequestProcessed {
+            request_id,
+            server_id,
+            outcome,
+            ..
+        } => (
+            "RequestProcessed",
+            Some(*request_id),
+            Some(*server_id),
+            Some(format!("{outcome:?}")),
+        ),
+        SystemEvent::RequestRejected { request } => {
+            ("RequestRejected", Some(request.id), None, class(request))
+        }
+        SystemEvent::RequestExpired { request, server_id } => (
+            "RequestExpired",
+            Some(request.id),
+            *server_id,
+            class(request),
+        ),
+        SystemEvent::RequestPreempted {
+            request_id,
+            server_id,
+        } => (
+            "RequestPreempted",
+            Some(*request_id),
+            Some(*server_id),
+            None,
+        ),
+        SystemEvent::RequestParked {
+            request_id,
+            server_id,
+        } => ("RequestParked", Some(*request_id), Some(*server_id), None),
+        SystemEvent::RequestRequeued { server_id, request } => {
+            ("RequestRequeued", Some(request.id), Some(*server_id), None)
+        }
+        SystemEvent::RequestForwarded { server_id, request } => {
+            ("RequestForwarded", Some(request.id), Some(*server_id), None)
+        }
+        SystemEvent::RequestCoalesced {
+            request_id,
+            leader_id,
+        } => (
+            "RequestCoalesced",
+            Some(*request_id),
+            None,
+            Some(format!("leader {leader_id}")),
+        ),
+        SystemEvent::RequestDeduplicated {
+            request,
+            leader_id,
+            outcome,
+        } => (
+            "RequestDeduplicated",
+            Some(request.id),
+            None,
+            Some(format!("leader {leader_id}, {outcome:?}")),
+        ),
+        SystemEvent::ServerAdded { server_id } => ("ServerAdded", None, Some(*server_id), None),
+        SystemEvent::ServerRemoved { server_id } => ("ServerRemoved", None, Some(*server_id), None),
+        SystemEvent::ServerStatusChanged { server_id, status } => (
+            "ServerStatusChanged",
+            None,
+            Some(*server_id),
+            Some(format!("{status:?}")),
+        ),
+        SystemEvent::BreakerChanged { server_id, state } => (
+            "BreakerChanged",
+            None,
+            Some(*server_id),
+            Some(format!("{state:?}")),
+        ),
+        SystemEvent::ServerStalled {
+            server_id,
+            duration,
+        } => (
+            "ServerStalled",
+            None,
+            Some(*server_id),
+            Some(format!("{duration}ms")),
+        ),
+        SystemEvent::ServerPaused { server_id, paused } => (
+            "ServerPaused",
+            None,
+            Some(*server_id),
+            Some(if *paused { "paused" } else { "resumed" }.to_string()),
+        ),
+        SystemEvent::ServerCrashed {
+            server_id,
+            drop_requests,
+        } => (
+            "ServerCrashed",
+            None,
+            Some(*server_id),
+            Some(
+                if *drop_requests {
+                    "dropped"
+                } else {
+                    "requeued"
+                }
+                .to_string(),
+            ),
+        ),
+        SystemEvent::QueueDisciplineChanged {
+            server_id,
+            discipline,
+        } => (
+            "QueueDisciplineChanged",
+            None,
+            Some(*server_id),
+            Some(format!("{discipline:?}")),
+        ),
+        SystemEvent::RateLimitChanged { server_id, limit } => (
+            "RateLimitChanged",
+            None,
+            Some(*server_id),
+            Some(limit.map_or("none".to_string(), |limit| format!("{limit} req/sec"))),
+        ),
+        SystemEvent::FailureRateChanged { server_id, rate } => (
+            "FailureRateChanged",
+            None,
+            Some(*server_id),
+            Some(rate.to_string()),
+        ),
+        SystemEvent::WorkersChanged { server_id, workers } => (
+            "WorkersChanged",
+            None,
+            Some(*server_id),
+            Some(format!("{workers} workers")),
+        ),
+        SystemEvent::Scaled(message) => ("Scaled", None, None, Some(message.clone())),
+        SystemEvent::BurstChanged { rate } => (
+            "BurstChanged",
+            None,
+            None,
+            Some(rate.map_or("ended".to_string(), |rate| format!("{rate:.1} req/sec"))),
+        ),
+        SystemEvent::ErrorEncountered(message) => {
+            ("ErrorEncountered", None, None, Some(message.clone()))
+        }
+        SystemEvent::ConfigChanged(update) => ("ConfigChanged", None, None, config_detail(update)),
+        SystemEvent::AlertChanged { rule, firing } => (
+            "AlertChanged",
+            None,
+            None,
+            Some(format!(
+                "{rule} {}",
+                if *firing { "firing" } else { "resolved" }
+            )),
+        ),
+    }
+}
+
+/// The settings the update changes, e.g. `arrival_rate=4.0, retries=on`.
+fn config_detail(update: &ConfigUpdate) -> Option<String> {
+    let toggles = [
+        ("admission_control", update.admission_control),
+        ("anti_affinity", update.anti_affinity),
+        ("autoscaling", update.autoscaling),
+        ("contention", update.contention),
+        ("stalls", update.stalls),
+        ("preemption", update.preemption),
+        ("retries", update.retries),
+        ("bursts", update.bursts),
+        ("sessions", update.sessions),
+        ("batching", update.batching),
+        ("forks", update.forks),
+        ("pipelines", update.pipelines),
+        ("network", update.network),
+        ("dependencies", update.dependencies),
+        ("deduplication", update.deduplication),
+        ("caching", update.caching),
+        ("fair_queueing", update.fair_queueing),
+        ("paused", update.paused),
+    ];
+
+    let changes: Vec<String> = update
+        .arrival_rate
+        .map(|rate| format!("arrival_rate={rate:.1}"))
+        .into_iter()
+        .chain(update.choice_mode.map(|mode| format!("policy={mode}")))
+        .chain(
+            update
+                .delegate_mode
+                .map(|mode| format!("delegate_policy={mode}")),
+        )
+        .chain(update.speed.map(|speed| format!("speed={speed}")))
+        .chain(
+            update
+                .queue_capacity
+                .map(|capacity| format!("queue_capacity={capacity}")),
+        )
+        .chain(update.seed.map(|seed| format!("seed={seed}")))
+        .chain(toggles.into_iter().filter_map(|(name, value)| {
+            value.map(|on| format!("{name}={}", if on { "on" } else { "off" }))
+        }))
+        .collect();
+
+    (!changes.is_empty()).then(|| changes.join(", "))
+}
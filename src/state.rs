@@ -0,0 +1,904 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::alert::{AlertRule, AlertRules, Alerts};
+use crate::histogram::LatencyHistogram;
+use crate::request::{self, Request};
+use crate::server::{self, ServerState, find_server};
+use crate::slo::SloTracker;
+use crate::strategy::{self, ServerChoiceMode};
+use crate::tenant::TenantTracker;
+use crate::timeline::{Timeline, TimelineEntry};
+use crate::timeseries::QueueHistory;
+use crate::window::{SlidingWindow, window};
+use crate::{
+    ConfigUpdate, PENDING_REQUESTS_LIMIT, RequestOutcome, ResponseTimes, Simulation, Slowdowns,
+    SystemConfig, SystemEvent, SystemState, SystemStats, clock,
+};
+
+/// Span the throughput sparkline covers, one bar per second.
+pub const THROUGHPUT_HISTORY: Duration = Duration::from_secs(60);
+
+impl SystemState {
+    /// State of a simulation that just started, whose statistics start over
+    /// after the warm-up, if any, and whose alerts are checked against the
+    /// rules.
+    pub fn new(simulation: &Simulation, warmup: Option<Duration>, alerts: AlertRules) -> Self {
+        let context = simulation.context();
+        context.enter(|| Self {
+            configs: SystemConfig {
+                arrival_rate: 3.0,
+                choice_mode: ServerChoiceMode::default(),
+                delegate_mode: None,
+                admission_control: false,
+                anti_affinity: false,
+                autoscaling: false,
+                contention: false,
+                stalls: false,
+                preemption: false,
+                retries: false,
+                bursts: false,
+                sessions: false,
+                batching: false,
+                forks: false,
+                pipelines: false,
+                network: false,
+                dependencies: false,
+                deduplication: false,
+                caching: false,
+                fair_queueing: false,
+                paused: false,
+                seed: simulation.seed(),
+            },
+            pending_requests: VecDeque::new(),
+            servers: server::initial_servers(),
+            logs: Vec::with_capacity(100),
+            stats: initial_stats(),
+            warming_up_until: warmup.map(|warmup| clock::now() + warmup),
+            launched_at: clock::now(),
+            launched_at_real: Instant::now(),
+            alerts: Alerts::new(alerts),
+            retry_policy: simulation.retry_policy().clone(),
+            context: simulation.context(),
+        })
+    }
+
+    /// Brings the state up to date with an event the simulation sent,
+    /// logging it.
+    pub fn apply(&mut self, event: SystemEvent) {
+        let context = Arc::clone(&self.context);
+        context.enter(|| self.apply_event(event));
+    }
+
+    fn apply_event(&mut self, event: SystemEvent) {
+        match event {
+            SystemEvent::RequestCreated(request) => {
+                request::insert_by_priority(&mut self.pending_requests, request);
+
+                if request.attempt > 1 {
+                    add_log(
+                        &mut self.logs,
+                        format!(
+                            "Request #{} retried (attempt {})",
+                            request.id, request.attempt
+                        ),
+                    );
+                } else {
+                    self.stats.total_requests += 1;
+                    add_log(&mut self.logs, format!("Request #{} created", request.id));
+
+                    if let Some(fork) = request.fork {
+                        self.stats.fork_parts.insert(fork.parent, fork.parts);
+                    }
+                }
+            }
+            SystemEvent::RequestAssigned { server_id, request } => {
+                self.pending_requests.retain(|r| r.id != request.id);
+                *self
+                    .stats
+                    .assignments
+                    .entry((request.kind, request.size, server_id))
+                    .or_default() += 1;
+
+                if let Some(arrives_at) = request.arrives_at {
+                    let transfer = arrives_at.saturating_duration_since(clock::now());
+                    self.stats.transfers.record(transfer.as_millis() as f64);
+                }
+
+                if let Some(session_id) = request.session_id
+                    && let Some(previous) = self.stats.session_servers.insert(session_id, server_id)
+                {
+                    self.stats.session_requests += 1;
+                    if previous != server_id {
+                        self.stats.sticky_violations += 1;
+                    }
+                }
+
+                if let Some(server) = find_server(&mut self.servers, server_id) {
+                    server.add_request(request);
+                    add_log(
+                        &mut self.logs,
+                        format!("Request #{} assigned to Server {}", request.id, server_id),
+                    );
+                }
+            }
+            SystemEvent::RequestProcessStarted {
+                request_id,
+                server_id,
+                service_time,
+                cache_hit,
+            } => {
+                let mut resumed = false;
+                if let Some(server) = find_server(&mut self.servers, server_id) {
+                    resumed = server.preempted.iter().any(|p| p.request.id == request_id);
+                    server.start_request(request_id, service_time, cache_hit);
+                }
+                add_log(
+                    &mut self.logs,
+                    if resumed {
+                        format!("Server {} resumed Request #{}", server_id, request_id)
+                    } else {
+                        format!(
+                            "Server {} started at Request #{} ({}ms{})",
+                            server_id,
+                            request_id,
+                            service_time,
+                            if cache_hit == Some(true) {
+                                ", cache hit"
+                            } else {
+                                ""
+                            }
+                        )
+                    },
+                );
+            }
+            SystemEvent::RequestProcessed {
+                request_id,
+                server_id,
+                created_at,
+                assigned_at,
+                started_at,
+                outcome,
+            } => {
+                let now = clock::now();
+                let wait_time = now.duration_since(created_at).as_millis() as f64;
+
+                let finished = find_server(&mut self.servers, server_id)
+                    .and_then(|server| server.finish_request(request_id));
+
+                self.stats.timeline.record(TimelineEntry {
+                    request_id,
+                    server_id,
+                    class: finished.map(|request| (request.kind, request.size)),
+                    created_at,
+                    assigned_at,
+                    started_at,
+                    finished_at: now,
+                    failed: outcome == RequestOutcome::Failed,
+                });
+
+                let server_stats = self.stats.servers.entry(server_id).or_default();
+                if outcome == RequestOutcome::Failed {
+                    server_stats.failed += 1;
+                } else {
+                    server_stats.record(wait_time);
+                }
+
+                if outcome == RequestOutcome::Failed {
+                    self.stats.failed_requests += 1;
+
+                    if let Some(request) = finished {
+                        self.stats.slo.record(request.size, None);
+                        self.drop_fork_part(&request);
+                    }
+
+                    add_failure_log(
+                        &mut self.logs,
+                        format!("Server {} failed Request #{}", server_id, request_id),
+                    );
+                    return;
+                }
+
+                // Only the last stage of a pipeline completes the request.
+                if finished.is_some_and(|request| request.next_stage().is_some()) {
+                    return;
+                }
+
+                if let Some(request) = finished {
+                    if request.stage.is_some() {
+                        self.stats.pipelines.record(wait_time);
+                        add_log(
+                            &mut self.logs,
+                            format!(
+                                "Request #{} left the pipeline after {:.0}ms",
+                                request.id, wait_time
+                            ),
+                        );
+                    }
+
+                    if request.deadline.is_some() {
+                        self.stats.deadline_requests += 1;
+                        if request.is_expired() {
+                            self.stats.deadline_misses += 1;
+                        }
+                    }
+
+                    self.stats.slo.record(request.size, Some(wait_time as u64));
+                    self.stats
+                        .classes
+                        .entry((request.kind, request.size))
+                        .or_default()
+                        .record(wait_time);
+                    if request.total_time() > 0 {
+                        let slowdown = wait_time / request.total_time() as f64;
+                        self.stats.slowdowns.record(slowdown);
+                        self.stats
+                            .size_slowdowns
+                            .entry(request.size)
+                            .or_default()
+                            .record(slowdown);
+                    }
+                    self.stats.tenants.record(request.tenant);
+
+                    if let Some(tag) = request.tag {
+                        self.stats.tags.entry(tag).or_default().record(wait_time);
+                    }
+
+                    if request.batched {
+                        self.stats.batched.record(wait_time);
+                    } else {
+                        self.stats.immediate.record(wait_time);
+                    }
+
+                    if let Some(fork) = request.fork
+                        && let Some(left) = self.stats.fork_parts.get_mut(&fork.parent)
+                    {
+                        *left -= 1;
+                        if *left == 0 {
+                            self.stats.fork_parts.remove(&fork.parent);
+                            self.stats.joins.record(wait_time);
+                            add_log(
+                                &mut self.logs,
+                                format!("Fork #{} joined after {:.0}ms", fork.parent, wait_time),
+                            );
+                        }
+                    }
+                }
+
+                if let (Some(assigned_at), Some(started_at)) = (assigned_at, started_at) {
+                    let millis = |duration: Duration| duration.as_millis() as f64;
+                    let stats = &mut self.stats;
+                    stats
+                        .pending_times
+                        .record(millis(assigned_at.duration_since(created_at)));
+                    stats
+                        .queue_times
+                        .record(millis(started_at.duration_since(assigned_at)));
+                    stats
+                        .processing_times
+                        .record(millis(now.duration_since(started_at)));
+                }
+
+                self.record_processed(created_at);
+
+                add_log(
+                    &mut self.logs,
+                    format!("Server {} processed Request #{}", server_id, request_id),
+                );
+            }
+            SystemEvent::RequestCoalesced {
+                request_id,
+                leader_id,
+            } => {
+                self.pending_requests.retain(|r| r.id != request_id);
+                self.stats.dedup_hits += 1;
+                add_log(
+                    &mut self.logs,
+                    format!(
+                        "Request #{} coalesced with Request #{}",
+                        request_id, leader_id
+                    ),
+                );
+            }
+            SystemEvent::RequestDeduplicated {
+                request,
+                leader_id,
+                outcome,
+            } => {
+                if outcome == RequestOutcome::Failed {
+                    self.stats.failed_requests += 1;
+                    add_failure_log(
+                        &mut self.logs,
+                        format!(
+                            "Request #{} failed along with Request #{}",
+                            request.id, leader_id
+                        ),
+                    );
+                } else {
+                    self.record_processed(request.created_at);
+                    self.stats.tenants.record(request.tenant);
+                    add_log(
+                        &mut self.logs,
+                        format!(
+                            "Request #{} completed along with Request #{}",
+                            request.id, leader_id
+                        ),
+                    );
+                }
+            }
+            SystemEvent::RequestForwarded { server_id, request } => {
+                request::insert_by_priority(&mut self.pending_requests, request);
+                if let Some(stage) = request.stage {
+                    add_log(
+                        &mut self.logs,
+                        format!(
+                            "Request #{} forwarded from Server {} to stage {}/{}",
+                            request.id, server_id, stage.stage, stage.stages
+                        ),
+                    );
+                }
+            }
+            SystemEvent::RequestRequeued { server_id, request } => {
+                request::insert_by_priority(&mut self.pending_requests, request);
+                add_log(
+                    &mut self.logs,
+                    format!(
+                        "Request #{} re-queued from Server {}",
+                        request.id, server_id
+                    ),
+                );
+            }
+            SystemEvent::ServerAdded { server_id } => {
+                self.servers.push(ServerState::added(server_id));
+                self.update_key_remap_ratio();
+                add_log(&mut self.logs, format!("Server {} added", server_id));
+            }
+            SystemEvent::ServerRemoved { server_id } => {
+                self.servers.retain(|server| server.id != server_id);
+                self.update_key_remap_ratio();
+                add_log(&mut self.logs, format!("Server {} removed", server_id));
+            }
+            SystemEvent::ServerStatusChanged { server_id, status } => {
+                if let Some(server) = find_server(&mut self.servers, server_id) {
+                    let previous = server.status;
+                    server.set_status(status);
+
+                    if server.status != previous {
+                        add_log(
+                            &mut self.logs,
+                            format!("Server {} is {:?}", server_id, server.status),
+                        );
+                    }
+                }
+            }
+            SystemEvent::RequestExpired { request, server_id } => {
+                let request_id = request.id;
+
+                match server_id {
+                    Some(server_id) => {
+                        if let Some(server) = find_server(&mut self.servers, server_id) {
+                            server.remove_queued(request_id);
+                        }
+                        self.stats.servers.entry(server_id).or_default().expired += 1;
+                    }
+                    None => self.pending_requests.retain(|r| r.id != request_id),
+                }
+                self.stats.expired_requests += 1;
+                self.stats.deadline_requests += 1;
+                self.stats.deadline_misses += 1;
+                self.drop_fork_part(&request);
+
+                add_failure_log(
+                    &mut self.logs,
+                    match server_id {
+                        Some(server_id) => format!(
+                            "Request #{} expired in Server {}'s queue",
+                            request_id, server_id
+                        ),
+                        None => format!("Request #{} expired while pending", request_id),
+                    },
+                );
+            }
+            SystemEvent::RequestPreempted {
+                request_id,
+                server_id,
+            } => {
+                if let Some(server) = find_server(&mut self.servers, server_id) {
+                    server.preempt(request_id);
+                }
+                add_log(
+                    &mut self.logs,
+                    format!("Server {} preempted Request #{}", server_id, request_id),
+                );
+            }
+            SystemEvent::RequestParked {
+                request_id,
+                server_id,
+            } => {
+                // Not logged, "Simulation paused" covers every request.
+                if let Some(server) = find_server(&mut self.servers, server_id) {
+                    server.preempt(request_id);
+                }
+            }
+            SystemEvent::FailureRateChanged { server_id, rate } => {
+                if let Some(server) = find_server(&mut self.servers, server_id) {
+                    server.failure_rate = rate;
+                }
+                add_log(
+                    &mut self.logs,
+                    format!(
+                        "Server {} fails {:.0}% of requests",
+                        server_id,
+                        rate * 100.0
+                    ),
+                );
+            }
+            SystemEvent::WorkersChanged { server_id, workers } => {
+                if let Some(server) = find_server(&mut self.servers, server_id) {
+                    // The cost so far is for the old number of workers.
+                    self.stats.total_cost += server.accrue_cost();
+                    server.workers = workers;
+                }
+                add_log(
+                    &mut self.logs,
+                    format!("Server {} has {} workers", server_id, workers),
+                );
+            }
+            SystemEvent::RateLimitChanged { server_id, limit } => {
+                if let Some(server) = find_server(&mut self.servers, server_id) {
+                    server.rate_limit = limit;
+                }
+                add_log(
+                    &mut self.logs,
+                    match limit {
+                        Some(limit) => format!("Server {} limited to {} req/sec", server_id, limit),
+                        None => format!("Server {} rate limit removed", server_id),
+                    },
+                );
+            }
+            SystemEvent::QueueDisciplineChanged {
+                server_id,
+                discipline,
+            } => {
+                if let Some(server) = find_server(&mut self.servers, server_id) {
+                    server.discipline = discipline;
+                }
+                add_log(
+                    &mut self.logs,
+                    format!("Server {} now serves its queue {}", server_id, discipline),
+                );
+            }
+            SystemEvent::ServerPaused { server_id, paused } => {
+                if let Some(server) = find_server(&mut self.servers, server_id) {
+                    server.set_paused(paused);
+                }
+                add_log(
+                    &mut self.logs,
+                    if paused {
+                        format!("Server {} paused", server_id)
+                    } else {
+                        format!("Server {} resumed", server_id)
+                    },
+                );
+            }
+            SystemEvent::ServerCrashed {
+                server_id,
+                drop_requests,
+            } => {
+                if let Some(server) = find_server(&mut self.servers, server_id) {
+                    let request_ids: Vec<String> = server
+                        .crash()
+                        .iter()
+                        .map(|request| format!("#{}", request.id))
+                        .collect();
+
+                    add_log(
+                        &mut self.logs,
+                        if request_ids.is_empty() {
+                            format!("Server {} crashed", server_id)
+                        } else {
+                            format!(
+                                "Server {} crashed, {} {}",
+                                server_id,
+                                if drop_requests {
+                                    "failing"
+                                } else {
+                                    "re-dispatching"
+                                },
+                                request_ids.join(", ")
+                            )
+                        },
+                    );
+                }
+            }
+            SystemEvent::ServerStalled {
+                server_id,
+                duration,
+            } => {
+                if let Some(server) = find_server(&mut self.servers, server_id) {
+                    server.stall(duration);
+                }
+                add_log(
+                    &mut self.logs,
+                    format!("Server {} stalled for {}ms", server_id, duration),
+                );
+            }
+            SystemEvent::BreakerChanged {
+                server_id,
+                state: breaker,
+            } => {
+                if let Some(server) = find_server(&mut self.servers, server_id) {
+                    server.breaker = breaker;
+                }
+                add_log(
+                    &mut self.logs,
+                    format!("Server {} circuit breaker is {}", server_id, breaker),
+                );
+            }
+            SystemEvent::Scaled(message) => {
+                add_log(&mut self.logs, format!("Autoscaler: {message}"));
+            }
+            SystemEvent::BurstChanged { rate } => {
+                add_log(
+                    &mut self.logs,
+                    match rate {
+                        Some(rate) => format!("Burst started: {:.1} req/sec", rate),
+                        None => "Burst ended".to_string(),
+                    },
+                );
+            }
+            SystemEvent::ErrorEncountered(error_msg) => {
+                add_failure_log(&mut self.logs, format!("Error: {error_msg}"));
+            }
+            SystemEvent::AlertChanged { rule, firing } => {
+                if firing {
+                    add_failure_log(&mut self.logs, format!("Alert: {rule} firing"));
+                } else {
+                    add_log(&mut self.logs, format!("Alert: {rule} resolved"));
+                }
+            }
+            SystemEvent::RequestRejected { request } => {
+                self.pending_requests.retain(|r| r.id != request.id);
+                self.stats.rejected_requests += 1;
+                self.drop_fork_part(&request);
+                add_failure_log(
+                    &mut self.logs,
+                    format!("Request #{} rejected by admission control", request.id),
+                );
+            }
+            SystemEvent::ConfigChanged(ConfigUpdate {
+                arrival_rate,
+                choice_mode,
+                delegate_mode,
+                admission_control,
+                anti_affinity,
+                autoscaling,
+                contention,
+                stalls,
+                preemption,
+                retries,
+                bursts,
+                sessions,
+                batching,
+                forks,
+                pipelines,
+                network,
+                dependencies,
+                deduplication,
+                caching,
+                fair_queueing,
+                paused,
+                speed,
+                queue_capacity,
+                seed,
+            }) => {
+                if let Some(arrival_rate) = arrival_rate {
+                    self.configs.arrival_rate = arrival_rate;
+                }
+                if let Some(choice_mode) = choice_mode {
+                    self.configs.choice_mode = choice_mode;
+                    self.configs.delegate_mode = None;
+
+                    self.update_key_remap_ratio();
+                    if let Some(ratio) = self.stats.key_remap_ratio {
+                        add_log(
+                            &mut self.logs,
+                            format!(
+                                "{} remaps {:.1}% of keys when a server is removed",
+                                choice_mode,
+                                ratio * 100.0
+                            ),
+                        );
+                    }
+                }
+                if let Some(delegate_mode) = delegate_mode {
+                    self.configs.delegate_mode = Some(delegate_mode);
+                    add_log(
+                        &mut self.logs,
+                        format!("{} switched to {}", self.configs.choice_mode, delegate_mode),
+                    );
+                }
+                if let Some(admission_control) = admission_control {
+                    self.configs.admission_control = admission_control;
+                }
+                if let Some(anti_affinity) = anti_affinity {
+                    self.configs.anti_affinity = anti_affinity;
+                }
+                if let Some(autoscaling) = autoscaling {
+                    self.configs.autoscaling = autoscaling;
+                }
+                if let Some(contention) = contention {
+                    self.configs.contention = contention;
+                }
+                if let Some(stalls) = stalls {
+                    self.configs.stalls = stalls;
+                }
+                if let Some(preemption) = preemption {
+                    self.configs.preemption = preemption;
+                }
+                if let Some(retries) = retries {
+                    self.configs.retries = retries;
+                }
+                if let Some(bursts) = bursts {
+                    self.configs.bursts = bursts;
+                }
+                if let Some(sessions) = sessions {
+                    self.configs.sessions = sessions;
+                }
+                if let Some(batching) = batching {
+                    self.configs.batching = batching;
+                }
+                if let Some(forks) = forks {
+                    self.configs.forks = forks;
+                }
+                if let Some(pipelines) = pipelines {
+                    self.configs.pipelines = pipelines;
+                }
+                if let Some(network) = network {
+                    self.configs.network = network;
+                }
+                if let Some(dependencies) = dependencies {
+                    self.configs.dependencies = dependencies;
+                }
+                if let Some(deduplication) = deduplication {
+                    self.configs.deduplication = deduplication;
+                }
+                if let Some(caching) = caching {
+                    self.configs.caching = caching;
+                }
+                if let Some(fair_queueing) = fair_queueing {
+                    self.configs.fair_queueing = fair_queueing;
+                }
+                if let Some(paused) = paused
+                    && paused != self.configs.paused
+                {
+                    self.configs.paused = paused;
+                    add_log(
+                        &mut self.logs,
+                        if paused {
+                            "Simulation paused".to_string()
+                        } else {
+                            "Simulation resumed".to_string()
+                        },
+                    );
+                }
+                if let Some(speed) = speed {
+                    add_log(&mut self.logs, format!("Simulation speed set to {speed}x"));
+                }
+                if let Some(capacity) = queue_capacity {
+                    add_log(
+                        &mut self.logs,
+                        format!("Queue capacity set to {capacity} requests"),
+                    );
+                }
+                if let Some(seed) = seed {
+                    self.configs.seed = seed;
+                    add_log(&mut self.logs, format!("Random seed set to {seed}"));
+                }
+            }
+        }
+    }
+
+    /// Keeps the state going between events: ends the warm-up once it's
+    /// due, adds up the servers' cost, samples their queues and checks the
+    /// alerts. Returns the rules that started firing, `true`, or stopped,
+    /// `false`, for the caller to send back as `AlertChanged` events, which
+    /// get logged like any other.
+    pub fn tick(&mut self) -> Vec<(AlertRule, bool)> {
+        let context = Arc::clone(&self.context);
+        context.enter(|| self.tick_now())
+    }
+
+    fn tick_now(&mut self) -> Vec<(AlertRule, bool)> {
+        if self
+            .warming_up_until
+            .is_some_and(|until| clock::now() >= until)
+        {
+            self.end_warmup();
+        }
+
+        for server in &mut self.servers {
+            self.stats.total_cost += server.accrue_cost();
+        }
+        self.stats.queue_history.sample(&self.servers);
+
+        let pending_full = self.pending_requests.len() >= PENDING_REQUESTS_LIMIT as usize;
+        self.alerts.check(pending_full)
+    }
+
+    /// Adds a line to the event log.
+    pub fn log(&mut self, message: String) {
+        add_log(&mut self.logs, message);
+    }
+
+    /// Adds a failure to the event log.
+    pub fn log_failure(&mut self, message: String) {
+        add_failure_log(&mut self.logs, message);
+    }
+
+    /// Starts the statistics over once the warm-up is over, keeping the queue
+    /// history and the timeline, which cover the whole run.
+    fn end_warmup(&mut self) {
+        self.warming_up_until = None;
+
+        let queue_history = std::mem::take(&mut self.stats.queue_history);
+        let timeline = std::mem::take(&mut self.stats.timeline);
+        self.stats = SystemStats {
+            queue_history,
+            timeline,
+            ..initial_stats()
+        };
+        for server in &mut self.servers {
+            server.reset_stats();
+        }
+
+        add_log(&mut self.logs, "Warm-up over, statistics reset".to_string());
+    }
+
+    /// Forgets the fork of a dropped part, which can no longer join, unless the
+    /// part is going to be retried.
+    fn drop_fork_part(&mut self, request: &Request) {
+        let retried = self.configs.retries && self.retry_policy.backoff(request.attempt).is_some();
+
+        if let Some(fork) = request.fork
+            && !retried
+        {
+            self.stats.fork_parts.remove(&fork.parent);
+        }
+    }
+
+    /// Counts a completed request in the processed count, throughput, average
+    /// response time and the alerts' percentiles.
+    fn record_processed(&mut self, created_at: Instant) {
+        let now = clock::now();
+        let wait_time = now.duration_since(created_at).as_millis() as f64;
+        self.alerts.record(wait_time as u64);
+
+        let stats = &mut self.stats;
+
+        stats.processed_requests += 1;
+        stats.latencies.record(wait_time as u64);
+        stats.wait_times.record(wait_time);
+
+        stats.throughput_window.push(());
+
+        let recent: Vec<Instant> = stats
+            .throughput_window
+            .recent(window())
+            .map(|(at, _)| at)
+            .collect();
+        if let Some(oldest) = recent.first() {
+            let window_duration = now.duration_since(*oldest).as_secs_f64();
+            if window_duration > 0.0 {
+                stats.throughput = recent.len() as f64 / window_duration;
+            }
+        }
+
+        if created_at != now {
+            stats.avg_wait_time = if stats.processed_requests <= 1 {
+                wait_time
+            } else {
+                (stats.avg_wait_time * (stats.processed_requests - 1) as f64 + wait_time)
+                    / stats.processed_requests as f64
+            }
+        }
+    }
+
+    fn update_key_remap_ratio(&mut self) {
+        let choice_mode = self.configs.choice_mode;
+
+        self.stats.key_remap_ratio = choice_mode.is_keyed().then(|| {
+            let server_ids: Vec<u64> = self.servers.iter().map(|s| s.id).collect();
+            strategy::key_remap_ratio(choice_mode, &server_ids)
+        });
+    }
+}
+
+fn initial_stats() -> SystemStats {
+    SystemStats {
+        started_at: clock::now(),
+        total_requests: 0,
+        processed_requests: 0,
+        avg_wait_time: 0.0,
+        wait_times: ResponseTimes::default(),
+        throughput: 0.0,
+        throughput_window: SlidingWindow::new(THROUGHPUT_HISTORY.max(window())),
+        key_remap_ratio: None,
+        rejected_requests: 0,
+        expired_requests: 0,
+        failed_requests: 0,
+        dedup_hits: 0,
+        deadline_requests: 0,
+        deadline_misses: 0,
+        session_servers: HashMap::new(),
+        session_requests: 0,
+        sticky_violations: 0,
+        pending_times: ResponseTimes::default(),
+        queue_times: ResponseTimes::default(),
+        processing_times: ResponseTimes::default(),
+        batched: ResponseTimes::default(),
+        immediate: ResponseTimes::default(),
+        fork_parts: HashMap::new(),
+        joins: ResponseTimes::default(),
+        pipelines: ResponseTimes::default(),
+        slo: SloTracker::default(),
+        transfers: ResponseTimes::default(),
+        tags: BTreeMap::new(),
+        tenants: TenantTracker::default(),
+        servers: HashMap::new(),
+        latencies: LatencyHistogram::default(),
+        classes: HashMap::new(),
+        slowdowns: Slowdowns::default(),
+        size_slowdowns: HashMap::new(),
+        assignments: HashMap::new(),
+        queue_history: QueueHistory::default(),
+        timeline: Timeline::default(),
+        total_cost: 0.0,
+    }
+}
+
+/// An event log entry; failures are shown in red.
+pub struct LogLine {
+    text: String,
+    failed: bool,
+    /// Counts up over the run, so a line stays selected as newer ones come
+    /// in.
+    number: usize,
+}
+
+impl LogLine {
+    /// The message, prefixed with the time it was logged at.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn failed(&self) -> bool {
+        self.failed
+    }
+
+    pub fn number(&self) -> usize {
+        self.number
+    }
+}
+
+fn add_log(logs: &mut Vec<LogLine>, message: String) {
+    push_log(logs, message, false);
+}
+
+fn add_failure_log(logs: &mut Vec<LogLine>, message: String) {
+    push_log(logs, message, true);
+}
+
+fn push_log(logs: &mut Vec<LogLine>, message: String, failed: bool) {
+    let number = logs.last().map_or(0, |log| log.number + 1);
+    if logs.len() >= logs.capacity() {
+        logs.remove(0);
+    };
+
+    logs.push(LogLine {
+        text: format!("[{}] {}", chrono::Local::now().format("%H:%M:%S"), message),
+        failed,
+        number,
+    });
+}
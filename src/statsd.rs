@@ -0,0 +1,92 @@
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+use crate::clock;
+use crate::server::ServerState;
+use crate::{RequestOutcome, SystemEvent};
+
+/// Prefix of every metric name.
+const STATSD_PREFIX: &str = "load_balancer";
+/// How often the servers' queue depths are sent.
+const GAUGE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Sends counters and timers of the requests and gauges of the servers'
+/// queue depths to a StatsD server over UDP. Sending is fire-and-forget, so
+/// metrics are silently lost while nothing listens.
+pub struct StatsdSink {
+    socket: UdpSocket,
+    gauged_at: Option<Instant>,
+}
+
+impl StatsdSink {
+    /// Sends to the `host:port` address.
+    pub fn connect(address: &str) -> Result<Self, String> {
+        let error = |e: std::io::Error| format!("cannot send metrics to {address}: {e}");
+        let socket = UdpSocket::bind("0.0.0.0:0").map_err(error)?;
+        socket.connect(address).map_err(error)?;
+
+        Ok(Self {
+            socket,
+            gauged_at: None,
+        })
+    }
+
+    /// Counts the created, assigned and processed requests, timing the
+    /// processed ones.
+    pub fn observe(&self, event: &SystemEvent) {
+        match event {
+            SystemEvent::RequestCreated(_) => self.send("requests.created:1|c"),
+            SystemEvent::RequestAssigned { server_id, .. } => {
+                self.send("requests.assigned:1|c");
+                self.send(&format!("server.{server_id}.assigned:1|c"));
+            }
+            SystemEvent::RequestProcessed {
+                server_id,
+                created_at,
+                outcome,
+                ..
+            } => match outcome {
+                RequestOutcome::Succeeded => {
+                    self.send("requests.processed:1|c");
+                    self.send(&format!(
+                        "requests.response_time:{}|ms",
+                        clock::since(*created_at).as_millis()
+                    ));
+                }
+                RequestOutcome::Failed => {
+                    self.send("requests.failed:1|c");
+                    self.send(&format!("server.{server_id}.failed:1|c"));
+                }
+            },
+            SystemEvent::RequestRejected { .. } => self.send("requests.rejected:1|c"),
+            SystemEvent::RequestExpired { .. } => self.send("requests.expired:1|c"),
+            _ => {}
+        }
+    }
+
+    /// Sends each server's queue depth if the last ones are `GAUGE_INTERVAL`
+    /// old.
+    pub fn gauge_queues(&mut self, servers: &[ServerState]) {
+        if self
+            .gauged_at
+            .is_some_and(|at| at.elapsed() < GAUGE_INTERVAL)
+        {
+            return;
+        }
+        self.gauged_at = Some(Instant::now());
+
+        for server in servers {
+            self.send(&format!(
+                "server.{}.queue_depth:{}|g",
+                server.id,
+                server.queue.len()
+            ));
+        }
+    }
+
+    fn send(&self, metric: &str) {
+        self.socket
+            .send(format!("{STATSD_PREFIX}.{metric}").as_bytes())
+            .ok();
+    }
+}
@@ -0,0 +1,725 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use std::time::{Duration, Instant};
+
+use crate::request::{KEY_SPACE, Request, ServiceTimeDistribution};
+use crate::server::{ServerProfile, ServerState};
+use crate::{PENDING_REQUESTS_LIMIT, SystemEvent};
+use crate::{clock, context};
+
+const VIRTUAL_NODES: u32 = 64;
+/// Upper service-time bounds (ms) of each SITA interval unless
+/// `--sita-cutoffs` says otherwise.
+pub const SITA_CUTOFFS: [u64; 2] = [100, 300];
+const ADAPTIVE_WINDOW: Duration = Duration::from_secs(5);
+const ADAPTIVE_MIN_DWELL: Duration = Duration::from_secs(3);
+/// Relative weights of servers 1, 2 and 3 for the weighted random mode unless
+/// `--weights` says otherwise.
+pub const SERVER_WEIGHTS: [f64; 3] = [3.0, 2.0, 1.0];
+/// Expected response time (ms) a request may reach on the cheapest server
+/// before the cost-based mode pays for a faster one.
+const COST_LATENCY_BOUND: u64 = 2000;
+const BANDIT_EPSILON: f64 = 0.1;
+/// Weight of the newest latency sample; recent samples matter more because
+/// queues keep changing.
+const BANDIT_ALPHA: f64 = 0.2;
+
+pub trait Strategy: Send {
+    /// Returns server indices ordered by preference; the allocator assigns the
+    /// request to the first one that still has room in its queue.
+    fn choose(
+        &mut self,
+        servers: &[ServerState],
+        request: &Request,
+        rng: &mut StdRng,
+    ) -> Vec<usize>;
+
+    /// Called for every event the allocator receives or emits.
+    fn observe(&mut self, _event: &SystemEvent) {}
+
+    /// Called on every allocator tick with the number of requests waiting to
+    /// be assigned.
+    fn observe_pending(&mut self, _pending: usize) {}
+
+    /// Strategy currently making the decisions, for meta-strategies.
+    fn delegate(&self) -> Option<ServerChoiceMode> {
+        None
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct StrategyEntry {
+    pub name: &'static str,
+    /// Whether the strategy routes by request key, so key remapping is meaningful.
+    pub keyed: bool,
+    pub build: fn(&StrategyConfig) -> Box<dyn Strategy>,
+}
+
+/// Settings of the strategies that take any.
+#[derive(Debug, Clone)]
+pub struct StrategyConfig {
+    /// Upper service-time bounds (ms) of each SITA interval, in ascending
+    /// order; interval `i` goes to server `i` and anything above the last
+    /// cutoff goes to the next server.
+    pub sita_cutoffs: Vec<u64>,
+    /// Relative weights of the servers, in order, for the weighted random
+    /// mode; servers without an entry get a weight of 1.
+    pub server_weights: Vec<f64>,
+}
+
+impl Default for StrategyConfig {
+    fn default() -> Self {
+        Self {
+            sita_cutoffs: SITA_CUTOFFS.to_vec(),
+            server_weights: SERVER_WEIGHTS.to_vec(),
+        }
+    }
+}
+
+/// Built-in strategies, which every simulation's registry starts with,
+/// followed by the ones registered with `SimulationBuilder::strategy`.
+pub const REGISTRY: &[StrategyEntry] = &[
+    StrategyEntry {
+        name: "Random",
+        keyed: false,
+        build: |_| Box::new(Random),
+    },
+    StrategyEntry {
+        name: "Round Robin",
+        keyed: false,
+        build: |_| Box::new(RoundRobin { server_num: 0 }),
+    },
+    StrategyEntry {
+        name: "Smaller Queue",
+        keyed: false,
+        build: |_| Box::new(SmallerQueue),
+    },
+    StrategyEntry {
+        name: "Consistent Hash",
+        keyed: true,
+        build: |_| Box::new(ConsistentHash::default()),
+    },
+    StrategyEntry {
+        name: "Rendezvous Hash",
+        keyed: true,
+        build: |_| Box::new(Rendezvous),
+    },
+    StrategyEntry {
+        name: "Size Interval",
+        keyed: false,
+        build: |config| {
+            Box::new(SizeInterval {
+                cutoffs: config.sita_cutoffs.clone(),
+            })
+        },
+    },
+    StrategyEntry {
+        name: "Adaptive",
+        keyed: false,
+        build: |config| Box::new(Adaptive::new(config)),
+    },
+    StrategyEntry {
+        name: "Bandit",
+        keyed: false,
+        build: |_| Box::new(Bandit::default()),
+    },
+    StrategyEntry {
+        name: "Weighted Random",
+        keyed: false,
+        build: |config| {
+            Box::new(WeightedRandom(Weighting::Static(
+                config.server_weights.clone(),
+            )))
+        },
+    },
+    StrategyEntry {
+        name: "Inverse Load Random",
+        keyed: false,
+        build: |_| Box::new(WeightedRandom(Weighting::InverseWorkload)),
+    },
+    StrategyEntry {
+        name: "Zone Aware",
+        keyed: false,
+        build: |_| Box::new(ZoneAware),
+    },
+    StrategyEntry {
+        name: "Least Outstanding",
+        keyed: false,
+        build: |_| Box::new(LeastOutstanding),
+    },
+    StrategyEntry {
+        name: "Cost Based",
+        keyed: false,
+        build: |_| Box::new(CostBased),
+    },
+    StrategyEntry {
+        name: "Type Aware",
+        keyed: false,
+        build: |_| Box::new(TypeAware),
+    },
+    StrategyEntry {
+        name: "Memory Aware",
+        keyed: false,
+        build: |_| Box::new(MemoryAware),
+    },
+    StrategyEntry {
+        name: "Sticky Session",
+        keyed: false,
+        build: |_| Box::new(StickySession::default()),
+    },
+];
+
+/// Handle to an entry of the simulation's registry, cheap to copy around in
+/// events. Anything but the built-in constants reads the registry of the
+/// simulation it's called in.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct ServerChoiceMode(usize);
+
+impl Default for ServerChoiceMode {
+    fn default() -> Self {
+        Self::RANDOM
+    }
+}
+
+impl ServerChoiceMode {
+    pub const RANDOM: Self = Self::named("Random");
+    pub const ROUND_ROBIN: Self = Self::named("Round Robin");
+    pub const SMALLER_QUEUE: Self = Self::named("Smaller Queue");
+
+    /// The built-in strategy called `name`, looked up while compiling so a
+    /// missing one fails the build.
+    const fn named(name: &str) -> Self {
+        let mut idx = 0;
+        while idx < REGISTRY.len() {
+            if REGISTRY[idx].name.eq_ignore_ascii_case(name) {
+                return Self(idx);
+            }
+            idx += 1;
+        }
+        panic!("no strategy with that name in the registry");
+    }
+
+    /// The strategy called `name`, built-in or registered, if there is one.
+    pub fn find(name: &str) -> Option<Self> {
+        registry(|registry| {
+            registry
+                .iter()
+                .position(|entry| entry.name.eq_ignore_ascii_case(name))
+                .map(Self)
+        })
+    }
+
+    pub fn next(self) -> Self {
+        registry(|registry| Self((self.0 + 1) % registry.len()))
+    }
+
+    pub fn prev(self) -> Self {
+        registry(|registry| Self((self.0 + registry.len() - 1) % registry.len()))
+    }
+
+    /// The entry at `idx` of the registry, if there is one.
+    pub fn nth(idx: usize) -> Option<Self> {
+        registry(|registry| (idx < registry.len()).then_some(Self(idx)))
+    }
+
+    pub fn build(self, config: &StrategyConfig) -> Box<dyn Strategy> {
+        (self.entry().build)(config)
+    }
+
+    pub fn is_keyed(self) -> bool {
+        self.entry().keyed
+    }
+
+    fn entry(self) -> StrategyEntry {
+        registry(|registry| registry[self.0])
+    }
+}
+
+impl fmt::Display for ServerChoiceMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.entry().name)
+    }
+}
+
+/// Calls `f` with the strategies of the simulation the caller runs in.
+fn registry<R>(f: impl FnOnce(&[StrategyEntry]) -> R) -> R {
+    context::with(|context| f(&context.strategies))
+}
+
+/// Fraction of the key space whose preferred server changes when a single server
+/// is removed, averaged over every possible removal.
+pub fn key_remap_ratio(mode: ServerChoiceMode, server_ids: &[u64]) -> f64 {
+    if server_ids.len() < 2 {
+        return 0.0;
+    }
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let before = key_owners(mode, server_ids, &mut rng);
+
+    let mut remapped = 0;
+    for removed in server_ids {
+        let remaining: Vec<u64> = server_ids
+            .iter()
+            .copied()
+            .filter(|id| id != removed)
+            .collect();
+        let after = key_owners(mode, &remaining, &mut rng);
+
+        remapped += before.iter().zip(&after).filter(|(b, a)| b != a).count();
+    }
+
+    remapped as f64 / (KEY_SPACE as usize * server_ids.len()) as f64
+}
+
+fn key_owners(mode: ServerChoiceMode, server_ids: &[u64], rng: &mut StdRng) -> Vec<u64> {
+    let servers: Vec<ServerState> = server_ids.iter().map(|&id| ServerState::new(id)).collect();
+    let mut strategy = mode.build(&StrategyConfig::default());
+
+    (0..KEY_SPACE)
+        .map(|key| {
+            let request = Request {
+                key: Some(key),
+                ..Request::create_random(ServiceTimeDistribution::Fixed, rng)
+            };
+            servers[strategy.choose(&servers, &request, rng)[0]].id
+        })
+        .collect()
+}
+
+struct Random;
+
+impl Strategy for Random {
+    fn choose(&mut self, servers: &[ServerState], _: &Request, rng: &mut StdRng) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..servers.len()).collect();
+        indices.shuffle(rng);
+        indices
+    }
+}
+
+struct RoundRobin {
+    server_num: usize,
+}
+
+impl Strategy for RoundRobin {
+    fn choose(&mut self, servers: &[ServerState], _: &Request, _: &mut StdRng) -> Vec<usize> {
+        let len = servers.len();
+        let start = self.server_num % len;
+        self.server_num = (start + 1) % len;
+
+        (0..len).map(|offset| (start + offset) % len).collect()
+    }
+}
+
+struct SmallerQueue;
+
+impl Strategy for SmallerQueue {
+    fn choose(&mut self, servers: &[ServerState], _: &Request, _: &mut StdRng) -> Vec<usize> {
+        let mut servers_by_load: Vec<(usize, u64)> = servers
+            .iter()
+            .enumerate()
+            .map(|(idx, state)| (idx, state.total_workload))
+            .collect();
+
+        servers_by_load.sort_by_key(|(_, workload)| *workload);
+        servers_by_load.into_iter().map(|(idx, _)| idx).collect()
+    }
+}
+
+fn hash_of(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn request_key(request: &Request) -> u64 {
+    // Unkeyed requests have no affinity, so their id spreads them over the ring.
+    request.key.map_or(request.id as u64, u64::from)
+}
+
+#[derive(Default)]
+struct ConsistentHash {
+    ring: Vec<(u64, u64)>,
+    server_ids: Vec<u64>,
+}
+
+impl ConsistentHash {
+    fn rebuild(&mut self, servers: &[ServerState]) {
+        let server_ids: Vec<u64> = servers.iter().map(|server| server.id).collect();
+        if server_ids == self.server_ids {
+            return;
+        }
+
+        self.ring = server_ids
+            .iter()
+            .flat_map(|&id| (0..VIRTUAL_NODES).map(move |vnode| (hash_of((id, vnode)), id)))
+            .collect();
+        self.ring.sort_unstable();
+        self.server_ids = server_ids;
+    }
+}
+
+impl Strategy for ConsistentHash {
+    fn choose(&mut self, servers: &[ServerState], request: &Request, _: &mut StdRng) -> Vec<usize> {
+        self.rebuild(servers);
+
+        let hash = hash_of(request_key(request));
+        let start = self.ring.partition_point(|(point, _)| *point < hash);
+
+        let mut indices = Vec::with_capacity(servers.len());
+        for (_, id) in self.ring.iter().cycle().skip(start).take(self.ring.len()) {
+            if let Some(idx) = servers.iter().position(|server| server.id == *id)
+                && !indices.contains(&idx)
+            {
+                indices.push(idx);
+                if indices.len() == servers.len() {
+                    break;
+                }
+            }
+        }
+        indices
+    }
+}
+
+struct Rendezvous;
+
+impl Strategy for Rendezvous {
+    fn choose(&mut self, servers: &[ServerState], request: &Request, _: &mut StdRng) -> Vec<usize> {
+        let key = request_key(request);
+
+        let mut servers_by_weight: Vec<(usize, u64)> = servers
+            .iter()
+            .enumerate()
+            .map(|(idx, server)| (idx, hash_of((key, server.id))))
+            .collect();
+
+        servers_by_weight.sort_by_key(|(_, weight)| std::cmp::Reverse(*weight));
+        servers_by_weight.into_iter().map(|(idx, _)| idx).collect()
+    }
+}
+
+struct SizeInterval {
+    cutoffs: Vec<u64>,
+}
+
+impl Strategy for SizeInterval {
+    fn choose(&mut self, servers: &[ServerState], request: &Request, _: &mut StdRng) -> Vec<usize> {
+        let time = request.get_time();
+        let interval = self.cutoffs.partition_point(|cutoff| *cutoff < time);
+        let target = interval.min(servers.len().saturating_sub(1));
+
+        let mut indices: Vec<usize> = (0..servers.len()).collect();
+        indices.sort_by_key(|idx| idx.abs_diff(target));
+        indices
+    }
+}
+
+/// Switches between Random, Round Robin and Smaller Queue as the system gets
+/// more saturated, judged by pending queue depth and whether completions keep
+/// up with arrivals.
+struct Adaptive {
+    config: StrategyConfig,
+    active: ServerChoiceMode,
+    delegate: Box<dyn Strategy>,
+    /// Requests in the allocator's pending list as of its latest tick.
+    pending: usize,
+    arrivals: VecDeque<Instant>,
+    completions: VecDeque<Instant>,
+    last_switch: Instant,
+}
+
+impl Adaptive {
+    fn new(config: &StrategyConfig) -> Self {
+        Self {
+            config: config.clone(),
+            active: ServerChoiceMode::RANDOM,
+            delegate: ServerChoiceMode::RANDOM.build(config),
+            pending: 0,
+            arrivals: VecDeque::new(),
+            completions: VecDeque::new(),
+            last_switch: clock::now(),
+        }
+    }
+
+    fn target_mode(&self) -> ServerChoiceMode {
+        let pending_ratio = self.pending as f64 / PENDING_REQUESTS_LIMIT as f64;
+        let falling_behind = self.arrivals.len() > self.completions.len() + 2;
+
+        if pending_ratio >= 0.5 || (falling_behind && pending_ratio >= 0.2) {
+            ServerChoiceMode::SMALLER_QUEUE
+        } else if pending_ratio >= 0.2 || falling_behind {
+            ServerChoiceMode::ROUND_ROBIN
+        } else {
+            ServerChoiceMode::RANDOM
+        }
+    }
+}
+
+impl Strategy for Adaptive {
+    fn choose(
+        &mut self,
+        servers: &[ServerState],
+        request: &Request,
+        rng: &mut StdRng,
+    ) -> Vec<usize> {
+        let target = self.target_mode();
+        if target != self.active && clock::since(self.last_switch) >= ADAPTIVE_MIN_DWELL {
+            self.active = target;
+            self.delegate = target.build(&self.config);
+            self.last_switch = clock::now();
+        }
+
+        self.delegate.choose(servers, request, rng)
+    }
+
+    fn observe(&mut self, event: &SystemEvent) {
+        let now = clock::now();
+        match event {
+            SystemEvent::RequestCreated(_) => self.arrivals.push_back(now),
+            SystemEvent::RequestProcessed { .. } => self.completions.push_back(now),
+            _ => {}
+        }
+
+        for window in [&mut self.arrivals, &mut self.completions] {
+            while window
+                .front()
+                .is_some_and(|at| now.duration_since(*at) > ADAPTIVE_WINDOW)
+            {
+                window.pop_front();
+            }
+        }
+
+        self.delegate.observe(event);
+    }
+
+    fn observe_pending(&mut self, pending: usize) {
+        self.pending = pending;
+        self.delegate.observe_pending(pending);
+    }
+
+    fn delegate(&self) -> Option<ServerChoiceMode> {
+        Some(self.active)
+    }
+}
+
+/// Epsilon-greedy bandit treating each server as an arm whose cost is the
+/// observed time from assignment to completion, leaving out the time spent
+/// pending, which no choice of server affects.
+#[derive(Default)]
+struct Bandit {
+    latency_estimates: HashMap<u64, f64>,
+}
+
+impl Strategy for Bandit {
+    fn choose(&mut self, servers: &[ServerState], _: &Request, rng: &mut StdRng) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..servers.len()).collect();
+
+        if rng.random_bool(BANDIT_EPSILON) {
+            indices.shuffle(rng);
+        } else {
+            // Unexplored arms have no estimate yet, so they are tried first.
+            indices.sort_by(|a, b| {
+                let estimate = |idx: &usize| {
+                    self.latency_estimates
+                        .get(&servers[*idx].id)
+                        .copied()
+                        .unwrap_or(0.0)
+                };
+                estimate(a).total_cmp(&estimate(b))
+            });
+        }
+        indices
+    }
+
+    fn observe(&mut self, event: &SystemEvent) {
+        if let SystemEvent::RequestProcessed {
+            server_id,
+            assigned_at: Some(assigned_at),
+            ..
+        } = event
+        {
+            let latency = clock::since(*assigned_at).as_millis() as f64;
+
+            self.latency_estimates
+                .entry(*server_id)
+                .and_modify(|estimate| *estimate += BANDIT_ALPHA * (latency - *estimate))
+                .or_insert(latency);
+        }
+    }
+}
+
+enum Weighting {
+    Static(Vec<f64>),
+    InverseWorkload,
+}
+
+struct WeightedRandom(Weighting);
+
+impl WeightedRandom {
+    fn weight(&self, idx: usize, server: &ServerState) -> f64 {
+        match &self.0 {
+            Weighting::Static(weights) => weights.get(idx).copied().unwrap_or(1.0),
+            Weighting::InverseWorkload => 1.0 / (server.total_workload + 1) as f64,
+        }
+    }
+}
+
+impl Strategy for WeightedRandom {
+    fn choose(&mut self, servers: &[ServerState], _: &Request, rng: &mut StdRng) -> Vec<usize> {
+        // Weighted sampling without replacement: sorting by u^(1/w) gives each
+        // server the first slot with probability proportional to its weight.
+        let mut servers_by_key: Vec<(usize, f64)> = servers
+            .iter()
+            .enumerate()
+            .map(|(idx, server)| {
+                let weight = self.weight(idx, server).max(f64::MIN_POSITIVE);
+                (idx, rng.random::<f64>().powf(1.0 / weight))
+            })
+            .collect();
+
+        servers_by_key.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        servers_by_key.into_iter().map(|(idx, _)| idx).collect()
+    }
+}
+
+/// Prefers servers in the request's zone, least loaded first, and only falls
+/// back to other zones when every local queue is full.
+struct ZoneAware;
+
+impl Strategy for ZoneAware {
+    fn choose(&mut self, servers: &[ServerState], request: &Request, _: &mut StdRng) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..servers.len()).collect();
+
+        indices.sort_by_key(|idx| {
+            let server = &servers[*idx];
+            let remote = request.zone.is_some() && server.zone != request.zone;
+            (remote, server.total_workload)
+        });
+        indices
+    }
+}
+
+/// Like Smaller Queue, but also accounts for the time left on the request
+/// being processed, so a long request mid-flight keeps new work away.
+struct LeastOutstanding;
+
+impl Strategy for LeastOutstanding {
+    fn choose(&mut self, servers: &[ServerState], _: &Request, _: &mut StdRng) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..servers.len()).collect();
+
+        indices.sort_by_key(|idx| {
+            let server = &servers[*idx];
+            (server.remaining_workload(), server.queue.len())
+        });
+        indices
+    }
+}
+
+/// Picks the cheapest server whose expected response time stays within
+/// `COST_LATENCY_BOUND`, falling back to the fastest ones otherwise.
+struct CostBased;
+
+impl Strategy for CostBased {
+    fn choose(&mut self, servers: &[ServerState], request: &Request, _: &mut StdRng) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..servers.len()).collect();
+
+        indices.sort_by(|a, b| {
+            let key = |idx: &usize| {
+                let server = &servers[*idx];
+                let latency = server.remaining_workload() + request.get_time();
+                let cost = server.cost_per_ms * request.get_time() as f64;
+
+                if latency <= COST_LATENCY_BOUND {
+                    (false, cost, latency)
+                } else {
+                    (true, latency as f64, latency)
+                }
+            };
+
+            let (a_over, a_rank, a_latency) = key(a);
+            let (b_over, b_rank, b_latency) = key(b);
+            a_over
+                .cmp(&b_over)
+                .then(a_rank.total_cmp(&b_rank))
+                .then(a_latency.cmp(&b_latency))
+        });
+        indices
+    }
+}
+
+/// Prefers servers whose profile suits the request type, least loaded first,
+/// so CPU-bound requests go to CPU-optimized servers while they have room.
+struct TypeAware;
+
+impl Strategy for TypeAware {
+    fn choose(&mut self, servers: &[ServerState], request: &Request, _: &mut StdRng) -> Vec<usize> {
+        let suited = ServerProfile::suited_to(request.kind);
+        let mut indices: Vec<usize> = (0..servers.len()).collect();
+
+        indices.sort_by_key(|idx| {
+            let server = &servers[*idx];
+            (server.profile != suited, server.remaining_workload())
+        });
+        indices
+    }
+}
+
+/// Places requests only where they fit in memory, then balances the
+/// outstanding work, so a server is never pushed into running out of memory
+/// while another has room.
+struct MemoryAware;
+
+impl Strategy for MemoryAware {
+    fn choose(&mut self, servers: &[ServerState], request: &Request, _: &mut StdRng) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..servers.len()).collect();
+
+        indices.sort_by_key(|idx| {
+            let server = &servers[*idx];
+            let overflows = server.memory_used() + request.memory > server.memory;
+            (overflows, server.remaining_workload())
+        });
+        indices
+    }
+}
+
+/// Keeps each session on the server that got its previous request, and places
+/// new sessions and requests without one on the server with the least
+/// outstanding work. A session moves when its server's queue is full.
+#[derive(Default)]
+struct StickySession {
+    session_servers: HashMap<u64, u64>,
+}
+
+impl Strategy for StickySession {
+    fn choose(&mut self, servers: &[ServerState], request: &Request, _: &mut StdRng) -> Vec<usize> {
+        let sticky_id = request
+            .session_id
+            .and_then(|session_id| self.session_servers.get(&session_id));
+
+        let mut indices: Vec<usize> = (0..servers.len()).collect();
+        indices.sort_by_key(|idx| {
+            let server = &servers[*idx];
+            (Some(&server.id) != sticky_id, server.remaining_workload())
+        });
+        indices
+    }
+
+    fn observe(&mut self, event: &SystemEvent) {
+        match event {
+            SystemEvent::RequestAssigned { server_id, request } => {
+                if let Some(session_id) = request.session_id {
+                    self.session_servers.insert(session_id, *server_id);
+                }
+            }
+            SystemEvent::ServerRemoved { server_id } => {
+                self.session_servers.retain(|_, id| id != server_id);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use std::fmt;
+use std::fs;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::SystemState;
+use crate::clock;
+use crate::window::window;
+
+/// Results of a run, printed on exit and optionally saved as JSON with
+/// `--summary`. Everything but the configuration covers the run since the
+/// end of the warm-up, if any.
+#[derive(Serialize)]
+pub struct Summary {
+    config: RunConfig,
+    /// Seconds the statistics cover.
+    duration_s: f64,
+    created: usize,
+    processed: usize,
+    throughput: f64,
+    response_time_ms: ResponseTimeSummary,
+    drops: Drops,
+    servers: Vec<ServerSummary>,
+}
+
+/// Configuration at the end of the run.
+#[derive(Serialize)]
+struct RunConfig {
+    policy: String,
+    arrival_rate: f32,
+    servers: usize,
+    /// Toggled features that were on.
+    features: Vec<&'static str>,
+    warmup_s: Option<f64>,
+    window_s: f64,
+}
+
+#[derive(Serialize)]
+struct ResponseTimeSummary {
+    average: f64,
+    /// Half-width of the 95% confidence interval of the average.
+    margin_of_error: Option<f64>,
+    p50: Option<u64>,
+    p90: Option<u64>,
+    p99: Option<u64>,
+    p999: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct Drops {
+    rejected: usize,
+    expired: usize,
+    failed: usize,
+    /// Share of the requests that left the system without being processed,
+    /// in percent.
+    rate: f64,
+}
+
+/// Requests a server handled, including the ones it no longer runs.
+#[derive(Serialize)]
+struct ServerSummary {
+    id: u64,
+    processed: usize,
+    failed: usize,
+    expired: usize,
+    average_ms: f64,
+    /// Over its latest response times.
+    p99_ms: Option<f64>,
+}
+
+impl Summary {
+    pub fn new(state: &SystemState, warmup: Option<Duration>) -> Self {
+        state.context.enter(|| Self::summarize(state, warmup))
+    }
+
+    fn summarize(state: &SystemState, warmup: Option<Duration>) -> Self {
+        let config = &state.configs;
+        let stats = &state.stats;
+        let duration_s = clock::since(stats.started_at).as_secs_f64();
+
+        let features = [
+            ("Admission Control", config.admission_control),
+            ("Large Anti-Affinity", config.anti_affinity),
+            ("Autoscaling", config.autoscaling),
+            ("Contention", config.contention),
+            ("Stalls", config.stalls),
+            ("SRPT Preemption", config.preemption),
+            ("Retries", config.retries),
+            ("Bursts", config.bursts),
+            ("Sessions", config.sessions),
+            ("Batching", config.batching),
+            ("Fork-Join", config.forks),
+            ("Pipelines", config.pipelines),
+            ("Network", config.network),
+            ("Dependencies", config.dependencies),
+            ("Deduplication", config.deduplication),
+            ("Warm Cache", config.caching),
+            ("Fair Queueing", config.fair_queueing),
+        ]
+        .into_iter()
+        .filter(|(_, on)| *on)
+        .map(|(name, _)| name)
+        .collect();
+
+        let policy = match config.delegate_mode {
+            Some(delegate_mode) => format!("{} ({})", config.choice_mode, delegate_mode),
+            None => config.choice_mode.to_string(),
+        };
+
+        let dropped = stats.rejected_requests + stats.expired_requests + stats.failed_requests;
+        let resolved = stats.processed_requests + dropped;
+
+        let mut servers: Vec<ServerSummary> = stats
+            .servers
+            .iter()
+            .map(|(id, server)| ServerSummary {
+                id: *id,
+                processed: server.response_times.count,
+                failed: server.failed,
+                expired: server.expired,
+                average_ms: server.response_times.average(),
+                p99_ms: server.percentile(0.99),
+            })
+            .collect();
+        servers.sort_by_key(|server| server.id);
+
+        Self {
+            config: RunConfig {
+                policy,
+                arrival_rate: config.arrival_rate,
+                servers: state.servers.len(),
+                features,
+                warmup_s: warmup.map(|warmup| warmup.as_secs_f64()),
+                window_s: window().as_secs_f64(),
+            },
+            duration_s,
+            created: stats.total_requests,
+            processed: stats.processed_requests,
+            throughput: if duration_s > 0.0 {
+                stats.processed_requests as f64 / duration_s
+            } else {
+                0.0
+            },
+            response_time_ms: ResponseTimeSummary {
+                average: stats.wait_times.average(),
+                margin_of_error: stats.wait_times.margin_of_error(),
+                p50: stats.latencies.percentile(0.5),
+                p90: stats.latencies.percentile(0.9),
+                p99: stats.latencies.percentile(0.99),
+                p999: stats.latencies.percentile(0.999),
+            },
+            drops: Drops {
+                rejected: stats.rejected_requests,
+                expired: stats.expired_requests,
+                failed: stats.failed_requests,
+                rate: if resolved == 0 {
+                    0.0
+                } else {
+                    dropped as f64 / resolved as f64 * 100.0
+                },
+            },
+            servers,
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, json + "\n").map_err(|e| format!("cannot write {path}: {e}"))
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let config = &self.config;
+        let millis = |value: Option<u64>| value.map_or("-".to_string(), |ms| format!("{ms}ms"));
+
+        writeln!(
+            f,
+            "Policy: {}, {:.1} req/sec, {} servers",
+            config.policy, config.arrival_rate, config.servers
+        )?;
+        writeln!(
+            f,
+            "Features: {}",
+            if config.features.is_empty() {
+                "none".to_string()
+            } else {
+                config.features.join(", ")
+            }
+        )?;
+        match config.warmup_s {
+            Some(warmup) => writeln!(
+                f,
+                "Measured over {:.1}s after a {:.0}s warm-up, {:.0}s window",
+                self.duration_s, warmup, config.window_s
+            )?,
+            None => writeln!(
+                f,
+                "Measured over {:.1}s, {:.0}s window",
+                self.duration_s, config.window_s
+            )?,
+        }
+
+        let response_time = &self.response_time_ms;
+        writeln!(
+            f,
+            "Requests: {} created, {} processed, {:.2} req/sec",
+            self.created, self.processed, self.throughput
+        )?;
+        writeln!(
+            f,
+            "Response Time: {:.1}ms{} avg, p50 {}, p90 {}, p99 {}, p99.9 {}",
+            response_time.average,
+            response_time
+                .margin_of_error
+                .map_or(String::new(), |margin| format!(" ± {margin:.1}ms")),
+            millis(response_time.p50),
+            millis(response_time.p90),
+            millis(response_time.p99),
+            millis(response_time.p999)
+        )?;
+        writeln!(
+            f,
+            "Drops: {:.1}% ({} rejected, {} expired, {} failed)",
+            self.drops.rate, self.drops.rejected, self.drops.expired, self.drops.failed
+        )?;
+
+        for server in &self.servers {
+            writeln!(
+                f,
+                "Server {}: {} processed, {} failed, {} expired, {:.1}ms avg, p99 {}",
+                server.id,
+                server.processed,
+                server.failed,
+                server.expired,
+                server.average_ms,
+                server
+                    .p99_ms
+                    .map_or("-".to_string(), |ms| format!("{ms:.0}ms"))
+            )?;
+        }
+        Ok(())
+    }
+}
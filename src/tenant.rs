@@ -0,0 +1,99 @@
+use std::collections::VecDeque;
+
+use crate::request::Request;
+use crate::window::SlidingWindow;
+
+pub struct Tenant {
+    pub name: &'static str,
+    /// Share of the servers' work the tenant is entitled to under fair
+    /// queueing, relative to the other tenants.
+    pub weight: f64,
+    /// Share of the generated requests coming from the tenant.
+    pub traffic: f64,
+}
+
+/// Tenants the generated requests come from; `acme` is the noisy one.
+pub const TENANTS: [Tenant; 3] = [
+    Tenant {
+        name: "acme",
+        weight: 1.0,
+        traffic: 0.7,
+    },
+    Tenant {
+        name: "globex",
+        weight: 1.0,
+        traffic: 0.2,
+    },
+    Tenant {
+        name: "initech",
+        weight: 2.0,
+        traffic: 0.1,
+    },
+];
+
+/// Weighted fair queueing over the tenants: the next request comes from the
+/// waiting tenant that got the least work relative to its weight.
+#[derive(Default)]
+pub struct FairQueue {
+    /// Work (ms) assigned to each tenant, divided by its weight.
+    served: [f64; TENANTS.len()],
+    /// Normalized work of the tenant picked last. A tenant that had nothing
+    /// waiting catches up to it, so it can't bank credit while idle.
+    virtual_time: f64,
+}
+
+impl FairQueue {
+    /// Index of the next request to assign among the eligible ones: the
+    /// first of the tenant furthest behind its fair share.
+    pub fn pick(
+        &mut self,
+        requests: &VecDeque<Request>,
+        eligible: impl Fn(&Request) -> bool,
+    ) -> Option<usize> {
+        let mut backlogged = [false; TENANTS.len()];
+        for request in requests.iter().filter(|request| eligible(request)) {
+            backlogged[request.tenant] = true;
+        }
+
+        for (served, _) in self.served.iter_mut().zip(backlogged).filter(|(_, b)| *b) {
+            *served = served.max(self.virtual_time);
+        }
+
+        let tenant = (0..TENANTS.len())
+            .filter(|tenant| backlogged[*tenant])
+            .min_by(|a, b| self.served[*a].total_cmp(&self.served[*b]))?;
+        self.virtual_time = self.served[tenant];
+
+        requests
+            .iter()
+            .position(|request| request.tenant == tenant && eligible(request))
+    }
+
+    /// Charges an assigned request's work to its tenant.
+    pub fn charge(&mut self, request: &Request) {
+        self.served[request.tenant] += request.get_time() as f64 / TENANTS[request.tenant].weight;
+    }
+}
+
+/// When requests of each tenant finished over the shared window.
+#[derive(Default)]
+pub struct TenantTracker {
+    completions: SlidingWindow<usize>,
+}
+
+impl TenantTracker {
+    pub fn record(&mut self, tenant: usize) {
+        self.completions.push(tenant);
+    }
+
+    /// Requests of the tenant processed per second over the window.
+    pub fn throughput(&self, tenant: usize) -> f64 {
+        let count = self
+            .completions
+            .iter()
+            .filter(|(_, t)| **t == tenant)
+            .count();
+
+        count as f64 / self.completions.span().as_secs_f64()
+    }
+}
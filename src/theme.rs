@@ -0,0 +1,259 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ratatui::style::{Color, Modifier, Style};
+
+use load_balancer::server::MAX_SERVERS;
+
+/// Styles the UI draws with, by what they mean rather than by color, so the
+/// whole UI can switch themes at once.
+pub struct Theme {
+    pub name: &'static str,
+    /// Full queues, failures, the slowest class and missed objectives.
+    pub error: Style,
+    /// Preempted requests and old pending requests.
+    pub warning: Style,
+    /// Requests being processed, met objectives and features that are on.
+    pub good: Style,
+    /// The arrival rate and the latency charts.
+    pub info: Style,
+    /// Blocked, in-transit and low priority requests and features that are off.
+    pub muted: Style,
+    pub high_priority: Style,
+    pub paused: Style,
+    pub stalled: Style,
+    pub heading: Style,
+    /// The `PAUSED` badge.
+    pub alert: Style,
+    /// The policy badge.
+    pub badge: Style,
+    pub status_bar: Style,
+    /// Heatmap cells, from the smallest share to the largest.
+    pub heat: [Style; 5],
+    /// Lines of the servers in the queue history charts, in order.
+    pub servers: [Color; MAX_SERVERS],
+    /// Whether full queues and the requests being processed are also marked
+    /// with text, for those who can't tell the colors apart.
+    pub cues: bool,
+}
+
+const fn fg(color: Color) -> Style {
+    Style::new().fg(color)
+}
+
+const fn on(color: Color) -> Style {
+    Style::new().fg(Color::Black).bg(color)
+}
+
+/// For terminals with a dark background.
+const DARK: Theme = Theme {
+    name: "dark",
+    error: fg(Color::Red),
+    warning: fg(Color::Yellow),
+    good: fg(Color::Green),
+    info: fg(Color::Cyan),
+    muted: fg(Color::DarkGray),
+    high_priority: fg(Color::Magenta),
+    paused: fg(Color::Blue),
+    stalled: fg(Color::LightRed),
+    heading: fg(Color::Yellow).add_modifier(Modifier::BOLD),
+    alert: fg(Color::White).bg(Color::Red),
+    badge: on(Color::Yellow),
+    status_bar: Style::new().bg(Color::DarkGray),
+    heat: [
+        on(Color::Blue),
+        on(Color::Green),
+        on(Color::Yellow),
+        on(Color::LightRed),
+        on(Color::Red),
+    ],
+    servers: [
+        Color::Cyan,
+        Color::Yellow,
+        Color::Magenta,
+        Color::Green,
+        Color::Red,
+        Color::Blue,
+        Color::White,
+        Color::LightRed,
+    ],
+    cues: false,
+};
+
+/// For terminals with a light background, with darker colors in place of
+/// the ones that don't show on white.
+const LIGHT: Theme = Theme {
+    name: "light",
+    error: fg(Color::Red),
+    // Dark orange.
+    warning: fg(Color::Indexed(130)),
+    good: fg(Color::Green),
+    info: fg(Color::Blue),
+    muted: fg(Color::DarkGray),
+    high_priority: fg(Color::Magenta),
+    // Dark cyan.
+    paused: fg(Color::Indexed(30)),
+    stalled: fg(Color::LightRed),
+    heading: fg(Color::Blue).add_modifier(Modifier::BOLD),
+    alert: fg(Color::White).bg(Color::Red),
+    badge: fg(Color::White).bg(Color::Blue),
+    status_bar: Style::new().fg(Color::Black).bg(Color::Gray),
+    heat: [
+        on(Color::LightBlue),
+        on(Color::LightGreen),
+        on(Color::LightYellow),
+        on(Color::LightRed),
+        on(Color::Red),
+    ],
+    servers: [
+        Color::Blue,
+        Color::Red,
+        Color::Green,
+        Color::Magenta,
+        Color::Indexed(130),
+        Color::Cyan,
+        Color::Black,
+        Color::DarkGray,
+    ],
+    cues: false,
+};
+
+/// No colors at all, only bold, dim, italic and reversed text.
+const MONOCHROME: Theme = Theme {
+    name: "monochrome",
+    error: Style::new().add_modifier(Modifier::BOLD),
+    warning: Style::new().add_modifier(Modifier::UNDERLINED),
+    good: Style::new().add_modifier(Modifier::BOLD),
+    info: Style::new(),
+    muted: Style::new().add_modifier(Modifier::DIM),
+    high_priority: Style::new().add_modifier(Modifier::BOLD),
+    paused: Style::new().add_modifier(Modifier::ITALIC),
+    stalled: Style::new().add_modifier(Modifier::DIM.union(Modifier::ITALIC)),
+    heading: Style::new().add_modifier(Modifier::BOLD.union(Modifier::UNDERLINED)),
+    alert: Style::new().add_modifier(Modifier::REVERSED),
+    badge: Style::new().add_modifier(Modifier::REVERSED),
+    status_bar: Style::new(),
+    heat: [
+        Style::new().add_modifier(Modifier::DIM),
+        Style::new(),
+        Style::new().add_modifier(Modifier::BOLD),
+        Style::new().add_modifier(Modifier::REVERSED),
+        Style::new().add_modifier(Modifier::REVERSED.union(Modifier::BOLD)),
+    ],
+    servers: [Color::Reset; MAX_SERVERS],
+    cues: true,
+};
+
+// Colors of the Okabe-Ito palette, told apart with any kind of color vision.
+const ORANGE: Color = Color::Rgb(230, 159, 0);
+const SKY_BLUE: Color = Color::Rgb(86, 180, 233);
+const BLUISH_GREEN: Color = Color::Rgb(0, 158, 115);
+const YELLOW: Color = Color::Rgb(240, 228, 66);
+const BLUE: Color = Color::Rgb(0, 114, 178);
+const VERMILLION: Color = Color::Rgb(213, 94, 0);
+const REDDISH_PURPLE: Color = Color::Rgb(204, 121, 167);
+
+/// For red-green color blindness (deuteranopia and protanopia): blue and
+/// orange in place of green and red, with bold text and marks for what
+/// matters most.
+const DEUTERANOPIA: Theme = Theme {
+    name: "deuteranopia",
+    error: fg(VERMILLION).add_modifier(Modifier::BOLD),
+    warning: fg(YELLOW),
+    good: fg(SKY_BLUE).add_modifier(Modifier::BOLD),
+    info: fg(Color::White),
+    muted: fg(Color::DarkGray),
+    high_priority: fg(REDDISH_PURPLE).add_modifier(Modifier::BOLD),
+    paused: fg(BLUE),
+    stalled: fg(ORANGE).add_modifier(Modifier::ITALIC),
+    heading: fg(YELLOW).add_modifier(Modifier::BOLD),
+    alert: fg(Color::White).bg(VERMILLION),
+    badge: on(SKY_BLUE),
+    status_bar: Style::new().bg(Color::DarkGray),
+    heat: [
+        on(SKY_BLUE),
+        on(Color::Gray),
+        on(YELLOW),
+        on(ORANGE),
+        on(VERMILLION),
+    ],
+    servers: [
+        ORANGE,
+        SKY_BLUE,
+        BLUISH_GREEN,
+        YELLOW,
+        BLUE,
+        VERMILLION,
+        REDDISH_PURPLE,
+        Color::White,
+    ],
+    cues: true,
+};
+
+/// For blue-yellow color blindness (tritanopia): red and cyan, which stay
+/// apart without blue cones, with bold text and marks for what matters most.
+const TRITANOPIA: Theme = Theme {
+    name: "tritanopia",
+    error: fg(Color::Red).add_modifier(Modifier::BOLD),
+    warning: fg(Color::LightMagenta),
+    good: fg(Color::Cyan).add_modifier(Modifier::BOLD),
+    info: fg(Color::White),
+    muted: fg(Color::DarkGray),
+    high_priority: fg(Color::Magenta).add_modifier(Modifier::BOLD),
+    // Teal.
+    paused: fg(Color::Rgb(0, 150, 150)),
+    stalled: fg(Color::LightRed).add_modifier(Modifier::ITALIC),
+    heading: fg(Color::Cyan).add_modifier(Modifier::BOLD),
+    alert: fg(Color::White).bg(Color::Red),
+    badge: on(Color::Cyan),
+    status_bar: Style::new().bg(Color::DarkGray),
+    heat: [
+        on(Color::Rgb(160, 230, 230)),
+        on(Color::Gray),
+        on(Color::Rgb(255, 180, 190)),
+        on(Color::LightRed),
+        on(Color::Red),
+    ],
+    servers: [
+        Color::Red,
+        Color::Cyan,
+        Color::White,
+        Color::Magenta,
+        Color::Rgb(0, 150, 150),
+        Color::LightRed,
+        Color::Gray,
+        Color::LightMagenta,
+    ],
+    cues: true,
+};
+
+pub static THEMES: [Theme; 5] = [DARK, LIGHT, MONOCHROME, DEUTERANOPIA, TRITANOPIA];
+
+static SELECTED_THEME: AtomicUsize = AtomicUsize::new(0);
+
+pub fn theme() -> &'static Theme {
+    &THEMES[SELECTED_THEME.load(Ordering::SeqCst)]
+}
+
+/// Switches to the theme with the given name.
+pub fn set_theme(name: &str) -> Result<(), String> {
+    let idx = THEMES
+        .iter()
+        .position(|theme| theme.name == name)
+        .ok_or_else(|| {
+            let names: Vec<_> = THEMES.iter().map(|theme| theme.name).collect();
+            format!(
+                "unknown theme '{name}', expected one of {}",
+                names.join(", ")
+            )
+        })?;
+    SELECTED_THEME.store(idx, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Switches to the theme after the current one, back to the first after the
+/// last.
+pub fn next_theme() -> &'static Theme {
+    let idx = (SELECTED_THEME.load(Ordering::SeqCst) + 1) % THEMES.len();
+    SELECTED_THEME.store(idx, Ordering::SeqCst);
+    &THEMES[idx]
+}
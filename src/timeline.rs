@@ -0,0 +1,166 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::time::Instant;
+
+use crate::clock;
+use crate::request::{RequestSize, RequestType};
+
+/// Entries kept in memory; older ones are dropped.
+const TIMELINE_LIMIT: usize = 100_000;
+
+/// When a request went through each phase on a server. Pipeline requests get
+/// an entry per stage.
+pub struct TimelineEntry {
+    pub request_id: usize,
+    pub server_id: u64,
+    /// Unknown if the server no longer held the request.
+    pub class: Option<(RequestType, RequestSize)>,
+    pub created_at: Instant,
+    pub assigned_at: Option<Instant>,
+    pub started_at: Option<Instant>,
+    pub finished_at: Instant,
+    pub failed: bool,
+}
+
+/// Timestamps of every processed request, to be rendered as a Gantt chart.
+pub struct Timeline {
+    started_at: Instant,
+    entries: VecDeque<TimelineEntry>,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self {
+            started_at: clock::now(),
+            entries: VecDeque::new(),
+        }
+    }
+}
+
+impl Timeline {
+    pub fn record(&mut self, entry: TimelineEntry) {
+        if self.entries.len() == TIMELINE_LIMIT {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// Writes an entry per line, with times in ms since the start of the run;
+    /// phases a request skipped and unknown classes are left empty.
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let optional =
+            |at: Option<Instant>| at.map(|at| self.offset(at).to_string()).unwrap_or_default();
+
+        let mut lines = vec![
+            "# request_id,server_id,created_ms,assigned_ms,started_ms,finished_ms,outcome,type,size"
+                .to_string(),
+        ];
+        for entry in &self.entries {
+            let (kind, size) = match entry.class {
+                Some((kind, size)) => (format!("{kind:?}"), format!("{size:?}")),
+                None => Default::default(),
+            };
+            lines.push(format!(
+                "{},{},{},{},{},{},{},{kind},{size}",
+                entry.request_id,
+                entry.server_id,
+                self.offset(entry.created_at),
+                optional(entry.assigned_at),
+                optional(entry.started_at),
+                self.offset(entry.finished_at),
+                entry.outcome()
+            ));
+        }
+
+        fs::write(path, lines.join("\n") + "\n").map_err(|e| format!("cannot write {path}: {e}"))
+    }
+
+    /// Writes the entries as a Parquet file with the same columns as `save`,
+    /// for analysis in pandas or polars.
+    #[cfg(feature = "parquet")]
+    pub fn save_parquet(&self, path: &str) -> Result<(), String> {
+        use std::sync::Arc;
+
+        use arrow_array::{ArrayRef, Int64Array, RecordBatch, StringArray, UInt64Array};
+        use parquet::arrow::ArrowWriter;
+
+        let error = |e: &dyn std::fmt::Display| format!("cannot write {path}: {e}");
+        let millis = |at: Instant| Some(self.offset(at) as i64);
+        let class = |name: fn(&(RequestType, RequestSize)) -> String| {
+            StringArray::from_iter(
+                self.entries
+                    .iter()
+                    .map(|entry| entry.class.as_ref().map(name)),
+            )
+        };
+
+        let columns: Vec<(&str, ArrayRef)> = vec![
+            (
+                "request_id",
+                Arc::new(UInt64Array::from_iter_values(
+                    self.entries.iter().map(|entry| entry.request_id as u64),
+                )),
+            ),
+            (
+                "server_id",
+                Arc::new(UInt64Array::from_iter_values(
+                    self.entries.iter().map(|entry| entry.server_id),
+                )),
+            ),
+            (
+                "created_ms",
+                Arc::new(Int64Array::from_iter(
+                    self.entries.iter().map(|entry| millis(entry.created_at)),
+                )),
+            ),
+            (
+                "assigned_ms",
+                Arc::new(Int64Array::from_iter(
+                    self.entries
+                        .iter()
+                        .map(|entry| entry.assigned_at.and_then(millis)),
+                )),
+            ),
+            (
+                "started_ms",
+                Arc::new(Int64Array::from_iter(
+                    self.entries
+                        .iter()
+                        .map(|entry| entry.started_at.and_then(millis)),
+                )),
+            ),
+            (
+                "finished_ms",
+                Arc::new(Int64Array::from_iter(
+                    self.entries.iter().map(|entry| millis(entry.finished_at)),
+                )),
+            ),
+            (
+                "outcome",
+                Arc::new(StringArray::from_iter_values(
+                    self.entries.iter().map(TimelineEntry::outcome),
+                )),
+            ),
+            ("type", Arc::new(class(|(kind, _)| format!("{kind:?}")))),
+            ("size", Arc::new(class(|(_, size)| format!("{size:?}")))),
+        ];
+
+        let batch = RecordBatch::try_from_iter(columns).map_err(|e| error(&e))?;
+        let file = fs::File::create(path).map_err(|e| error(&e))?;
+        let mut writer = ArrowWriter::try_new(file, batch.schema(), None).map_err(|e| error(&e))?;
+        writer.write(&batch).map_err(|e| error(&e))?;
+        writer.close().map_err(|e| error(&e))?;
+        Ok(())
+    }
+
+    /// Milliseconds since the start of the run.
+    fn offset(&self, at: Instant) -> u128 {
+        at.saturating_duration_since(self.started_at).as_millis()
+    }
+}
+
+impl TimelineEntry {
+    fn outcome(&self) -> &'static str {
+        if self.failed { "failed" } else { "succeeded" }
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::time::{Duration, Instant};
+
+use crate::clock;
+use crate::server::ServerState;
+
+/// How often the servers are sampled.
+pub const SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+/// Samples kept in memory, an hour's worth; older ones are dropped.
+const SAMPLE_LIMIT: usize = 7200;
+
+/// (seconds, value) points of a server's series.
+pub type Points = Vec<(f64, f64)>;
+
+/// Queue length and remaining workload (ms) of each server at a point in
+/// time, as seconds since the start of the run.
+struct Sample {
+    at: f64,
+    servers: Vec<(u64, usize, u64)>,
+}
+
+/// Queue lengths and workloads of the servers, sampled every
+/// `SAMPLE_INTERVAL`.
+pub struct QueueHistory {
+    started_at: Instant,
+    sampled_at: Option<Instant>,
+    samples: VecDeque<Sample>,
+}
+
+impl Default for QueueHistory {
+    fn default() -> Self {
+        Self {
+            started_at: clock::now(),
+            sampled_at: None,
+            samples: VecDeque::new(),
+        }
+    }
+}
+
+impl QueueHistory {
+    /// Takes a sample if the last one is `SAMPLE_INTERVAL` old.
+    pub fn sample(&mut self, servers: &[ServerState]) {
+        if self
+            .sampled_at
+            .is_some_and(|at| clock::since(at) < SAMPLE_INTERVAL)
+        {
+            return;
+        }
+        self.sampled_at = Some(clock::now());
+
+        if self.samples.len() == SAMPLE_LIMIT {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(Sample {
+            at: clock::since(self.started_at).as_secs_f64(),
+            servers: servers
+                .iter()
+                .map(|server| (server.id, server.queue.len(), server.remaining_workload()))
+                .collect(),
+        });
+    }
+
+    /// Seconds since the start of the run of the latest sample.
+    pub fn latest(&self) -> f64 {
+        self.samples.back().map_or(0.0, |sample| sample.at)
+    }
+
+    /// Queue lengths and workloads of the server over the samples taken since
+    /// `since`.
+    pub fn series(&self, server_id: u64, since: f64) -> (Points, Points) {
+        self.samples
+            .iter()
+            .filter(|sample| sample.at >= since)
+            .filter_map(|sample| {
+                let (_, queue, workload) =
+                    sample.servers.iter().find(|(id, _, _)| *id == server_id)?;
+                Some(((sample.at, *queue as f64), (sample.at, *workload as f64)))
+            })
+            .unzip()
+    }
+
+    /// Writes every sample, one line per server.
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let mut lines = vec!["# time_s,server,queue,workload_ms".to_string()];
+        for sample in &self.samples {
+            for (id, queue, workload) in &sample.servers {
+                lines.push(format!("{:.1},{id},{queue},{workload}", sample.at));
+            }
+        }
+
+        fs::write(path, lines.join("\n") + "\n").map_err(|e| format!("cannot write {path}: {e}"))
+    }
+}
@@ -0,0 +1,114 @@
+use std::fs;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::clock;
+use crate::request::{Request, RequestSize, RequestType};
+
+/// A request arrival recorded in a trace file, one per line as
+/// `offset_ms,type,size[,id[,time_ms]]`, e.g. `1250,CPUsBound,Large,4821337,1000`.
+pub struct TraceEntry {
+    /// Arrival time since the start of the run.
+    pub offset: Duration,
+    pub id: usize,
+    pub kind: RequestType,
+    pub size: RequestSize,
+    pub time: Option<u64>,
+}
+
+impl TraceEntry {
+    /// The recorded request, arriving now.
+    pub fn to_request(&self) -> Request {
+        Request::from_trace(self.id, self.kind, self.size, self.time)
+    }
+}
+
+/// Reads a trace, sorted by arrival. Lines starting with `#` are ignored and
+/// requests without an id are numbered by their line.
+pub fn load(path: &str) -> Result<Vec<TraceEntry>, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("cannot read {path}: {e}"))?;
+    let mut entries = Vec::new();
+
+    for (line_idx, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let error = |message: String| format!("{path}:{}: {message}", line_idx + 1);
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+
+        let [offset, kind, size, rest @ ..] = fields.as_slice() else {
+            return Err(error(
+                "expected offset_ms,type,size[,id[,time_ms]]".to_string(),
+            ));
+        };
+        let offset: u64 = offset
+            .parse()
+            .map_err(|_| error(format!("invalid offset '{offset}'")))?;
+        let kind: RequestType = kind.parse().map_err(error)?;
+        let size: RequestSize = size.parse().map_err(error)?;
+        let id = match rest.first() {
+            Some(id) => id
+                .parse()
+                .map_err(|_| error(format!("invalid id '{id}'")))?,
+            None => line_idx + 1,
+        };
+        let time = match rest.get(1) {
+            Some(time) => Some(
+                time.parse()
+                    .map_err(|_| error(format!("invalid time '{time}'")))?,
+            ),
+            None => None,
+        };
+
+        entries.push(TraceEntry {
+            offset: Duration::from_millis(offset),
+            id,
+            kind,
+            size,
+            time,
+        });
+    }
+
+    entries.sort_by_key(|entry| entry.offset);
+    Ok(entries)
+}
+
+/// Collects the generated requests in the trace format, to be written out when
+/// the simulation ends.
+pub(crate) struct TraceRecorder {
+    started: Instant,
+    lines: Mutex<Vec<String>>,
+}
+
+impl TraceRecorder {
+    pub fn new() -> Self {
+        Self {
+            started: clock::now(),
+            lines: Mutex::new(vec!["# offset_ms,type,size,id,time_ms".to_string()]),
+        }
+    }
+
+    pub fn record(&self, request: &Request) {
+        let offset = request.created_at.duration_since(self.started).as_millis();
+        let line = format!(
+            "{},{:?},{:?},{},{}",
+            offset,
+            request.kind,
+            request.size,
+            request.id,
+            request.get_time()
+        );
+
+        if let Ok(mut lines) = self.lines.lock() {
+            lines.push(line);
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let lines = self.lines.lock().map_err(|e| e.to_string())?;
+
+        fs::write(path, lines.join("\n") + "\n").map_err(|e| format!("cannot write {path}: {e}"))
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::VecDeque;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use crate::{clock, context};
+
+/// Span of the rolling metrics unless set with `--window`.
+pub const DEFAULT_WINDOW: Duration = Duration::from_secs(10);
+
+/// Span shared by the rolling metrics: throughput, utilization and tenant
+/// throughput.
+pub fn window() -> Duration {
+    context::with(|context| Duration::from_millis(context.window_ms.load(Ordering::SeqCst)))
+}
+
+pub fn set_window(window: Duration) {
+    context::with(|context| {
+        context
+            .window_ms
+            .store(window.as_millis() as u64, Ordering::SeqCst)
+    });
+}
+
+/// Values recorded over a sliding span of time, the shared `window()` unless
+/// given another one.
+pub struct SlidingWindow<T> {
+    samples: VecDeque<(Instant, T)>,
+    span: Option<Duration>,
+}
+
+impl<T> Default for SlidingWindow<T> {
+    fn default() -> Self {
+        Self {
+            samples: VecDeque::new(),
+            span: None,
+        }
+    }
+}
+
+impl<T> SlidingWindow<T> {
+    pub fn new(span: Duration) -> Self {
+        Self {
+            samples: VecDeque::new(),
+            span: Some(span),
+        }
+    }
+
+    pub fn span(&self) -> Duration {
+        self.span.unwrap_or_else(window)
+    }
+
+    pub fn push(&mut self, value: T) {
+        let now = clock::now();
+        let span = self.span();
+        while self
+            .samples
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) > span)
+        {
+            self.samples.pop_front();
+        }
+
+        self.samples.push_back((now, value));
+    }
+
+    /// Values recorded over the span, oldest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (Instant, &T)> {
+        self.recent(self.span())
+    }
+
+    /// Values recorded over the last `span`, at most the window's own.
+    pub fn recent(&self, span: Duration) -> impl DoubleEndedIterator<Item = (Instant, &T)> {
+        self.samples
+            .iter()
+            .filter(move |(at, _)| clock::since(*at) <= span)
+            .map(|(at, value)| (*at, value))
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
@@ -0,0 +1,106 @@
+use std::time::Duration;
+
+use load_balancer::alert::AlertRules;
+use load_balancer::request::Request;
+use load_balancer::server::ServerState;
+use load_balancer::strategy::Strategy;
+use load_balancer::{ConfigUpdate, Simulation, SystemEvent, SystemState, clock, window};
+use rand::rngs::StdRng;
+
+/// Runs a simulation without the UI until it has processed a request.
+#[tokio::test]
+async fn processes_requests() {
+    let mut simulation = Simulation::builder().seed(42).speed(10.0).build();
+    let mut state = SystemState::new(&simulation, None, AlertRules::default());
+
+    let processed = tokio::time::timeout(Duration::from_secs(10), async {
+        while let Some(event) = simulation.next_event().await {
+            state.apply(event);
+            if state.stats().processed_requests() > 0 {
+                return true;
+            }
+        }
+        false
+    })
+    .await;
+    simulation.stop();
+
+    assert_eq!(processed, Ok(true));
+    assert!(state.stats().total_requests() > 0);
+}
+
+/// Runs two simulations side by side, neither changing the other's settings.
+#[tokio::test]
+async fn keeps_simulations_apart() {
+    let fast = Simulation::builder()
+        .speed(10.0)
+        .window(Duration::from_secs(5))
+        .build();
+    let slow = Simulation::builder().speed(0.5).build();
+
+    assert_eq!(fast.context().enter(clock::speed), 10.0);
+    assert_eq!(fast.context().enter(window::window), Duration::from_secs(5));
+    assert_eq!(slow.context().enter(clock::speed), 0.5);
+    assert_eq!(slow.context().enter(window::window), window::DEFAULT_WINDOW);
+
+    fast.stop();
+    slow.stop();
+}
+
+/// Always picks the first server.
+struct FirstServer;
+
+impl Strategy for FirstServer {
+    fn choose(&mut self, _: &[ServerState], _: &Request, _: &mut StdRng) -> Vec<usize> {
+        vec![0]
+    }
+}
+
+/// Balances with a strategy registered from outside the crate.
+#[tokio::test]
+async fn balances_with_a_registered_strategy() {
+    let mut simulation = Simulation::builder()
+        .seed(42)
+        .speed(10.0)
+        .strategy("First Server", |_| Box::new(FirstServer))
+        .build();
+    let mode = simulation.choice_mode("First Server");
+    assert!(mode.is_some());
+    simulation
+        .sender()
+        .send(SystemEvent::ConfigChanged(ConfigUpdate {
+            choice_mode: mode,
+            ..Default::default()
+        }))
+        .await
+        .unwrap();
+
+    let assigned = tokio::time::timeout(Duration::from_secs(10), async {
+        let mut changed = false;
+        let mut assigned = Vec::new();
+        while let Some(event) = simulation.next_event().await {
+            match event {
+                SystemEvent::ConfigChanged(update) if update.choice_mode.is_some() => {
+                    changed = true
+                }
+                SystemEvent::RequestAssigned { server_id, .. } if changed => {
+                    assigned.push(server_id);
+                    if assigned.len() == 10 {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        assigned
+    })
+    .await;
+    simulation.stop();
+
+    // The first few may have been picked before the change reached the
+    // allocator.
+    assert_eq!(
+        assigned.map(|assigned| assigned[5..].to_vec()),
+        Ok(vec![1; 5])
+    );
+}